@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use nix_installed_list::{CachePackages, Profile, ProfileError};
+
+/// how long the details of an installed package
+/// are trusted before they're fetched again.
+pub const PROFILE_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// how long the versions nixhub.io knows about
+/// a package are trusted before scraping again.
+pub const VERSION_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// the directory gnix keeps its caches in, this follows
+/// the XDG base directory spec, so it's usually
+/// ~/.cache/gnix unless XDG_CACHE_HOME says otherwise.
+pub fn cache_dir() -> PathBuf {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".cache"),
+    };
+    base.join("gnix")
+}
+
+/// make sure the cache directory (and its parents) exist.
+pub fn setup_cache_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)
+}
+
+/// where the versions nixhub.io knows about each package are kept.
+pub fn version_cache_path() -> PathBuf {
+    cache_dir().join("version-cache.json")
+}
+
+/// where the details of the packages installed in a profile
+/// are kept between runs of `gnix list`.
+pub fn profile_cache_path(profile: &Profile) -> PathBuf {
+    cache_dir().join(profile.cache_file_name())
+}
+
+/// what `gnix gc --cache` cleaned up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcSummary {
+    /// how many entries were removed, the whole cache is removed
+    /// without reading it, so it's `None` then.
+    pub removed: Option<usize>,
+    /// roughly how much space the removed entries took up,
+    /// estimated from the size of them as JSON.
+    pub freed_bytes: usize,
+}
+
+fn json_size(cache: &CachePackages) -> usize {
+    cache
+        .packages
+        .iter()
+        .map(|package| serde_json::to_string(package).map_or(0, |json| json.len()))
+        .sum()
+}
+
+/// remove the entries in the cache at `path` which are older than
+/// `older_than`, or the whole cache if it's `None`. if there's no
+/// cache to clean up, the result is `None`.
+pub fn gc_cache(
+    path: &Path,
+    older_than: Option<Duration>,
+) -> Result<Option<GcSummary>, ProfileError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let Some(age) = older_than else {
+        // a cache which won't parse is when this is needed
+        // most, so it's removed without being read.
+        let freed_bytes = std::fs::metadata(path)?.len() as usize;
+        std::fs::remove_file(path)?;
+        return Ok(Some(GcSummary {
+            removed: None,
+            freed_bytes,
+        }));
+    };
+
+    let mut cache = CachePackages::load(path)?;
+    let before = json_size(&cache);
+    let removed = cache.evict_expired(age);
+    cache.save(path)?;
+    Ok(Some(GcSummary {
+        removed: Some(removed),
+        freed_bytes: before - json_size(&cache),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::SystemTime;
+
+    use nix_installed_list::CachePackage;
+
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn write_cache(path: &Path) {
+        let now = SystemTime::now();
+        let cache = CachePackages {
+            packages: [("ripgrep", now), ("gleam", now - 10 * DAY)]
+                .into_iter()
+                .map(|(name, cached_at)| CachePackage {
+                    name: name.to_owned(),
+                    attr_path: format!("legacyPackages.x86_64-linux.{name}"),
+                    cached_at: Some(cached_at.into()),
+                    ..Default::default()
+                })
+                .collect(),
+        };
+        cache.save(path).unwrap();
+    }
+
+    #[test]
+    fn test_gc_older_than() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile-installed.toml");
+        write_cache(&path);
+
+        let summary = gc_cache(&path, Some(7 * DAY)).unwrap().unwrap();
+        assert_eq!(summary.removed, Some(1));
+        assert!(summary.freed_bytes > 0);
+
+        let cache = CachePackages::load(&path).unwrap();
+        assert_eq!(cache.packages.len(), 1);
+        assert_eq!(cache.packages[0].name, "ripgrep");
+    }
+
+    #[test]
+    fn test_gc_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile-installed.toml");
+        write_cache(&path);
+
+        let size = std::fs::metadata(&path).unwrap().len() as usize;
+        let summary = gc_cache(&path, None).unwrap().unwrap();
+        assert_eq!(summary.removed, None);
+        assert_eq!(summary.freed_bytes, size);
+        assert!(!path.exists());
+
+        // there's nothing left to clean up the second time.
+        assert_eq!(gc_cache(&path, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_gc_corrupt_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile-installed.toml");
+        std::fs::write(&path, "not a cache").unwrap();
+
+        assert!(gc_cache(&path, Some(7 * DAY)).is_err());
+        assert!(gc_cache(&path, None).unwrap().is_some());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,50 @@
+use std::ffi::OsStr;
+use std::io::Write;
+
+use clap::CommandFactory;
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::Shell;
+use nix_installed_list::Profile;
+
+use crate::Cli;
+
+/// write a completion script for `shell`, such as with
+/// `gnix completions bash >> ~/.bashrc`, or with
+/// `gnix completions fish > ~/.config/fish/completions/gnix.fish`
+pub fn write_completions(shell: Shell, out: &mut impl Write) {
+    let mut cli = Cli::command();
+    clap_complete::generate(shell, &mut cli, "gnix", out);
+}
+
+/// the names of the installed packages which start with what's been
+/// typed so far, this runs while completing, so failures are silent.
+pub fn installed_package_names(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+    let Ok(manifest) = Profile::default().manifest_parsed() else {
+        return Vec::new();
+    };
+    let mut names = manifest
+        .elements
+        .packages
+        .into_keys()
+        .filter(|name| name.starts_with(current))
+        .collect::<Vec<_>>();
+    names.sort();
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bash_completions() {
+        let mut out = Vec::new();
+        write_completions(Shell::Bash, &mut out);
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("gnix"));
+        assert!(script.contains("list-channels"));
+    }
+}
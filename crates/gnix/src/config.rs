@@ -0,0 +1,329 @@
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use toml_edit::{DocumentMut, Item, Value};
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("IO error: {source}")]
+    Io {
+        #[from]
+        source: std::io::Error,
+    },
+    #[error("failed to parse config file: {source}")]
+    Toml {
+        #[from]
+        source: toml_edit::TomlError,
+    },
+    #[error("unknown config key `{key}`, known keys are: {}", known_keys())]
+    UnknownKey { key: String },
+    #[error("`{value}` is not a valid value for `{key}`, expected {expected}")]
+    InvalidValue {
+        key: String,
+        value: String,
+        expected: &'static str,
+    },
+    #[error("`{key}` cannot be set, because `{parent}` is not a table")]
+    NotATable { key: String, parent: String },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigValueKind {
+    Integer,
+    String,
+}
+
+impl ConfigValueKind {
+    fn describe(self) -> &'static str {
+        match self {
+            ConfigValueKind::Integer => "a non-negative integer",
+            ConfigValueKind::String => "a string",
+        }
+    }
+
+    fn parse(self, key: &str, raw: &str) -> Result<Value, ConfigError> {
+        let invalid = || ConfigError::InvalidValue {
+            key: key.to_owned(),
+            value: raw.to_owned(),
+            expected: self.describe(),
+        };
+        match self {
+            ConfigValueKind::Integer => {
+                let n: u32 = raw.trim().parse().map_err(|_| invalid())?;
+                Ok(Value::from(i64::from(n)))
+            }
+            ConfigValueKind::String => Ok(Value::from(raw)),
+        }
+    }
+
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            ConfigValueKind::Integer => value.is_integer(),
+            ConfigValueKind::String => value.is_str(),
+        }
+    }
+}
+
+/// a setting which gnix understands, settings are
+/// addressed by a dot separated path into the file.
+pub struct ConfigKey {
+    pub key: &'static str,
+    pub kind: ConfigValueKind,
+    pub default: &'static str,
+    pub description: &'static str,
+}
+
+pub const CONFIG_KEYS: &[ConfigKey] = &[
+    ConfigKey {
+        key: "search.default_limit",
+        kind: ConfigValueKind::Integer,
+        default: "10",
+        description: "how many results `gnix search` shows",
+    },
+    ConfigKey {
+        key: "search.default_channel",
+        kind: ConfigValueKind::String,
+        default: "unstable",
+        description: "the nixpkgs channel to search when none is given",
+    },
+    ConfigKey {
+        key: "elasticsearch.url",
+        kind: ConfigValueKind::String,
+        default: "https://search.nixos.org/backend",
+        description: "the search.nixos.org backend to query",
+    },
+];
+
+fn known_keys() -> String {
+    CONFIG_KEYS
+        .iter()
+        .map(|k| k.key)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn lookup_key(key: &str) -> Result<&'static ConfigKey, ConfigError> {
+    CONFIG_KEYS
+        .iter()
+        .find(|k| k.key == key)
+        .ok_or_else(|| ConfigError::UnknownKey {
+            key: key.to_owned(),
+        })
+}
+
+/// where the config file lives, usually ~/.config/gnix/config.toml
+pub fn config_path() -> PathBuf {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".config"),
+    };
+    base.join("gnix").join("config.toml")
+}
+
+/// the config file is parsed with toml_edit, so that writing
+/// it back out keeps the user's comments and formatting.
+pub struct GnixConfig {
+    path: PathBuf,
+    document: DocumentMut,
+}
+
+impl GnixConfig {
+    /// load the config at `path`, a missing file is
+    /// treated the same as an empty one.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            document: text.parse()?,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), ConfigError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, self.document.to_string())?;
+        Ok(())
+    }
+
+    fn raw(&self, key: &str) -> Option<&Value> {
+        let mut item = self.document.as_item();
+        for part in key.split('.') {
+            item = item.get(part)?;
+        }
+        item.as_value()
+    }
+
+    /// the current value of a setting, falling back to its
+    /// default when it isn't set (or is set to the wrong type).
+    pub fn get(&self, key: &str) -> Result<String, ConfigError> {
+        let config_key = lookup_key(key)?;
+        Ok(match self.raw(key) {
+            Some(value) if config_key.kind.matches(value) => display_value(value),
+            _ => config_key.default.to_owned(),
+        })
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let config_key = lookup_key(key)?;
+        let value = config_key.kind.parse(key, value)?;
+
+        let (parents, leaf) = key.rsplit_once('.').unwrap_or(("", key));
+
+        // indexing into an item creates any missing tables on the
+        // way, but it would panic on anything else, so check first.
+        let mut item = self.document.as_item_mut();
+        for part in parents.split('.').filter(|p| !p.is_empty()) {
+            item = &mut item[part];
+            if !(item.is_none() || item.is_table_like()) {
+                return Err(ConfigError::NotATable {
+                    key: key.to_owned(),
+                    parent: part.to_owned(),
+                });
+            }
+        }
+        item[leaf] = Item::Value(value);
+        Ok(())
+    }
+
+    /// every known setting, with its current value and
+    /// whether or not that value is just the default.
+    pub fn list(&self) -> Vec<(&'static ConfigKey, String, bool)> {
+        CONFIG_KEYS
+            .iter()
+            .map(|k| match self.raw(k.key) {
+                Some(value) if k.kind.matches(value) => (k, display_value(value), false),
+                _ => (k, k.default.to_owned(), true),
+            })
+            .collect()
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value.as_str() {
+        Some(s) => s.to_owned(),
+        None => value.to_string().trim().to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_config(contents: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gnix").join("config.toml");
+        if !contents.is_empty() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, contents).unwrap();
+        }
+        (dir, path)
+    }
+
+    #[test]
+    fn test_get_defaults_when_missing() {
+        let (_dir, path) = temp_config("");
+        let config = GnixConfig::load(&path).unwrap();
+        assert_eq!(config.get("search.default_limit").unwrap(), "10");
+        assert_eq!(
+            config.get("elasticsearch.url").unwrap(),
+            "https://search.nixos.org/backend"
+        );
+    }
+
+    #[test]
+    fn test_get_reads_file() {
+        let (_dir, path) = temp_config("[search]\ndefault_limit = 25\n");
+        let config = GnixConfig::load(&path).unwrap();
+        assert_eq!(config.get("search.default_limit").unwrap(), "25");
+    }
+
+    #[test]
+    fn test_set_preserves_comments() {
+        let (_dir, path) =
+            temp_config("# my gnix settings\n[search]\n# show lots\ndefault_limit = 25\n");
+        let mut config = GnixConfig::load(&path).unwrap();
+        config.set("search.default_limit", "50").unwrap();
+        config
+            .set("elasticsearch.url", "http://localhost:9200")
+            .unwrap();
+        config.save().unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("# my gnix settings"));
+        assert!(written.contains("# show lots"));
+        assert!(written.contains("default_limit = 50"));
+
+        let config = GnixConfig::load(&path).unwrap();
+        assert_eq!(config.get("search.default_limit").unwrap(), "50");
+        assert_eq!(
+            config.get("elasticsearch.url").unwrap(),
+            "http://localhost:9200"
+        );
+    }
+
+    #[test]
+    fn test_set_creates_file() {
+        let (_dir, path) = temp_config("");
+        let mut config = GnixConfig::load(&path).unwrap();
+        config.set("search.default_channel", "24.05").unwrap();
+        config.save().unwrap();
+
+        let config = GnixConfig::load(&path).unwrap();
+        assert_eq!(config.get("search.default_channel").unwrap(), "24.05");
+    }
+
+    #[test]
+    fn test_set_rejects_bad_input() {
+        let (_dir, path) = temp_config("");
+        let mut config = GnixConfig::load(&path).unwrap();
+        assert!(matches!(
+            config.set("search.nonsense", "1"),
+            Err(ConfigError::UnknownKey { .. })
+        ));
+        assert!(matches!(
+            config.set("search.default_limit", "lots"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            config.set("search.default_limit", "-1"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_refuses_to_clobber_non_tables() {
+        let (_dir, path) = temp_config("search = 5\n");
+        let mut config = GnixConfig::load(&path).unwrap();
+        assert!(matches!(
+            config.set("search.default_limit", "5"),
+            Err(ConfigError::NotATable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_list() {
+        let (_dir, path) = temp_config("[search]\ndefault_limit = 3\n");
+        let config = GnixConfig::load(&path).unwrap();
+        let listed = config.list();
+        assert_eq!(listed.len(), CONFIG_KEYS.len());
+
+        let (_, limit, limit_is_default) = listed
+            .iter()
+            .find(|(k, _, _)| k.key == "search.default_limit")
+            .unwrap();
+        assert_eq!(limit, "3");
+        assert!(!limit_is_default);
+
+        let (_, url, url_is_default) = listed
+            .iter()
+            .find(|(k, _, _)| k.key == "elasticsearch.url")
+            .unwrap();
+        assert_eq!(url, "https://search.nixos.org/backend");
+        assert!(url_is_default);
+    }
+}
@@ -0,0 +1,266 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use color_eyre::owo_colors::OwoColorize;
+
+use crate::cache;
+
+/// a remediation for a failed check, it should leave the
+/// system in a state where the check passes.
+pub type DoctorFix = Box<dyn Fn() -> Result<(), String>>;
+
+/// a single diagnostic which `gnix doctor` runs.
+pub trait DoctorCheck {
+    /// a short description of what is being checked.
+    fn name(&self) -> &str;
+
+    /// run the check, on failure, the error
+    /// describes what went wrong.
+    fn check(&self) -> Result<(), String>;
+
+    /// a description of what `fix` will do, shown to
+    /// the user before they confirm it.
+    fn fix_description(&self) -> Option<String> {
+        None
+    }
+
+    /// the known remediation for this check, if there is one.
+    fn fix(&self) -> Option<DoctorFix> {
+        None
+    }
+}
+
+/// the profile scripts which put nix on the PATH,
+/// which one exists depends on how nix was installed.
+const NIX_PROFILE_SCRIPTS: [&str; 2] = [
+    "/etc/profile.d/nix.sh",
+    "/nix/var/nix/profiles/default/etc/profile.d/nix-daemon.sh",
+];
+
+pub struct NixInPath;
+
+impl DoctorCheck for NixInPath {
+    fn name(&self) -> &str {
+        "nix binary is in PATH"
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        if std::env::split_paths(&path).any(|dir| dir.join("nix").is_file()) {
+            Ok(())
+        } else {
+            Err("nix binary not found in PATH".to_owned())
+        }
+    }
+
+    fn fix_description(&self) -> Option<String> {
+        let script = nix_profile_script()?;
+        Some(format!(
+            "start a new shell which has sourced {}",
+            script.display()
+        ))
+    }
+
+    fn fix(&self) -> Option<DoctorFix> {
+        let script = nix_profile_script()?;
+        Some(Box::new(move || source_in_new_shell(&script)))
+    }
+}
+
+fn nix_profile_script() -> Option<PathBuf> {
+    NIX_PROFILE_SCRIPTS
+        .iter()
+        .map(PathBuf::from)
+        .find(|script| script.is_file())
+}
+
+/// we can't change the environment of the shell which
+/// invoked us, so the best we can do is replace ourselves
+/// with a fresh shell which has nix set up.
+#[cfg(unix)]
+fn source_in_new_shell(script: &Path) -> Result<(), String> {
+    use std::os::unix::process::CommandExt;
+
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_owned());
+    let err = std::process::Command::new("/bin/sh")
+        .arg("-c")
+        .arg(format!(". '{}' && exec \"$0\"", script.display()))
+        .arg(shell)
+        .exec();
+    Err(format!("failed to start a new shell: {err}"))
+}
+
+#[cfg(not(unix))]
+fn source_in_new_shell(script: &Path) -> Result<(), String> {
+    Err(format!("run `source {}` in your shell", script.display()))
+}
+
+pub struct CacheDirExists {
+    pub dir: PathBuf,
+}
+
+impl Default for CacheDirExists {
+    fn default() -> Self {
+        Self {
+            dir: cache::cache_dir(),
+        }
+    }
+}
+
+impl DoctorCheck for CacheDirExists {
+    fn name(&self) -> &str {
+        "gnix cache directory exists"
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.dir.is_dir() {
+            Ok(())
+        } else {
+            Err(format!("gnix cache dir missing: {}", self.dir.display()))
+        }
+    }
+
+    fn fix_description(&self) -> Option<String> {
+        Some(format!("create {}", self.dir.display()))
+    }
+
+    fn fix(&self) -> Option<DoctorFix> {
+        let dir = self.dir.clone();
+        Some(Box::new(move || {
+            cache::setup_cache_dir(&dir).map_err(|e| e.to_string())
+        }))
+    }
+}
+
+pub struct ProfileManifestValid;
+
+impl DoctorCheck for ProfileManifestValid {
+    fn name(&self) -> &str {
+        "profile manifest is valid JSON"
+    }
+
+    fn check(&self) -> Result<(), String> {
+        nix_installed_list::manifest_parsed()
+            .map(|_| ())
+            .map_err(|e| format!("profile manifest invalid JSON: {e}"))
+    }
+
+    fn fix_description(&self) -> Option<String> {
+        Some("reset the profile history with `nix profile wipe-history`".to_owned())
+    }
+
+    fn fix(&self) -> Option<DoctorFix> {
+        Some(Box::new(|| {
+            let status = nix_installed_list::nix()
+                .arg("profile")
+                .arg("wipe-history")
+                .status()
+                .map_err(|e| e.to_string())?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(format!("nix profile wipe-history exited with {status}"))
+            }
+        }))
+    }
+}
+
+pub fn default_checks() -> Vec<Box<dyn DoctorCheck>> {
+    vec![
+        Box::new(NixInPath),
+        Box::new(CacheDirExists::default()),
+        Box::new(ProfileManifestValid),
+    ]
+}
+
+/// apply the fix for a check, and then check again
+/// to make sure that the fix actually worked.
+pub fn fix_and_recheck(check: &dyn DoctorCheck) -> Result<(), String> {
+    let Some(fix) = check.fix() else {
+        return check.check();
+    };
+    fix()?;
+    check.check()
+}
+
+/// ask the user a yes or no question on stdin,
+/// anything other than y or yes is a no.
+pub fn confirm(prompt: &str) -> bool {
+    print!("{prompt} [y/N] ");
+    if std::io::stdout().flush().is_err() {
+        return false;
+    }
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// run every check, printing the results as we go. returns
+/// the number of checks which are still failing at the end.
+pub fn run(checks: &[Box<dyn DoctorCheck>], fix: bool, yes: bool) -> usize {
+    let mut failing = 0;
+    for check in checks {
+        let err = match check.check() {
+            Ok(()) => {
+                println!("{} {}", "✓".green().bold(), check.name());
+                continue;
+            }
+            Err(err) => err,
+        };
+        println!("{} {}", "✗".red().bold(), check.name());
+        println!("  └─ {}", err.italic());
+
+        let description = check.fix_description();
+        let fixable = fix && check.fix().is_some();
+        if !fixable {
+            failing += 1;
+            continue;
+        }
+        let description = description.unwrap_or_else(|| "attempt a fix".to_owned());
+        if !yes && !confirm(&format!("  {description}?")) {
+            failing += 1;
+            continue;
+        }
+        match fix_and_recheck(check.as_ref()) {
+            Ok(()) => println!("  {} fixed", "✓".green().bold()),
+            Err(err) => {
+                failing += 1;
+                println!("  {} fix failed: {}", "✗".red().bold(), err.italic());
+            }
+        }
+    }
+    failing
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cache_dir_fix_and_recheck() {
+        let tmp = tempfile::tempdir().unwrap();
+        let check = CacheDirExists {
+            dir: tmp.path().join("nested").join("gnix"),
+        };
+
+        assert!(check.check().is_err());
+        assert!(check.fix().is_some());
+        fix_and_recheck(&check).unwrap();
+        assert!(check.check().is_ok());
+        assert!(check.dir.is_dir());
+    }
+
+    #[test]
+    fn test_fix_failure_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        // a file where the directory should be can't be fixed
+        // by creating the directory, so the fix must fail.
+        let blocker = tmp.path().join("gnix");
+        std::fs::write(&blocker, "").unwrap();
+        let check = CacheDirExists { dir: blocker };
+
+        assert!(fix_and_recheck(&check).is_err());
+    }
+}
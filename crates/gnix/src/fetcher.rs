@@ -0,0 +1,127 @@
+/// let people paste a link to a repository rather
+/// than having to know nix's fetcher syntax.
+pub fn package_prefix_map(src: &str) -> String {
+    if let Some(rest) = src.strip_prefix("https://github.com/") {
+        return format!("github:{}", owner_repo_ref(rest, "tree"));
+    }
+    if let Some(rest) = src.strip_prefix("https://gitlab.com/") {
+        // gitlab puts a `-` before the tree, as in owner/repo/-/tree/main
+        return format!(
+            "gitlab:{}",
+            owner_repo_ref(&rest.replacen("/-/", "/", 1), "tree")
+        );
+    }
+    if src.starts_with("https://codeberg.org/") {
+        return format!("git+{}", src.trim_end_matches('/'));
+    }
+    if src.starts_with("git://") {
+        return format!("git+{src}");
+    }
+    src.to_owned()
+}
+
+/// `owner/repo`, or `owner/repo/branch` when the path continues
+/// on to `/<tree>/<branch>`, anything else after the repo is dropped.
+fn owner_repo_ref(path: &str, tree: &str) -> String {
+    let mut parts = path.trim_end_matches('/').splitn(4, '/');
+    let (Some(owner), Some(repo)) = (parts.next(), parts.next()) else {
+        return path.trim_end_matches('/').to_owned();
+    };
+    let repo = repo.trim_end_matches(".git");
+    match (parts.next(), parts.next()) {
+        (Some(kind), Some(branch)) if kind == tree && !branch.is_empty() => {
+            format!("{owner}/{repo}/{branch}")
+        }
+        _ => format!("{owner}/{repo}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_github() {
+        assert_eq!(
+            package_prefix_map("https://github.com/andystopia/newt"),
+            "github:andystopia/newt"
+        );
+        assert_eq!(
+            package_prefix_map("https://github.com/andystopia/newt/"),
+            "github:andystopia/newt"
+        );
+        assert_eq!(
+            package_prefix_map("https://github.com/andystopia/newt.git"),
+            "github:andystopia/newt"
+        );
+        assert_eq!(
+            package_prefix_map("github:andystopia/newt"),
+            "github:andystopia/newt"
+        );
+    }
+
+    #[test]
+    fn test_github_branch() {
+        assert_eq!(
+            package_prefix_map("https://github.com/andystopia/newt/tree/dev"),
+            "github:andystopia/newt/dev"
+        );
+        assert_eq!(
+            package_prefix_map("https://github.com/andystopia/newt/tree/dev/"),
+            "github:andystopia/newt/dev"
+        );
+        // a link to something other than a branch is just the repo
+        assert_eq!(
+            package_prefix_map("https://github.com/andystopia/newt/issues/12"),
+            "github:andystopia/newt"
+        );
+        assert_eq!(
+            package_prefix_map("https://github.com/andystopia/newt/tree/"),
+            "github:andystopia/newt"
+        );
+    }
+
+    #[test]
+    fn test_gitlab() {
+        assert_eq!(
+            package_prefix_map("https://gitlab.com/owner/repo"),
+            "gitlab:owner/repo"
+        );
+        assert_eq!(
+            package_prefix_map("https://gitlab.com/owner/repo/-/tree/main"),
+            "gitlab:owner/repo/main"
+        );
+    }
+
+    #[test]
+    fn test_codeberg() {
+        assert_eq!(
+            package_prefix_map("https://codeberg.org/owner/repo"),
+            "git+https://codeberg.org/owner/repo"
+        );
+        assert_eq!(
+            package_prefix_map("https://codeberg.org/owner/repo/"),
+            "git+https://codeberg.org/owner/repo"
+        );
+    }
+
+    #[test]
+    fn test_git_protocol() {
+        assert_eq!(
+            package_prefix_map("git://example.org/repo.git"),
+            "git+git://example.org/repo.git"
+        );
+    }
+
+    #[test]
+    fn test_left_alone() {
+        for src in [
+            "nixpkgs#ripgrep",
+            "git+https://example.org/repo",
+            "path:/home/user/flake",
+            "https://example.org/flake.tar.gz",
+        ] {
+            assert_eq!(package_prefix_map(src), src);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::owo_colors::OwoColorize;
+use nix_elastic_search::response::NixPackage;
+use nix_elastic_search::{MatchName, Query, SearchWithin};
+use nix_installed_list::{Package, Profile, Root};
+
+use crate::install;
+
+const NONE: &str = "<none>";
+
+/// look up a package by its exact name, if more than one package has the
+/// name, the one whose attr name matches exactly wins, then the top result.
+pub fn query_package_info(package: &str, channel: &str) -> color_eyre::Result<NixPackage> {
+    let query = Query {
+        max_results: 10,
+        search_within: SearchWithin::Channel(channel.to_owned()),
+        search: None,
+        program: None,
+        name: Some(MatchName {
+            name: package.to_owned(),
+        }),
+        version: None,
+        query_string: None,
+    };
+    let mut packages = query
+        .send()
+        .map_err(|e| eyre!("failed to look up `{package}`: {e}"))?;
+    if packages.is_empty() {
+        bail!("there's no package named `{package}` in {channel}");
+    }
+    let top = packages
+        .iter()
+        .position(|p| p.package_attr_name == package)
+        .unwrap_or(0);
+    Ok(packages.swap_remove(top))
+}
+
+fn or_none(values: Vec<String>) -> String {
+    if values.is_empty() {
+        NONE.to_owned()
+    } else {
+        values.join(", ")
+    }
+}
+
+/// each detail of a package, along with its label, with `spdx`
+/// the SPDX id of each license we know follows it in parentheses.
+pub fn info_fields(package: &NixPackage, spdx: bool) -> Vec<(&'static str, String)> {
+    let text = |text: &Option<String>| match text.as_deref().map(str::trim) {
+        Some(text) if !text.is_empty() => text.to_owned(),
+        _ => NONE.to_owned(),
+    };
+    let version = if package.package_pversion.is_empty() {
+        NONE.to_owned()
+    } else {
+        package.package_pversion.clone()
+    };
+    let maintainers = package
+        .package_maintainers
+        .iter()
+        .filter_map(|m| match (&m.name, &m.github) {
+            (Some(name), Some(github)) => Some(format!("{name} (@{github})")),
+            (Some(name), None) => Some(name.clone()),
+            (None, Some(github)) => Some(format!("@{github}")),
+            (None, None) => m.email.clone(),
+        })
+        .collect();
+
+    vec![
+        ("name", package.package_attr_name.clone()),
+        ("version", version),
+        ("description", text(&package.package_description)),
+        ("long description", text(&package.package_long_description)),
+        ("homepage", or_none(package.package_homepage.clone())),
+        (
+            "license",
+            or_none(
+                package
+                    .package_license
+                    .iter()
+                    .map(|l| match nix_search_sort_utils::spdx_id(l) {
+                        Some(id) if spdx => format!("{} ({id})", l.full_name),
+                        _ => l.full_name.clone(),
+                    })
+                    .collect(),
+            ),
+        ),
+        ("maintainers", or_none(maintainers)),
+        ("platforms", or_none(package.package_platforms.clone())),
+        ("outputs", or_none(package.package_outputs.clone())),
+    ]
+}
+
+pub fn print_info(package: &NixPackage, channel: &str, spdx: bool) {
+    let fields = info_fields(package, spdx);
+    let width = fields
+        .iter()
+        .map(|(label, _)| label.len())
+        .max()
+        .unwrap_or(0);
+    for (label, value) in &fields {
+        println!("{:>width$}  {value}", label.bold());
+    }
+    println!();
+    println!("to install it, run:");
+    println!(
+        "  nix profile install {}",
+        install::installable(&package.package_attr_name, Some(channel))
+    );
+}
+
+/// the package in the profile with this attr name, packages
+/// from nixpkgs are installed as legacyPackages.<system>.<attr>.
+pub fn find_installed<'a>(root: &'a Root, attr_name: &str) -> Option<&'a Package> {
+    root.elements.packages.values().find(|package| {
+        match package.attr_path.splitn(3, '.').collect::<Vec<_>>()[..] {
+            ["legacyPackages", _, attr] => attr == attr_name,
+            _ => package.attr_path == attr_name,
+        }
+    })
+}
+
+pub fn format_outputs(outputs: &[(String, String)]) -> String {
+    let width = outputs
+        .iter()
+        .map(|(output, _)| output.len())
+        .max()
+        .unwrap_or(0);
+    outputs
+        .iter()
+        .map(|(output, store_path)| format!("  {output:<width$}  {store_path}\n"))
+        .collect()
+}
+
+/// if the package is installed, show where each of its outputs
+/// are, a profile we can't read just means there's nothing to show.
+pub fn print_installed_outputs(attr_name: &str) {
+    let Ok(root) = Profile::default().manifest_parsed() else {
+        return;
+    };
+    let Some(installed) = find_installed(&root, attr_name) else {
+        return;
+    };
+    println!();
+    match installed.output_paths() {
+        Ok(outputs) => {
+            println!("installed outputs:");
+            print!("{}", format_outputs(&outputs));
+        }
+        Err(e) => eprintln!("couldn't find the installed outputs: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nix_elastic_search::response::{PackageLicense, PackageMaintainer};
+
+    use super::*;
+
+    fn field<'a>(fields: &'a [(&str, String)], label: &str) -> &'a str {
+        &fields.iter().find(|(l, _)| *l == label).unwrap().1
+    }
+
+    #[test]
+    fn test_info_fields() {
+        let package = NixPackage {
+            package_attr_name: "ripgrep".to_owned(),
+            package_pversion: "14.1.1".to_owned(),
+            package_description: Some("A search tool".to_owned()),
+            package_homepage: vec!["https://github.com/BurntSushi/ripgrep".to_owned()],
+            package_license: vec![
+                PackageLicense {
+                    full_name: "MIT License".to_owned(),
+                    ..Default::default()
+                },
+                PackageLicense {
+                    full_name: "The Unlicense".to_owned(),
+                    ..Default::default()
+                },
+            ],
+            package_maintainers: vec![PackageMaintainer {
+                name: Some("Someone".to_owned()),
+                github: Some("someone".to_owned()),
+                email: None,
+            }],
+            package_outputs: vec!["out".to_owned()],
+            ..Default::default()
+        };
+        let fields = info_fields(&package, false);
+        assert_eq!(field(&fields, "name"), "ripgrep");
+        assert_eq!(field(&fields, "version"), "14.1.1");
+        assert_eq!(field(&fields, "description"), "A search tool");
+        assert_eq!(field(&fields, "long description"), "<none>");
+        assert_eq!(field(&fields, "license"), "MIT License, The Unlicense");
+        assert_eq!(field(&fields, "maintainers"), "Someone (@someone)");
+        assert_eq!(field(&fields, "platforms"), "<none>");
+        assert_eq!(field(&fields, "outputs"), "out");
+
+        let fields = info_fields(&package, true);
+        assert_eq!(
+            field(&fields, "license"),
+            "MIT License (MIT), The Unlicense (Unlicense)"
+        );
+    }
+
+    #[test]
+    fn test_empty_fields() {
+        let fields = info_fields(
+            &NixPackage {
+                package_attr_name: "mystery".to_owned(),
+                package_description: Some("  ".to_owned()),
+                ..Default::default()
+            },
+            true,
+        );
+        for (label, value) in &fields[1..] {
+            assert_eq!(value, "<none>", "{label}");
+        }
+    }
+
+    #[test]
+    fn test_find_installed() {
+        let mut root = Root::default();
+        for (name, attr_path) in [
+            ("ripgrep", "legacyPackages.x86_64-linux.ripgrep"),
+            (
+                "requests",
+                "legacyPackages.x86_64-linux.python3Packages.requests",
+            ),
+            ("newt", "packages.x86_64-linux.default"),
+        ] {
+            root.elements.packages.insert(
+                name.to_owned(),
+                Package {
+                    attr_path: attr_path.to_owned(),
+                    ..Default::default()
+                },
+            );
+        }
+        let attr_path = |attr_name: &str| {
+            find_installed(&root, attr_name).map(|package| package.attr_path.as_str())
+        };
+        assert_eq!(
+            attr_path("ripgrep"),
+            Some("legacyPackages.x86_64-linux.ripgrep")
+        );
+        assert_eq!(
+            attr_path("python3Packages.requests"),
+            Some("legacyPackages.x86_64-linux.python3Packages.requests")
+        );
+        assert_eq!(attr_path("requests"), None);
+        assert_eq!(attr_path("gleam"), None);
+    }
+
+    #[test]
+    fn test_format_outputs() {
+        let outputs = [
+            ("out".to_owned(), "/nix/store/abc-ripgrep-14.1.0".to_owned()),
+            (
+                "man".to_owned(),
+                "/nix/store/def-ripgrep-14.1.0-man".to_owned(),
+            ),
+            (
+                "devdoc".to_owned(),
+                "/nix/store/ghi-ripgrep-14.1.0-devdoc".to_owned(),
+            ),
+        ];
+        assert_eq!(
+            format_outputs(&outputs),
+            "  out     /nix/store/abc-ripgrep-14.1.0\n  \
+             man     /nix/store/def-ripgrep-14.1.0-man\n  \
+             devdoc  /nix/store/ghi-ripgrep-14.1.0-devdoc\n"
+        );
+    }
+}
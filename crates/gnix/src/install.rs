@@ -0,0 +1,186 @@
+use color_eyre::owo_colors::OwoColorize;
+use nix_installed_list::Profile;
+
+use crate::doctor;
+use crate::fetcher::package_prefix_map;
+
+/// whether an install actually happens, and whether we ask first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DryRunMode {
+    /// only print what would be run.
+    DryRun,
+    /// ask before installing, the default.
+    Confirm,
+    /// install without asking, as with `--yes`.
+    Yes,
+}
+
+impl DryRunMode {
+    pub fn from_flags(dry_run: bool, yes: bool) -> Self {
+        match (dry_run, yes) {
+            (true, _) => DryRunMode::DryRun,
+            (false, true) => DryRunMode::Yes,
+            (false, false) => DryRunMode::Confirm,
+        }
+    }
+}
+
+/// whether `name` is a nix identifier, a letter or underscore
+/// followed by letters, digits, underscores, dashes and quotes.
+fn is_nix_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '\'' | '-'))
+}
+
+/// package names are attribute paths, identifiers separated by
+/// dots, so that nested ones such as python3Packages.requests work.
+pub fn is_valid_package_name(name: &str) -> bool {
+    name.split('.').all(is_nix_identifier)
+}
+
+/// nixpkgs as of the latest commit on a channel, such as 24.05
+pub fn nixpkgs_channel_flake(channel: &str) -> String {
+    format!("github:NixOS/nixpkgs/nixos-{channel}")
+}
+
+/// what to hand to `nix profile install`, a bare attribute name
+/// such as ripgrep comes from nixpkgs, or from the given channel
+/// of nixpkgs, anything else is taken to be a flake reference.
+pub fn installable(src: &str, channel: Option<&str>) -> String {
+    if src.contains([':', '#']) {
+        return package_prefix_map(src);
+    }
+    match channel {
+        Some(channel) => format!("{}#{src}", nixpkgs_channel_flake(channel)),
+        None => format!("nixpkgs#{src}"),
+    }
+}
+
+/// colour a flake reference the same way `gnix list` does.
+pub fn style_src(src: &str) -> String {
+    match src.split_once(':') {
+        Some((fetcher, rest)) => format!(
+            "{} {}",
+            format!("{fetcher}:").purple().bold(),
+            rest.underline()
+        ),
+        None => src.to_owned(),
+    }
+}
+
+pub fn dry_run_message(src: &str) -> String {
+    format!("Would run: nix profile install {}", style_src(src))
+}
+
+pub fn confirm_prompt(src: &str) -> String {
+    format!("Install {}?", style_src(src))
+}
+
+/// whether to go ahead and install `src`, a dry run just prints
+/// what it would have run, and `confirm` asks the user.
+fn should_install(src: &str, mode: DryRunMode, confirm: impl FnOnce(&str) -> bool) -> bool {
+    match mode {
+        DryRunMode::DryRun => {
+            println!("{}", dry_run_message(src));
+            false
+        }
+        DryRunMode::Confirm => confirm(&confirm_prompt(src)),
+        DryRunMode::Yes => {
+            println!("installing {}", style_src(src));
+            true
+        }
+    }
+}
+
+pub fn install_package(src: &str, mode: DryRunMode) -> color_eyre::Result<()> {
+    if !should_install(src, mode, doctor::confirm) {
+        return Ok(());
+    }
+    let status = Profile::default()
+        .nix_profile("install")
+        .arg(src)
+        .status()?;
+    if !status.success() {
+        color_eyre::eyre::bail!("nix profile install exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_valid_package_names() {
+        assert!(is_valid_package_name("cargo"));
+        assert!(is_valid_package_name("python3Packages.requests"));
+        assert!(is_valid_package_name("_1password-gui"));
+        assert!(is_valid_package_name("haskellPackages.lens'"));
+        assert!(!is_valid_package_name("gtk+3"));
+        assert!(!is_valid_package_name("3proxy"));
+        assert!(!is_valid_package_name("'quoted"));
+        assert!(!is_valid_package_name("python3Packages."));
+        assert!(!is_valid_package_name(""));
+        assert!(!is_valid_package_name("cargo; rm -rf /"));
+        assert!(!is_valid_package_name("pkgs\"inject"));
+    }
+
+    #[test]
+    fn test_dry_run_mode_from_flags() {
+        assert_eq!(DryRunMode::from_flags(true, false), DryRunMode::DryRun);
+        assert_eq!(DryRunMode::from_flags(true, true), DryRunMode::DryRun);
+        assert_eq!(DryRunMode::from_flags(false, true), DryRunMode::Yes);
+        assert_eq!(DryRunMode::from_flags(false, false), DryRunMode::Confirm);
+    }
+
+    #[test]
+    fn test_dry_run_message() {
+        assert_eq!(
+            dry_run_message("ripgrep"),
+            "Would run: nix profile install ripgrep"
+        );
+        let message = dry_run_message("nixpkgs#ripgrep");
+        assert!(message.starts_with("Would run: nix profile install "));
+        assert!(message.contains("#ripgrep"));
+    }
+
+    #[test]
+    fn test_should_install() {
+        let never_asked = |_: &str| -> bool { panic!("shouldn't have asked") };
+        assert!(!should_install("ripgrep", DryRunMode::DryRun, never_asked));
+        assert!(should_install("ripgrep", DryRunMode::Yes, never_asked));
+
+        let mut asked = None;
+        let answer = should_install("ripgrep", DryRunMode::Confirm, |prompt| {
+            asked = Some(prompt.to_owned());
+            false
+        });
+        assert!(!answer);
+        assert_eq!(asked.as_deref(), Some("Install ripgrep?"));
+        assert!(should_install("ripgrep", DryRunMode::Confirm, |_| true));
+    }
+
+    #[test]
+    fn test_installable() {
+        assert_eq!(installable("ripgrep", None), "nixpkgs#ripgrep");
+        assert_eq!(
+            installable("ripgrep", Some("24.05")),
+            "github:NixOS/nixpkgs/nixos-24.05#ripgrep"
+        );
+        assert_eq!(
+            installable("ripgrep", Some("unstable")),
+            "github:NixOS/nixpkgs/nixos-unstable#ripgrep"
+        );
+        assert_eq!(
+            installable("https://github.com/andystopia/newt", Some("24.05")),
+            "github:andystopia/newt"
+        );
+        assert_eq!(
+            installable("nixpkgs#python3Packages.requests", None),
+            "nixpkgs#python3Packages.requests"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use color_eyre::owo_colors::OwoColorize;
+use nix_installed_list::{CachePackage, CachePackages};
+
+use crate::{install, wrap};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, ValueEnum)]
+pub enum ListFormat {
+    /// a tree, with each package's description
+    #[default]
+    Table,
+    /// a JSON array
+    Json,
+    /// `[[package]]` tables, the same as the gnix cache
+    Toml,
+}
+
+/// turns the installed packages into text, for `gnix list`
+pub trait PackageOutputFormatter {
+    fn format(&self, packages: &[CachePackage]) -> String;
+}
+
+pub struct JsonFormatter;
+
+impl PackageOutputFormatter for JsonFormatter {
+    fn format(&self, packages: &[CachePackage]) -> String {
+        let mut out =
+            serde_json::to_string_pretty(packages).expect("packages can always be written as JSON");
+        out.push('\n');
+        out
+    }
+}
+
+pub struct TomlFormatter;
+
+impl PackageOutputFormatter for TomlFormatter {
+    fn format(&self, packages: &[CachePackage]) -> String {
+        let cache = CachePackages {
+            packages: packages.to_vec(),
+        };
+        cache
+            .to_toml_string()
+            .expect("packages can always be written as TOML")
+    }
+}
+
+/// draws the packages as a tree, which is what
+/// `gnix list` prints when it's not asked for JSON.
+pub struct PrettyInstalledPackagePrinter {
+    /// how wide descriptions can be before they wrap
+    pub description_width: usize,
+}
+
+impl Default for PrettyInstalledPackagePrinter {
+    fn default() -> Self {
+        // the description is drawn after `│  ├─ `, which
+        // is six columns wide.
+        Self {
+            description_width: wrap::terminal_width().saturating_sub(6),
+        }
+    }
+}
+
+impl PrettyInstalledPackagePrinter {
+    /// a single branch of the tree, the last package
+    /// closes off the tree rather than continuing it.
+    pub fn format_package(&self, package: &CachePackage, last: bool) -> String {
+        let mut out = String::new();
+        let joiner = if last { "└" } else { "├" };
+        let indent = if last { " " } else { "│" };
+
+        let version = match package.version.as_deref() {
+            Some(s) => format!("{}", s.bold().on_blue()),
+            None => format!("{}", "<version unknown>".italic().on_blue()),
+        };
+        writeln!(
+            out,
+            "{joiner}─{}{}{}{}{} ",
+            " ".on_blue(),
+            package.name.bold().on_blue(),
+            " @ ".on_blue(),
+            version,
+            " ".on_blue()
+        )
+        .unwrap();
+        if let Some(description) = package.description() {
+            for (i, line) in wrap::wrap_lines(description, self.description_width)
+                .iter()
+                .enumerate()
+            {
+                let branch = if i == 0 { "├─" } else { "│ " };
+                writeln!(out, "{indent}  {branch} {}", line.white().bold()).unwrap();
+            }
+        }
+        writeln!(
+            out,
+            "{indent}  └─ {}#{}",
+            install::style_src(&package.original_url),
+            package.attr_path.italic()
+        )
+        .unwrap();
+
+        if !last {
+            writeln!(out, "{indent}").unwrap();
+        }
+        out
+    }
+}
+
+impl PackageOutputFormatter for PrettyInstalledPackagePrinter {
+    fn format(&self, packages: &[CachePackage]) -> String {
+        packages
+            .iter()
+            .enumerate()
+            .map(|(i, package)| self.format_package(package, i == packages.len() - 1))
+            .collect()
+    }
+}
+
+impl ListFormat {
+    pub fn formatter(self) -> Box<dyn PackageOutputFormatter> {
+        match self {
+            ListFormat::Table => Box::new(PrettyInstalledPackagePrinter::default()),
+            ListFormat::Json => Box::new(JsonFormatter),
+            ListFormat::Toml => Box::new(TomlFormatter),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn packages() -> Vec<CachePackage> {
+        ["gleam", "ripgrep"]
+            .into_iter()
+            .map(|name| {
+                let mut meta = toml_edit::Table::new();
+                meta.insert(
+                    "description",
+                    toml_edit::value(format!("the {name} package")),
+                );
+                CachePackage {
+                    name: name.to_owned(),
+                    version: Some("1.0.0".to_owned()),
+                    meta,
+                    attr_path: format!("legacyPackages.x86_64-linux.{name}"),
+                    url: "github:NixOS/nixpkgs/abc123".to_owned(),
+                    original_url: "flake:nixpkgs".to_owned(),
+                    enriched: true,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_json_formatter() {
+        let out = JsonFormatter.format(&packages());
+        let parsed: Vec<CachePackage> = serde_json::from_str(&out).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[1].name, "ripgrep");
+        assert_eq!(parsed[1].description(), Some("the ripgrep package"));
+        assert_eq!(JsonFormatter.format(&[]), "[]\n");
+    }
+
+    #[test]
+    fn test_toml_formatter() {
+        let out = TomlFormatter.format(&packages());
+        assert_eq!(out.matches("[[package]]").count(), 2);
+
+        // it's the same format as the cache, so it reads back in.
+        let parsed: CachePackages = toml_edit::de::from_str(&out).unwrap();
+        assert_eq!(parsed.packages[0].name, "gleam");
+        assert_eq!(parsed.packages[0].version.as_deref(), Some("1.0.0"));
+    }
+
+    #[test]
+    fn test_pretty_printer() {
+        let printer = PrettyInstalledPackagePrinter {
+            description_width: 80,
+        };
+        let out = printer.format(&packages());
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[0].starts_with("├─"));
+        assert!(lines[0].contains("gleam"));
+        assert!(lines[1].contains("the gleam package"));
+        assert!(lines[2].contains("legacyPackages.x86_64-linux.gleam"));
+        assert_eq!(lines[3], "│");
+        assert!(lines[4].starts_with("└─"));
+        assert!(lines[4].contains("ripgrep"));
+    }
+}
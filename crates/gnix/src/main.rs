@@ -1,90 +1,794 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::engine::ArgValueCompleter;
 use color_eyre::owo_colors::OwoColorize;
-use nix_installed_list::{get_meta, get_version, manifest_parsed};
+use nix_installed_list::{CachePackages, Profile};
+
+mod cache;
+mod completions;
+mod config;
+mod doctor;
+mod fetcher;
+mod info;
+mod install;
+mod list;
+mod outdated;
+mod output;
+mod pin;
+mod search;
+mod share;
+mod shell_nix;
+mod uninstall;
+mod update;
+mod upgrade;
+mod verify;
+mod wrap;
 
 #[derive(Parser, Debug)]
 pub enum Cli {
     #[clap(about = "List installed packages")]
-    List,
+    List {
+        /// list the packages in this profile, rather
+        /// than in your default profile.
+        #[clap(long)]
+        profile: Option<PathBuf>,
+        /// how to print the packages
+        #[clap(long, value_enum, default_value_t)]
+        format: list::ListFormat,
+        /// print the results as a JSON array,
+        /// the same as `--format json`.
+        #[clap(long, conflicts_with_all = ["json_lines", "format"])]
+        json: bool,
+        /// print one JSON object per line, each line
+        /// is written as soon as it's ready.
+        #[clap(long, visible_alias = "jsonl", conflicts_with = "format")]
+        json_lines: bool,
+    },
 
     /// list available channels from the nixpkgs
     /// repository. this command only shows "fully-fledged"
-    /// distributions, so small, and darwin channels 
-    /// are not displayed. Unstable is assumed to always 
-    /// exist, so it is not printed in this list, only 
+    /// distributions, so small, and darwin channels
+    /// are not displayed. Unstable is assumed to always
+    /// exist, so it is not printed in this list, only
     /// nixos-XX.YY channels are shown.
-    ListChannels { 
+    ListChannels {
         /// the most recent n packages will be shown,
         /// by default, this is 5.
-        n: Option<usize>
-    }
+        n: Option<usize>,
+    },
+
+    /// search nixpkgs for packages, using the same
+    /// backend as search.nixos.org
+    Search {
+        /// the name (or part of the name) of a package
+        package: String,
+        /// the channel to search, such as 24.05 or unstable,
+        /// by default, search.default_channel from the config.
+        #[clap(long)]
+        channel: Option<String>,
+        /// the most results to ask for, by default,
+        /// search.default_limit from the config.
+        #[clap(long, short = 'n', visible_alias = "max")]
+        limit: Option<u32>,
+        /// hide packages which don't support this platform, such
+        /// as aarch64-darwin, or `current` for this machine, those
+        /// which don't list any platforms are kept.
+        #[clap(long)]
+        filter_platform: Option<String>,
+        /// only show packages maintained by this github user
+        #[clap(long)]
+        by_maintainer: Option<String>,
+        /// only show packages with this license, given
+        /// as an SPDX identifier, such as MIT
+        #[clap(long)]
+        by_license: Option<String>,
+        /// how to print the results
+        #[clap(long, short, value_enum, default_value_t)]
+        output: search::SearchOutputFormat,
+        /// print the results as a JSON array,
+        /// the same as `--output json`.
+        #[clap(long, conflicts_with_all = ["json_lines", "output"])]
+        json: bool,
+        /// print one JSON object per line, the
+        /// same as `--output json-lines`.
+        #[clap(long, visible_alias = "jsonl", conflicts_with = "output")]
+        json_lines: bool,
+    },
+
+    /// install a package from nixpkgs, or from any flake
+    Install {
+        /// an attribute name, such as ripgrep, or a flake
+        /// reference, such as github:andystopia/newt
+        src: String,
+        /// install from a channel, such as 24.05 or unstable,
+        /// rather than from the nixpkgs in your registry.
+        #[clap(long)]
+        channel: Option<String>,
+        /// hand `src` to nix exactly as it's written, rather
+        /// than turning links to repositories into flake references.
+        #[clap(long, conflicts_with = "channel")]
+        no_remap: bool,
+        /// print the install command, without running it.
+        #[clap(long)]
+        dry_run: bool,
+        /// don't ask for confirmation before installing.
+        #[clap(long, short, conflicts_with = "dry_run")]
+        yes: bool,
+    },
+
+    /// upgrade every installed package to the latest
+    /// version in the channel it came from.
+    Update {
+        /// the profile to upgrade, rather than your default profile.
+        #[clap(long)]
+        profile: Option<PathBuf>,
+        /// show what would be upgraded, without upgrading anything.
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// show the packages which were installed from an
+    /// older nixpkgs than a channel has now.
+    Outdated {
+        /// the channel to compare against, such as 24.05 or
+        /// unstable, by default, the latest stable channel.
+        #[clap(long)]
+        channel: Option<String>,
+        /// print the results as a JSON array
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// show everything search.nixos.org knows about a package
+    Info {
+        /// the exact attribute name of the package
+        package: String,
+        /// the channel to look in, such as 24.05 or unstable,
+        /// by default, search.default_channel from the config.
+        #[clap(long)]
+        channel: Option<String>,
+        /// print the package as JSON
+        #[clap(long)]
+        json: bool,
+        /// follow each license with its SPDX identifier
+        #[clap(long)]
+        spdx: bool,
+    },
+
+    /// write the installed packages as JSON, so that
+    /// they can be installed elsewhere with `gnix import`.
+    Export {
+        /// write to this file, rather than to stdout.
+        output: Option<PathBuf>,
+    },
+
+    /// install every package in a list made by `gnix export`
+    /// which isn't installed already.
+    Import {
+        file: PathBuf,
+        /// print the install commands, without running them.
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// install a particular version of a package, using
+    /// nixhub.io to find a nixpkgs commit which has it.
+    Pin {
+        /// the attribute name of the package, such as gleam
+        package: String,
+        /// the exact version, such as 1.2.0
+        version: String,
+        /// install from this channel instead, such as 24.05, as
+        /// long as the version it has is the one asked for.
+        #[clap(long)]
+        channel: Option<String>,
+        /// print the install command, without running it.
+        #[clap(long)]
+        dry_run: bool,
+        /// don't ask for confirmation before installing.
+        #[clap(long, short, conflicts_with = "dry_run")]
+        yes: bool,
+    },
+
+    /// remove an installed package from your profile
+    Uninstall {
+        /// the name of the package, as shown by `nix profile list`
+        #[clap(add = ArgValueCompleter::new(completions::installed_package_names))]
+        package: String,
+    },
+
+    /// print a completion script for your shell. for bash, add
+    /// `source <(gnix completions bash)` to ~/.bashrc, for fish, run
+    /// `gnix completions fish > ~/.config/fish/completions/gnix.fish`.
+    /// to also complete the names of installed packages, use
+    /// `source <(COMPLETE=bash gnix)` instead.
+    Completions { shell: clap_complete::Shell },
+
+    /// check that every installed package is still in the
+    /// nix store, garbage collection can remove them.
+    Verify {
+        /// remove the packages which are missing from the
+        /// profile, so that they can be installed again.
+        #[clap(long)]
+        fix: bool,
+    },
+
+    /// clean up what gnix has stored on disk
+    Gc {
+        /// delete the cached details of the installed packages
+        #[clap(long)]
+        cache: bool,
+        /// only delete cached details which are older than
+        /// this many days, rather than all of them.
+        #[clap(long, requires = "cache")]
+        older_than: Option<u64>,
+    },
+
+    /// print a ready-to-use shell.nix which provides
+    /// the given packages, for use with `nix-shell`.
+    GenerateShellNix {
+        /// attribute names of the packages to put in the shell
+        #[clap(required = true)]
+        packages: Vec<String>,
+        /// pin nixpkgs to a channel, such as 24.05 or unstable,
+        /// instead of using <nixpkgs> from the NIX_PATH.
+        #[clap(long)]
+        channel: Option<String>,
+        /// the name of the shell, by default, gnix-shell.
+        #[clap(long)]
+        name: Option<String>,
+        /// print a flake.nix with a default devShell instead.
+        #[clap(long)]
+        flake: bool,
+    },
+
+    /// check that nix and gnix are set up correctly,
+    /// and report anything which looks wrong.
+    Doctor {
+        /// attempt to fix any problems which have a
+        /// known remediation, asking before each fix.
+        #[clap(long)]
+        fix: bool,
+        /// don't ask for confirmation before applying fixes.
+        #[clap(long, short, requires = "fix")]
+        yes: bool,
+    },
+
+    /// check whether a newer release of gnix is available,
+    /// the result is remembered for a day.
+    CheckUpdates {
+        /// also print where the version information came from
+        #[clap(long, short)]
+        verbose: bool,
+    },
+
+    /// show how much disk space each installed package
+    /// takes up, along with everything it depends on.
+    Size {
+        /// the profile to look at, rather than your default profile.
+        #[clap(long)]
+        profile: Option<PathBuf>,
+    },
+
+    /// switch a profile back to its previous generation
+    Rollback {
+        /// the profile to roll back, rather than your default profile.
+        #[clap(long)]
+        profile: Option<PathBuf>,
+    },
+
+    /// view and edit the gnix settings in ~/.config/gnix/config.toml
+    #[clap(subcommand)]
+    Config(ConfigCli),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCli {
+    /// print the current value of a setting
+    Get {
+        /// a dot separated path, such as search.default_limit
+        key: String,
+    },
+    /// change a setting, comments and formatting
+    /// in the config file are kept as they are.
+    Set {
+        /// a dot separated path, such as elasticsearch.url
+        key: String,
+        value: String,
+    },
+    /// print every setting along with its current value
+    List,
 }
 
 fn main() -> color_eyre::Result<()> {
+    // when the shell is asking for completions, this
+    // answers them and exits, otherwise it does nothing.
+    clap_complete::CompleteEnv::with_factory(Cli::command).complete();
+
     let cli = Cli::parse();
     match cli {
-        Cli::List => {
-            let parsed = manifest_parsed().unwrap();
-
-            let mut pkgs = parsed.elements.packages.into_iter().collect::<Vec<_>>();
-            pkgs.sort_by_key(|k| k.0.clone());
-
-            for (i, (pname, package)) in pkgs.iter().enumerate() {
-                let version = get_version(package);
-                let meta = get_meta(package);
-
-                let description = meta.get("description").and_then(|d| d.as_str());
-
-                let last_arg = i == pkgs.len() - 1;
-                let joiner = if last_arg { "└" } else { "├" };
-                let indent = if last_arg { " " } else { "│" };
-
-                println!(
-                    "{joiner}─{} ",
-                    format!(
-                        "{}{}{}{}{}",
-                        " ".on_blue(),
-                        pname.clone().bold().on_blue(),
-                        " @ ".on_blue(),
-                        match version {
-                            Some(s) => format!("{}", s.bold().on_blue()),
-                            None => format!("{}", "<version unknown>".italic().on_blue()),
-                        },
-                        " ".on_blue()
-                    ),
-                );
-                if let Some(description) = description {
-                    println!("{indent}  ├─ {}", description.white().bold());
+        Cli::List {
+            profile,
+            format,
+            json,
+            json_lines,
+        } => {
+            let profile = profile.map(Profile::at).unwrap_or_default();
+            let parsed = profile.manifest_parsed().unwrap();
+
+            // the listing starts straight away, and each package's
+            // details are fetched just before it's printed.
+            let mut cache = CachePackages::from_manifest_minimal(&parsed);
+            let keys = cache.packages.iter().map(|p| p.key()).collect::<Vec<_>>();
+
+            // anything fetched recently can be reused rather than
+            // asking nix again, a broken cache is just ignored.
+            let cache_path = cache::profile_cache_path(&profile);
+            if let Ok(cached) = CachePackages::load_with_ttl(&cache_path, cache::PROFILE_CACHE_TTL)
+            {
+                cache.reuse_cached(&cached);
+            }
+
+            // a package which can't be evaluated is still
+            // listed, just without its version or description.
+            if json_lines {
+                let mut stdout = std::io::stdout().lock();
+                for key in &keys {
+                    let _ = cache.enrich_entry(key);
+                    if let Some(package) = cache.get(key) {
+                        output::write_json_line(&mut stdout, package)?;
+                    }
                 }
-                println!(
-                    "{indent}  └─ {}#{}",
-                    match package.original_url.split_once(':') {
-                        Some((first, second)) => format!(
-                            "{} {}",
-                            format!("{}:", first).purple().bold(),
-                            second.underline()
-                        ),
-                        None => format!("{}", package.original_url),
-                    },
-                    package.attr_path.italic()
+                let _ = cache.save(&cache_path);
+                return Ok(());
+            }
+            let format = if json { list::ListFormat::Json } else { format };
+            if format != list::ListFormat::Table {
+                for key in &keys {
+                    let _ = cache.enrich_entry(key);
+                }
+                print!("{}", format.formatter().format(&cache.packages));
+                let _ = cache.save(&cache_path);
+                return Ok(());
+            }
+
+            // checking for updates goes over the network, so it
+            // happens alongside the listing rather than before it.
+            let update_check = std::thread::spawn(update::check_for_updates);
+
+            // the table is printed a package at a time, so that
+            // each package appears as soon as it's been fetched.
+            let printer = list::PrettyInstalledPackagePrinter::default();
+            for (i, key) in keys.iter().enumerate() {
+                let _ = cache.enrich_entry(key);
+                if let Some(package) = cache.get(key) {
+                    print!("{}", printer.format_package(package, i == keys.len() - 1));
+                }
+            }
+            // failing to save just means fetching again next time.
+            let _ = cache.save(&cache_path);
+
+            // the check is best effort, so failures are silent.
+            if let Ok(Ok(status @ update::UpdateStatus::Available { .. })) = update_check.join() {
+                eprintln!("{}", status.yellow());
+            }
+        }
+        Cli::Size { profile } => {
+            let profile = profile.map(Profile::at).unwrap_or_default();
+            let parsed = profile.manifest_parsed()?;
+
+            let mut sizes = parsed
+                .elements
+                .packages
+                .iter()
+                .map(|(name, package)| (name, package.closure_size_bytes().ok()))
+                .collect::<Vec<_>>();
+            // largest first, anything we couldn't size goes last.
+            sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+            let width = sizes.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+            for (name, size) in &sizes {
+                let size = match size {
+                    Some(size) => output::format_size(*size),
+                    None => "unknown".to_owned(),
+                };
+                println!("{:<width$}  {:>10}", name.bold(), size);
+            }
+        }
+        Cli::Rollback { profile } => {
+            let profile_path = match profile {
+                Some(profile) => profile,
+                None => nix_installed_list::default_profile_path()?,
+            };
+            let current = nix_installed_list::current_generation(&profile_path)?;
+            let previous = nix_installed_list::get_profile_generations(&profile_path)?
+                .into_iter()
+                .filter(|generation| *generation < current)
+                .max();
+            let Some(previous) = previous else {
+                color_eyre::eyre::bail!(
+                    "generation {current} is the oldest, there's nothing to roll back to"
                 );
+            };
 
-                if !last_arg {
-                    println!("{indent}");
+            // showing what will change is nice to have, but older
+            // generations may have manifests we can't read.
+            let before = nix_installed_list::manifest_parsed_with_profile(&profile_path);
+            let after = nix_installed_list::manifest_at_generation(&profile_path, previous);
+            if let (Ok(before), Ok(after)) = (before, after) {
+                let before = &before.elements.packages;
+                let after = &after.elements.packages;
+                let mut removed = before
+                    .keys()
+                    .filter(|k| !after.contains_key(*k))
+                    .collect::<Vec<_>>();
+                let mut added = after
+                    .keys()
+                    .filter(|k| !before.contains_key(*k))
+                    .collect::<Vec<_>>();
+                removed.sort();
+                added.sort();
+                for name in removed {
+                    println!("{} {}", "-".red().bold(), name);
+                }
+                for name in added {
+                    println!("{} {}", "+".green().bold(), name);
                 }
             }
-        },
+
+            let status = Profile::at(&profile_path)
+                .nix_profile("rollback")
+                .arg("--to")
+                .arg(previous.to_string())
+                .status()?;
+            if !status.success() {
+                color_eyre::eyre::bail!("nix profile rollback exited with {status}");
+            }
+            println!("rolled back from generation {current} to {previous}");
+        }
+        Cli::CheckUpdates { verbose } => {
+            if verbose {
+                println!("current version: v{}", update::current_version());
+                println!("releases: {}", update::RELEASES_URL);
+                println!("cache: {}", update::update_check_path().display());
+            }
+            match update::check_for_updates()? {
+                status @ update::UpdateStatus::UpToDate(_) => println!("{}", status.green()),
+                status @ update::UpdateStatus::Available { .. } => {
+                    println!("{}", status.yellow().bold())
+                }
+            }
+        }
         Cli::ListChannels { n } => {
-            let channel_list = nix_channel_list::get_full_channels()?;
-            let mut channel_list = channel_list.into_iter().collect::<Vec<_>>();
-            channel_list.sort();
-            channel_list.reverse();
+            let mut channel_list = nix_channel_list::get_full_channels()?;
+            channel_list.sort_by(|a, b| nix_channel_list::compare_channels(b, a));
             let n = n.unwrap_or(5);
             for channel in channel_list.iter().take(n) {
                 println!("{}", channel);
             }
-        },
+        }
+        Cli::Search {
+            package,
+            channel,
+            limit,
+            filter_platform,
+            by_maintainer,
+            by_license,
+            output,
+            json,
+            json_lines,
+        } => {
+            let config = config::GnixConfig::load(&config::config_path())?;
+            let channel = match channel {
+                Some(channel) => channel,
+                None => config.get("search.default_channel")?,
+            };
+            let limit = match limit {
+                Some(limit) => limit,
+                None => config.get("search.default_limit")?.parse()?,
+            };
+            let options = search::SearchOptions {
+                channel,
+                limit,
+                platform: filter_platform,
+                maintainer: by_maintainer,
+                license: by_license,
+                format: search::SearchOutputFormat::from_flags(output, json, json_lines),
+            };
+            search::search_package(&package, &options)?;
+        }
+        Cli::Install {
+            src,
+            channel,
+            no_remap,
+            dry_run,
+            yes,
+        } => {
+            // bare names are put after a `#`, so they have
+            // to be an attribute path, flake references don't.
+            if !no_remap && !src.contains([':', '#']) && !install::is_valid_package_name(&src) {
+                color_eyre::eyre::bail!("`{src}` is not a valid package name");
+            }
+            // nix's own error for a missing channel is hard to
+            // read, but we'll still let it try in case we're wrong.
+            if let Some(channel) = &channel {
+                match nix_channel_list::is_channel_accessible(channel) {
+                    Ok(true) => {}
+                    Ok(false) => eprintln!(
+                        "{} {} doesn't look like a channel which exists",
+                        "warning:".yellow().bold(),
+                        nix_channel_list::get_nixos_channel_url(channel)
+                    ),
+                    Err(e) => eprintln!("{} {e}", "warning:".yellow().bold()),
+                }
+            }
+            let src = if no_remap {
+                src
+            } else {
+                install::installable(&src, channel.as_deref())
+            };
+            install::install_package(&src, install::DryRunMode::from_flags(dry_run, yes))?;
+        }
+        Cli::Update { profile, dry_run } => {
+            let profile = profile.map(Profile::at).unwrap_or_default();
+            let mut packages = profile
+                .manifest_parsed()?
+                .elements
+                .packages
+                .into_iter()
+                .collect::<Vec<_>>();
+            // nix upgrades by the name of the element in the profile.
+            packages.sort_by(|a, b| a.0.cmp(&b.0));
+
+            if dry_run {
+                upgrade::pretty_print_upgrade_plan(&packages);
+            }
+            let summary = upgrade::upgrade_packages(&profile, &packages, dry_run);
+            upgrade::print_summary(&summary, dry_run);
+            if !summary.failed.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Cli::Outdated { channel, json } => {
+            let channel = match channel {
+                Some(channel) => channel,
+                None => nix_channel_list::get_latest_stable_channel()?,
+            };
+            let latest = outdated::latest_channel_rev(&channel)?;
+
+            // the names of packages come from the cache when they
+            // can, which saves asking nix about every package.
+            let profile = Profile::default();
+            let mut cache = CachePackages::from_manifest_minimal(&profile.manifest_parsed()?);
+            let cache_path = cache::profile_cache_path(&profile);
+            if let Ok(cached) = CachePackages::load_with_ttl(&cache_path, cache::PROFILE_CACHE_TTL)
+            {
+                cache.reuse_cached(&cached);
+            }
 
+            let outdated = outdated::find_outdated(&cache.packages, &latest, |rev| {
+                outdated::nixpkgs_rev_last_modified(rev).ok()
+            });
+            if json {
+                output::write_json(&mut std::io::stdout().lock(), &outdated)?;
+            } else {
+                outdated::print_outdated(&outdated);
+            }
+        }
+        Cli::Info {
+            package,
+            channel,
+            json,
+            spdx,
+        } => {
+            let channel = match channel {
+                Some(channel) => channel,
+                None => config::GnixConfig::load(&config::config_path())?
+                    .get("search.default_channel")?,
+            };
+            let package = info::query_package_info(&package, &channel)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&package)?);
+            } else {
+                info::print_info(&package, &channel, spdx);
+                info::print_installed_outputs(&package.package_attr_name);
+            }
+        }
+        Cli::Export { output } => {
+            let profile = Profile::default();
+            let mut cache = CachePackages::from_manifest_minimal(&profile.manifest_parsed()?);
+            if let Ok(cached) = CachePackages::load_with_ttl(
+                &cache::profile_cache_path(&profile),
+                cache::PROFILE_CACHE_TTL,
+            ) {
+                cache.reuse_cached(&cached);
+            }
+            let exported = share::export_packages(&cache);
+            match output {
+                Some(path) => std::fs::write(path, serde_json::to_string_pretty(&exported)?)?,
+                None => output::write_json(&mut std::io::stdout().lock(), &exported)?,
+            }
+        }
+        Cli::Import { file, dry_run } => {
+            let packages: Vec<share::ExportedPackage> =
+                serde_json::from_str(&std::fs::read_to_string(&file)?)?;
+            let installed = Profile::default().manifest_parsed()?;
+            // the whole file is what's being asked for,
+            // so each package isn't confirmed on its own.
+            let mode = install::DryRunMode::from_flags(dry_run, true);
+            for package in share::packages_to_import(&packages, &installed) {
+                install::install_package(&package.installable(), mode)?;
+            }
+        }
+        Cli::Pin {
+            package,
+            version,
+            channel,
+            dry_run,
+            yes,
+        } => {
+            let src = match channel {
+                Some(channel) => {
+                    let found = info::query_package_info(&package, &channel)?;
+                    if found.package_pversion != version {
+                        color_eyre::eyre::bail!(
+                            "{channel} has {package} {}, not {version}",
+                            found.package_pversion
+                        );
+                    }
+                    install::installable(&package, Some(&channel))
+                }
+                None => {
+                    let searcher = nixhub_version_search::CachedVersionSearcher::new(
+                        cache::version_cache_path(),
+                        cache::VERSION_CACHE_TTL,
+                    );
+                    let versions = searcher.scrape_package_version_cached(&package)?;
+                    let Some(lookup) = pin::find_version(&versions, &version) else {
+                        eprintln!("the versions of {package} which nixhub.io knows about are:");
+                        for lookup in &versions {
+                            eprintln!("  {}", lookup.version);
+                        }
+                        color_eyre::eyre::bail!("couldn't find {package} {version}");
+                    };
+                    pin::pinned_installable(lookup, &package)
+                }
+            };
+            install::install_package(&src, install::DryRunMode::from_flags(dry_run, yes))?;
+        }
+        Cli::Uninstall { package } => {
+            let profile = Profile::default();
+            // anything we can't find a similar name for is passed
+            // along as it is, nix also takes store paths and such.
+            let package = match uninstall::suggest(&profile.manifest_parsed()?, &package) {
+                Some(similar) => {
+                    let question =
+                        format!("`{package}` isn't installed, did you mean `{similar}`?");
+                    if !doctor::confirm(&question) {
+                        return Ok(());
+                    }
+                    similar
+                }
+                None => package,
+            };
+            let status = profile.nix_profile("remove").arg(&package).status()?;
+            if !status.success() {
+                color_eyre::eyre::bail!("nix profile remove exited with {status}");
+            }
+        }
+        Cli::Completions { shell } => {
+            completions::write_completions(shell, &mut std::io::stdout().lock());
+        }
+        Cli::Verify { fix } => {
+            let profile = Profile::default();
+            let verified = verify::verify_packages(&profile.manifest_parsed()?);
+            for package in &verified {
+                verify::print_verified(package);
+            }
+
+            let broken = verified
+                .iter()
+                .filter(|p| !p.is_valid())
+                .collect::<Vec<_>>();
+            println!(
+                "{} valid, {} broken",
+                verified.len() - broken.len(),
+                broken.len()
+            );
+
+            let mut unfixed = broken.len();
+            if fix {
+                for package in &broken {
+                    let status = profile.nix_profile("remove").arg(&package.name).status()?;
+                    if status.success() {
+                        println!("removed {}", package.name);
+                        unfixed -= 1;
+                    } else {
+                        eprintln!(
+                            "failed to remove {}: nix exited with {status}",
+                            package.name
+                        );
+                    }
+                }
+            }
+            if unfixed > 0 {
+                std::process::exit(1);
+            }
+        }
+        Cli::Gc { cache, older_than } => {
+            if !cache {
+                println!("nothing to clean up, pass --cache to clear the package cache");
+                return Ok(());
+            }
+            let path = cache::profile_cache_path(&Profile::default());
+            let older_than = older_than
+                .map(|days| std::time::Duration::from_secs(days.saturating_mul(24 * 60 * 60)));
+            match cache::gc_cache(&path, older_than)? {
+                Some(summary) => {
+                    let freed = output::format_size(summary.freed_bytes as u64);
+                    match summary.removed {
+                        Some(removed) => {
+                            println!("removed {removed} cached packages, freeing about {freed}")
+                        }
+                        None => println!("removed the package cache, freeing {freed}"),
+                    }
+                }
+                None => println!("there's no cache to clean up at {}", path.display()),
+            }
+        }
+        Cli::GenerateShellNix {
+            packages,
+            channel,
+            name,
+            flake,
+        } => {
+            if let Some(invalid) = packages
+                .iter()
+                .find(|pkg| !install::is_valid_package_name(pkg))
+            {
+                color_eyre::eyre::bail!("`{invalid}` is not a valid package name");
+            }
+            if let Some(channel) = channel
+                .as_deref()
+                .filter(|channel| !shell_nix::is_valid_channel(channel))
+            {
+                color_eyre::eyre::bail!("`{channel}` is not a channel, such as 24.05 or unstable");
+            }
+            let name = name.as_deref().unwrap_or(shell_nix::DEFAULT_SHELL_NAME);
+            let generated = if flake {
+                shell_nix::flake_nix(&packages, channel.as_deref(), name)
+            } else {
+                shell_nix::shell_nix(&packages, channel.as_deref(), name)
+            };
+            print!("{generated}");
+        }
+        Cli::Doctor { fix, yes } => {
+            let failing = doctor::run(&doctor::default_checks(), fix, yes);
+            if failing > 0 {
+                std::process::exit(1);
+            }
+        }
+        Cli::Config(config_cli) => {
+            let mut config = config::GnixConfig::load(&config::config_path())?;
+            match config_cli {
+                ConfigCli::Get { key } => println!("{}", config.get(&key)?),
+                ConfigCli::Set { key, value } => {
+                    config.set(&key, &value)?;
+                    config.save()?;
+                }
+                ConfigCli::List => {
+                    for (key, value, is_default) in config.list() {
+                        print!("{} = {}", key.key.bold(), value);
+                        if is_default {
+                            print!(" {}", "(default)".italic());
+                        }
+                        println!();
+                        println!("  └─ {}", key.description.white());
+                    }
+                }
+            }
+        }
     }
     Ok(())
 }
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::owo_colors::OwoColorize;
+use nix_installed_list::CachePackage;
+use serde::{Deserialize, Serialize};
+
+use crate::install;
+
+/// a package installed from an older nixpkgs
+/// commit than the one a channel is on now.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutdatedPackage {
+    pub name: String,
+    pub installed: String,
+    pub latest: String,
+}
+
+#[derive(Deserialize)]
+struct FlakeMetadata {
+    locked: LockedRev,
+}
+
+/// the commit a flake is locked to, and when it was made.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LockedRev {
+    pub rev: String,
+    /// seconds since the epoch.
+    #[serde(rename = "lastModified")]
+    pub last_modified: u64,
+}
+
+/// pull the locked commit out of the output of `nix flake metadata --json`
+pub fn parse_flake_metadata(json: &str) -> color_eyre::Result<LockedRev> {
+    let metadata: FlakeMetadata = serde_json::from_str(json)?;
+    Ok(metadata.locked)
+}
+
+fn flake_metadata(flake: &str) -> color_eyre::Result<LockedRev> {
+    let output = nix_installed_list::nix()
+        .args(["flake", "metadata", "--json", flake])
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "couldn't resolve {flake}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    parse_flake_metadata(&String::from_utf8_lossy(&output.stdout))
+        .map_err(|e| eyre!("unexpected metadata for {flake}: {e}"))
+}
+
+/// the commit a channel of nixpkgs is on right now.
+pub fn latest_channel_rev(channel: &str) -> color_eyre::Result<LockedRev> {
+    flake_metadata(&install::nixpkgs_channel_flake(channel))
+}
+
+/// when a nixpkgs commit was made, in seconds since the epoch.
+pub fn nixpkgs_rev_last_modified(rev: &str) -> color_eyre::Result<u64> {
+    Ok(flake_metadata(&format!("github:NixOS/nixpkgs/{rev}"))?.last_modified)
+}
+
+/// the packages installed from a nixpkgs commit older than `latest`,
+/// `last_modified` is asked once for each other commit, and packages
+/// whose commit it can't date, or from other flakes, are skipped.
+pub fn find_outdated(
+    packages: &[CachePackage],
+    latest: &LockedRev,
+    mut last_modified: impl FnMut(&str) -> Option<u64>,
+) -> Vec<OutdatedPackage> {
+    let mut dates = HashMap::new();
+    packages
+        .iter()
+        .filter_map(|package| {
+            let installed = package.nixpkgs_rev()?;
+            if installed == latest.rev {
+                return None;
+            }
+            let date = *dates
+                .entry(installed)
+                .or_insert_with(|| last_modified(installed));
+            (date? < latest.last_modified).then(|| OutdatedPackage {
+                name: package.name.clone(),
+                installed: installed.to_owned(),
+                latest: latest.rev.clone(),
+            })
+        })
+        .collect()
+}
+
+pub fn print_outdated(outdated: &[OutdatedPackage]) {
+    if outdated.is_empty() {
+        println!("{}", "everything is up to date".green());
+        return;
+    }
+    for package in outdated {
+        println!(
+            "{}: installed {} → latest {}",
+            package.name.bold(),
+            package.installed.red(),
+            package.latest.green()
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OLD: &str = "0123456789abcdef0123456789abcdef01234567";
+    const NEW: &str = "fedcba9876543210fedcba9876543210fedcba98";
+    const NEWER: &str = "00112233445566778899aabbccddeeff00112233";
+
+    fn package(name: &str, url: &str) -> CachePackage {
+        CachePackage {
+            name: name.to_owned(),
+            url: url.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_flake_metadata() {
+        let json = format!(
+            r#"{{
+                "description": "A collection of packages for the Nix package manager",
+                "locked": {{ "lastModified": 1718000000, "owner": "NixOS", "repo": "nixpkgs", "rev": "{NEW}", "type": "github" }},
+                "original": {{ "owner": "NixOS", "ref": "nixos-24.05", "repo": "nixpkgs", "type": "github" }}
+            }}"#
+        );
+        assert_eq!(
+            parse_flake_metadata(&json).unwrap(),
+            LockedRev {
+                rev: NEW.to_owned(),
+                last_modified: 1718000000,
+            }
+        );
+        assert!(parse_flake_metadata("{}").is_err());
+    }
+
+    #[test]
+    fn test_find_outdated() {
+        let packages = [
+            package(
+                "ripgrep",
+                &format!("github:NixOS/nixpkgs/{OLD}?narHash=sha256-abc"),
+            ),
+            package("fd", &format!("github:NixOS/nixpkgs/{OLD}")),
+            package("gleam", &format!("github:NixOS/nixpkgs/{NEW}")),
+            package("jq", &format!("github:NixOS/nixpkgs/{NEWER}")),
+            package("tool", "github:someone/tool/abc123"),
+        ];
+        let latest = LockedRev {
+            rev: NEW.to_owned(),
+            last_modified: 200,
+        };
+        let mut asked = Vec::new();
+        let outdated = find_outdated(&packages, &latest, |rev| {
+            asked.push(rev.to_owned());
+            match rev {
+                OLD => Some(100),
+                NEWER => Some(300),
+                _ => None,
+            }
+        });
+        // unstable is newer than the stable channel, so jq isn't outdated
+        assert_eq!(
+            outdated,
+            ["ripgrep", "fd"].map(|name| OutdatedPackage {
+                name: name.to_owned(),
+                installed: OLD.to_owned(),
+                latest: NEW.to_owned(),
+            })
+        );
+        assert_eq!(asked, [OLD, NEWER]);
+
+        // a commit we can't date isn't reported
+        assert!(find_outdated(&packages[..2], &latest, |_| None).is_empty());
+    }
+}
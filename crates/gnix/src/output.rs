@@ -0,0 +1,125 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+/// write a single value as one line of JSON, the output is
+/// flushed straight away so that anything reading from a
+/// pipe sees each line as soon as it's ready.
+pub fn write_json_line<T: Serialize>(out: &mut impl Write, value: &T) -> color_eyre::Result<()> {
+    writeln!(out, "{}", serde_json::to_string(value)?)?;
+    out.flush()?;
+    Ok(())
+}
+
+pub fn write_json<T: Serialize>(out: &mut impl Write, values: &[T]) -> color_eyre::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, values)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// a byte count in binary units, such as 33.8 MiB
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nix_installed_list::{CachePackage, CachePackageLookupKey, CachePackages, Package, Root};
+
+    use super::*;
+
+    fn cache() -> CachePackages {
+        let mut root = Root::default();
+        for name in ["gleam", "ripgrep"] {
+            root.elements.packages.insert(
+                name.to_owned(),
+                Package {
+                    attr_path: format!("legacyPackages.x86_64-linux.{name}"),
+                    original_url: "flake:nixpkgs".to_owned(),
+                    url: "github:NixOS/nixpkgs/abc123".to_owned(),
+                    store_paths: vec![format!("/nix/store/xyz-{name}")],
+                    ..Default::default()
+                },
+            );
+        }
+        let mut cache = CachePackages::from_manifest_minimal(&root);
+        let key = cache.packages[0].key();
+        cache
+            .enrich_entry_with(&key, |key: &CachePackageLookupKey, attr| {
+                Ok(match attr {
+                    "pname" => serde_json::json!(key.attr_path.rsplit('.').next()),
+                    "version" => serde_json::json!("1.0.0"),
+                    "meta" => serde_json::json!({ "description": "a language" }),
+                    _ => serde_json::Value::Null,
+                })
+            })
+            .unwrap();
+        cache
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let cache = cache();
+        let mut out = Vec::new();
+        for package in &cache.packages {
+            write_json_line(&mut out, package).unwrap();
+        }
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.ends_with('\n'));
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+
+        for line in &lines {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert!(value["name"].is_string());
+            assert!(value["version"].is_string() || value["version"].is_null());
+            assert!(value["meta"].is_object());
+            assert!(value["attr_path"].is_string());
+            assert!(value["url"].is_string());
+            assert!(value["original_url"].is_string());
+            assert!(value["store_paths"].is_array());
+            assert!(value["enriched"].is_boolean());
+        }
+
+        let gleam: CachePackage = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(gleam.name, "gleam");
+        assert_eq!(gleam.version.as_deref(), Some("1.0.0"));
+        assert_eq!(gleam.description(), Some("a language"));
+
+        let ripgrep: CachePackage = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(ripgrep.name, "legacyPackages.x86_64-linux.ripgrep");
+        assert_eq!(ripgrep.version, None);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(35462048), "33.8 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[test]
+    fn test_json_array() {
+        let cache = cache();
+        let mut out = Vec::new();
+        write_json(&mut out, &cache.packages).unwrap();
+
+        let parsed: Vec<CachePackage> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].name, "gleam");
+    }
+}
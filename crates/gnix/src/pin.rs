@@ -0,0 +1,42 @@
+use nixhub_version_search::VersionLookup;
+
+pub fn find_version<'a>(versions: &'a [VersionLookup], version: &str) -> Option<&'a VersionLookup> {
+    versions.iter().find(|lookup| lookup.version == version)
+}
+
+/// nixpkgs at the commit which has the version.
+pub fn pinned_installable(lookup: &VersionLookup, package: &str) -> String {
+    format!("github:NixOS/nixpkgs/{}#{package}", lookup.commit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn versions() -> Vec<VersionLookup> {
+        [
+            ("1.4.1", "4aa36568d413aca0ea84a1684d2d46f55dbabad7"),
+            ("1.2.0", "05bbf675397d5366259409139039af8077d695ce"),
+        ]
+        .into_iter()
+        .map(|(version, commit)| VersionLookup {
+            version: version.to_owned(),
+            commit: commit.to_owned(),
+            date: String::new(),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_pin_version() {
+        let versions = versions();
+        let lookup = find_version(&versions, "1.2.0").unwrap();
+        assert_eq!(
+            pinned_installable(lookup, "gleam"),
+            "github:NixOS/nixpkgs/05bbf675397d5366259409139039af8077d695ce#gleam"
+        );
+
+        assert!(find_version(&versions, "1.2").is_none());
+        assert!(find_version(&[], "1.2.0").is_none());
+    }
+}
@@ -0,0 +1,402 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use color_eyre::eyre::eyre;
+use color_eyre::owo_colors::OwoColorize;
+use nix_elastic_search::response::NixPackage;
+use nix_elastic_search::{MatchSearch, Query, SearchWithin};
+use nix_search_sort_utils::{filter_packages_by_platform, platform_support, PackageSupport};
+
+use crate::output;
+use crate::wrap;
+
+/// the description is printed after ` ↳ `,
+/// which takes up three columns.
+const DESCRIPTION_INDENT: usize = 3;
+
+/// `--filter-platform current` means whatever
+/// system nix is running on.
+pub const CURRENT_PLATFORM: &str = "current";
+
+/// turn the argument to `--filter-platform` into a platform,
+/// `current` is resolved with `current_system`, anything
+/// else is taken to be a platform such as aarch64-darwin.
+pub fn resolve_platform(
+    filter: &str,
+    current_system: impl FnOnce() -> color_eyre::Result<String>,
+) -> color_eyre::Result<String> {
+    if filter == CURRENT_PLATFORM {
+        current_system()
+    } else {
+        Ok(filter.to_owned())
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, ValueEnum)]
+pub enum SearchOutputFormat {
+    /// each package with its description
+    #[default]
+    Pretty,
+    /// a JSON array
+    Json,
+    /// one JSON object per line, each line
+    /// is written as soon as it's ready.
+    JsonLines,
+    /// the attribute name, version and description
+    /// of each package, separated by tabs.
+    Tsv,
+}
+
+impl SearchOutputFormat {
+    /// `--json` and `--json-lines` are kept around from
+    /// before `--output`, and win over it when they're given.
+    pub fn from_flags(output: SearchOutputFormat, json: bool, json_lines: bool) -> Self {
+        if json_lines {
+            SearchOutputFormat::JsonLines
+        } else if json {
+            SearchOutputFormat::Json
+        } else {
+            output
+        }
+    }
+}
+
+/// everything which changes what `gnix search` finds, and how it prints it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub channel: String,
+    pub limit: u32,
+    /// `--filter-platform`, see [`resolve_platform`]
+    pub platform: Option<String>,
+    /// a github handle, with or without the @
+    pub maintainer: Option<String>,
+    /// an SPDX identifier, such as MIT
+    pub license: Option<String>,
+    pub format: SearchOutputFormat,
+}
+
+/// how many times `limit` to ask search.nixos.org for when
+/// results are filtered afterwards, so that most of the time
+/// there are still `limit` left once the filters have run.
+const FILTERED_OVERFETCH: u32 = 20;
+
+impl SearchOptions {
+    /// whether any of the filters which search.nixos.org can't
+    /// do for us are set.
+    pub fn filters_locally(&self) -> bool {
+        self.platform.is_some() || self.maintainer.is_some() || self.license.is_some()
+    }
+
+    /// how many results to query for, see [`FILTERED_OVERFETCH`].
+    pub fn query_limit(&self) -> u32 {
+        if self.filters_locally() {
+            self.limit.saturating_mul(FILTERED_OVERFETCH)
+        } else {
+            self.limit
+        }
+    }
+}
+
+pub fn is_maintained_by(package: &NixPackage, handle: &str) -> bool {
+    let handle = handle.trim_start_matches('@');
+    package.package_maintainers.iter().any(|maintainer| {
+        maintainer
+            .github
+            .as_deref()
+            .is_some_and(|github| github.eq_ignore_ascii_case(handle))
+    })
+}
+
+/// `spdx_id` can also be the full name of the license.
+pub fn has_license(package: &NixPackage, spdx_id: &str) -> bool {
+    package.package_license.iter().any(|license| {
+        nix_search_sort_utils::spdx_id(license).is_some_and(|id| id.eq_ignore_ascii_case(spdx_id))
+            || license.full_name.eq_ignore_ascii_case(spdx_id)
+    })
+}
+
+/// query search.nixos.org for packages matching `package` in
+/// the given channel, returning at most `limit` results.
+pub fn query_packages(
+    package: &str,
+    channel: &str,
+    limit: u32,
+) -> color_eyre::Result<Vec<NixPackage>> {
+    let query = Query {
+        max_results: limit,
+        search_within: SearchWithin::Channel(channel.to_owned()),
+        search: Some(MatchSearch {
+            search: package.to_owned(),
+        }),
+        program: None,
+        name: None,
+        version: None,
+        query_string: None,
+    };
+    query
+        .send()
+        .map_err(|e| eyre!("failed to search for `{package}`: {e}"))
+}
+
+/// print a search result, when `system` is known, the
+/// result is marked with whether it supports that system.
+pub fn print_package(package: &NixPackage, width: usize, system: Option<&str>) {
+    print!(
+        "{} @ {}",
+        package.package_attr_name.bold(),
+        package.package_pversion.bold()
+    );
+    match system.map(|system| platform_support(system, package)) {
+        Some(PackageSupport::Supported) => print!(" {}", "✓".green().bold()),
+        Some(PackageSupport::MostLikelyNot) => print!(" {}", "✗ unsupported".red()),
+        Some(PackageSupport::NoneListed) => print!(" {}", "? no platforms listed".yellow()),
+        None => {}
+    }
+    println!();
+
+    if let Some(desc) = &package.package_description {
+        let desc = wrap::wrap_text(desc, width, DESCRIPTION_INDENT);
+        println!(" ↳ {}", desc.white());
+    }
+}
+
+pub fn search_package(package: &str, options: &SearchOptions) -> color_eyre::Result<()> {
+    let current_system = || Ok(nix_installed_list::try_nix_system()?);
+
+    let mut packages = query_packages(package, &options.channel, options.query_limit())?;
+    // search.nixos.org can't be asked for these,
+    // so they filter what it sends back.
+    if let Some(handle) = &options.maintainer {
+        packages.retain(|p| is_maintained_by(p, handle));
+    }
+    if let Some(spdx_id) = &options.license {
+        packages.retain(|p| has_license(p, spdx_id));
+    }
+    let system = match &options.platform {
+        Some(filter) => {
+            // packages which don't list any platforms are kept,
+            // they're marked as such when they're printed.
+            let platform = resolve_platform(filter, current_system)?;
+            filter_packages_by_platform(&platform, &mut packages);
+            Some(platform)
+        }
+        // the support indicator is nice to have, but
+        // not worth failing the search over.
+        None => current_system().ok(),
+    };
+    packages.truncate(options.limit as usize);
+
+    if packages.is_empty() && options.format == SearchOutputFormat::Pretty {
+        println!("no packages matching `{package}` in {}", options.channel);
+        return Ok(());
+    }
+    print_search_results(&packages, options.format, system.as_deref())
+}
+
+/// one line of `--output tsv`, tabs and newlines in the
+/// description would break the columns, so they're spaces.
+pub fn tsv_line(package: &NixPackage) -> String {
+    let description = package
+        .package_description
+        .as_deref()
+        .unwrap_or_default()
+        .replace(['\t', '\n', '\r'], " ");
+    format!(
+        "{}\t{}\t{}",
+        package.package_attr_name, package.package_pversion, description
+    )
+}
+
+pub fn write_tsv(out: &mut impl Write, packages: &[NixPackage]) -> color_eyre::Result<()> {
+    for package in packages {
+        writeln!(out, "{}", tsv_line(package))?;
+    }
+    Ok(())
+}
+
+/// print the results of a search, when printing them pretty and
+/// `system` is known, each is marked with whether it supports it.
+pub fn print_search_results(
+    packages: &[NixPackage],
+    format: SearchOutputFormat,
+    system: Option<&str>,
+) -> color_eyre::Result<()> {
+    match format {
+        SearchOutputFormat::Pretty => {
+            let width = wrap::terminal_width();
+            for package in packages {
+                print_package(package, width, system);
+            }
+        }
+        SearchOutputFormat::Json => output::write_json(&mut std::io::stdout().lock(), packages)?,
+        SearchOutputFormat::JsonLines => {
+            let mut stdout = std::io::stdout().lock();
+            for package in packages {
+                output::write_json_line(&mut stdout, package)?;
+            }
+        }
+        SearchOutputFormat::Tsv => write_tsv(&mut std::io::stdout().lock(), packages)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn package(name: &str, platforms: &[&str]) -> NixPackage {
+        NixPackage {
+            package_attr_name: name.to_owned(),
+            package_platforms: platforms.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn packages() -> Vec<NixPackage> {
+        vec![
+            package("everywhere", &["x86_64-linux", "aarch64-darwin"]),
+            package("linux-only", &["x86_64-linux"]),
+            package("mac-only", &["aarch64-darwin"]),
+            package("unlisted", &[]),
+        ]
+    }
+
+    fn names(packages: &[NixPackage]) -> Vec<&str> {
+        packages
+            .iter()
+            .map(|p| p.package_attr_name.as_str())
+            .collect()
+    }
+
+    fn filtered(platform: &str) -> Vec<NixPackage> {
+        let mut packages = packages();
+        filter_packages_by_platform(platform, &mut packages);
+        packages
+    }
+
+    #[test]
+    fn test_resolve_current_platform() {
+        let platform = resolve_platform("current", || Ok("x86_64-linux".to_owned())).unwrap();
+        assert_eq!(platform, "x86_64-linux");
+        assert_eq!(
+            names(&filtered(&platform)),
+            ["everywhere", "linux-only", "unlisted"]
+        );
+
+        assert!(resolve_platform("current", || Err(eyre!("no nix"))).is_err());
+    }
+
+    #[test]
+    fn test_literal_platform() {
+        let platform =
+            resolve_platform("aarch64-darwin", || panic!("current system not needed")).unwrap();
+        assert_eq!(platform, "aarch64-darwin");
+        assert_eq!(
+            names(&filtered(&platform)),
+            ["everywhere", "mac-only", "unlisted"]
+        );
+        // nothing lists it, so only the package which doesn't say is kept.
+        assert_eq!(names(&filtered("riscv64-linux")), ["unlisted"]);
+    }
+
+    #[test]
+    fn test_maintainer_and_license() {
+        use nix_elastic_search::response::{PackageLicense, PackageMaintainer};
+
+        let pkg = NixPackage {
+            package_maintainers: vec![PackageMaintainer {
+                name: Some("Andy".to_owned()),
+                github: Some("andystopia".to_owned()),
+                email: None,
+            }],
+            package_license: vec![PackageLicense {
+                full_name: "MIT License".to_owned(),
+                url: Some("https://spdx.org/licenses/MIT.html".to_owned()),
+            }],
+            ..Default::default()
+        };
+        assert!(is_maintained_by(&pkg, "andystopia"));
+        assert!(is_maintained_by(&pkg, "@AndyStopia"));
+        assert!(!is_maintained_by(&pkg, "someone"));
+        assert!(!is_maintained_by(&NixPackage::default(), "andystopia"));
+
+        assert!(has_license(&pkg, "MIT"));
+        assert!(has_license(&pkg, "mit"));
+        assert!(has_license(&pkg, "MIT License"));
+        assert!(!has_license(&pkg, "GPL-3.0-only"));
+
+        // licenses which don't link to spdx.org
+        let pkg = NixPackage {
+            package_license: vec![PackageLicense {
+                full_name: "Business Source License 1.1".to_owned(),
+                url: Some("https://mariadb.com/bsl11/".to_owned()),
+            }],
+            ..Default::default()
+        };
+        assert!(has_license(&pkg, "BUSL-1.1"));
+    }
+
+    #[test]
+    fn test_query_limit() {
+        let mut options = SearchOptions {
+            channel: "unstable".to_owned(),
+            limit: 10,
+            platform: None,
+            maintainer: None,
+            license: None,
+            format: SearchOutputFormat::Pretty,
+        };
+        assert_eq!(options.query_limit(), 10);
+
+        options.maintainer = Some("andystopia".to_owned());
+        assert_eq!(options.query_limit(), 10 * FILTERED_OVERFETCH);
+
+        options.limit = u32::MAX;
+        assert_eq!(options.query_limit(), u32::MAX);
+    }
+
+    #[test]
+    fn test_search_output_format_from_flags() {
+        use SearchOutputFormat::*;
+        assert_eq!(SearchOutputFormat::from_flags(Tsv, false, false), Tsv);
+        assert_eq!(SearchOutputFormat::from_flags(Pretty, true, false), Json);
+        assert_eq!(
+            SearchOutputFormat::from_flags(Pretty, true, true),
+            JsonLines
+        );
+    }
+
+    #[test]
+    fn test_json_output_round_trips() {
+        let pkgs = packages();
+        let mut out = Vec::new();
+        output::write_json(&mut out, &pkgs).unwrap();
+
+        let parsed: Vec<NixPackage> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(names(&parsed), names(&pkgs));
+        assert_eq!(parsed[1].package_platforms, ["x86_64-linux"]);
+    }
+
+    #[test]
+    fn test_tsv_output() {
+        let pkgs = vec![
+            NixPackage {
+                package_attr_name: "ripgrep".to_owned(),
+                package_pversion: "14.1.0".to_owned(),
+                package_description: Some("a fast\tgrep,\nin rust".to_owned()),
+                ..Default::default()
+            },
+            NixPackage {
+                package_attr_name: "gleam".to_owned(),
+                package_pversion: "1.2.0".to_owned(),
+                ..Default::default()
+            },
+        ];
+        let mut out = Vec::new();
+        write_tsv(&mut out, &pkgs).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "ripgrep\t14.1.0\ta fast grep, in rust\ngleam\t1.2.0\t\n"
+        );
+    }
+}
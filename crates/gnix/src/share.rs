@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+
+use nix_installed_list::{CachePackages, Root};
+use serde::{Deserialize, Serialize};
+
+/// a package in an exported package list, the name and version are
+/// only there to make the file easier to read, importing ignores them.
+/// the attr path leaves out the system, so that the list can be
+/// imported on another one, see [`without_system`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedPackage {
+    pub attr_path: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl ExportedPackage {
+    /// what to hand to `nix profile install`, nix looks the
+    /// attr path up under the system it's running on.
+    pub fn installable(&self) -> String {
+        format!("{}#{}", self.url, without_system(&self.attr_path))
+    }
+}
+
+/// the attr path without the `legacyPackages.<system>.` or
+/// `packages.<system>.` that the profile stores it under.
+pub fn without_system(attr_path: &str) -> &str {
+    match attr_path.splitn(3, '.').collect::<Vec<_>>()[..] {
+        ["legacyPackages" | "packages", _, attr] => attr,
+        _ => attr_path,
+    }
+}
+
+/// every package in the cache, whatever details have
+/// already been fetched are included.
+pub fn export_packages(cache: &CachePackages) -> Vec<ExportedPackage> {
+    cache
+        .packages
+        .iter()
+        .map(|package| ExportedPackage {
+            attr_path: without_system(&package.attr_path).to_owned(),
+            url: package.url.clone(),
+            name: package.enriched.then(|| package.name.clone()),
+            version: package.version.clone(),
+        })
+        .collect()
+}
+
+/// the packages in `packages` which aren't installed in `root` yet,
+/// whichever system either of them was on.
+pub fn packages_to_import<'a>(
+    packages: &'a [ExportedPackage],
+    root: &Root,
+) -> Vec<&'a ExportedPackage> {
+    let installed = root
+        .elements
+        .packages
+        .values()
+        .map(|package| without_system(&package.attr_path))
+        .collect::<HashSet<_>>();
+    packages
+        .iter()
+        .filter(|package| !installed.contains(without_system(&package.attr_path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use nix_installed_list::Package;
+
+    use super::*;
+
+    fn root(system: &str, names: &[&str]) -> Root {
+        let mut root = Root::default();
+        for name in names {
+            root.elements.packages.insert(
+                name.to_string(),
+                Package {
+                    attr_path: format!("legacyPackages.{system}.{name}"),
+                    original_url: "flake:nixpkgs".to_owned(),
+                    url: "github:NixOS/nixpkgs/abc123".to_owned(),
+                    ..Default::default()
+                },
+            );
+        }
+        root
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let ours = root("x86_64-linux", &["gleam", "ripgrep"]);
+        let exported = export_packages(&CachePackages::from_manifest_minimal(&ours));
+        let json = serde_json::to_string_pretty(&exported).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value[0],
+            serde_json::json!({
+                "attr_path": "gleam",
+                "url": "github:NixOS/nixpkgs/abc123",
+            })
+        );
+
+        let imported: Vec<ExportedPackage> = serde_json::from_str(&json).unwrap();
+        assert_eq!(imported, exported);
+
+        // importing into the same profile has nothing to do.
+        assert!(packages_to_import(&imported, &ours).is_empty());
+
+        // but into another, on another system, only the
+        // missing packages are installed, for that system.
+        let theirs = root("aarch64-darwin", &["ripgrep", "lazygit"]);
+        let to_import = packages_to_import(&imported, &theirs);
+        assert_eq!(to_import.len(), 1);
+        assert_eq!(
+            to_import[0].installable(),
+            "github:NixOS/nixpkgs/abc123#gleam"
+        );
+    }
+
+    #[test]
+    fn test_without_system() {
+        assert_eq!(without_system("legacyPackages.x86_64-linux.gleam"), "gleam");
+        assert_eq!(
+            without_system("legacyPackages.aarch64-darwin.python3Packages.numpy"),
+            "python3Packages.numpy"
+        );
+        assert_eq!(without_system("packages.x86_64-linux.default"), "default");
+        assert_eq!(without_system("gleam"), "gleam");
+
+        // lists exported before the system was left out still import
+        let old = ExportedPackage {
+            attr_path: "legacyPackages.x86_64-linux.gleam".to_owned(),
+            url: "github:NixOS/nixpkgs/abc123".to_owned(),
+            name: None,
+            version: None,
+        };
+        assert_eq!(old.installable(), "github:NixOS/nixpkgs/abc123#gleam");
+        let theirs = root("aarch64-darwin", &["gleam"]);
+        assert!(packages_to_import(std::slice::from_ref(&old), &theirs).is_empty());
+    }
+}
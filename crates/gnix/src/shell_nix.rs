@@ -0,0 +1,174 @@
+use std::fmt::Write;
+
+/// the name given to the shell when the user
+/// doesn't ask for anything in particular.
+pub const DEFAULT_SHELL_NAME: &str = "gnix-shell";
+
+/// channels can be given either as a bare version
+/// (24.05, unstable) or with the `nixos-` prefix
+/// already attached, we accept both.
+fn channel_branch(channel: &str) -> String {
+    if channel.starts_with("nixos-") || channel.starts_with("nixpkgs-") {
+        channel.to_owned()
+    } else {
+        format!("nixos-{channel}")
+    }
+}
+
+/// whether `channel` is something [`channel_branch`] can make a
+/// branch of, a version such as 24.05, or unstable.
+pub fn is_valid_channel(channel: &str) -> bool {
+    let channel = channel
+        .strip_prefix("nixos-")
+        .or_else(|| channel.strip_prefix("nixpkgs-"))
+        .unwrap_or(channel);
+    channel == "unstable" || nix_channel_list::parse_channel_version(channel).is_some()
+}
+
+/// quote `text` as a nix string literal, escaping anything
+/// which would otherwise end the string or interpolate.
+fn nix_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    format!("\"{escaped}\"")
+}
+
+/// produce a `shell.nix` which provides all of the given
+/// packages. when a channel is given, nixpkgs is fetched
+/// from that channel rather than taken from `<nixpkgs>`.
+pub fn shell_nix(packages: &[String], channel: Option<&str>, name: &str) -> String {
+    let nixpkgs = match channel {
+        Some(channel) => format!("(fetchTarball \"channel:{}\")", channel_branch(channel)),
+        None => "<nixpkgs>".to_owned(),
+    };
+
+    let mut out = String::new();
+    writeln!(out, "{{ pkgs ? import {nixpkgs} {{}} }}:").unwrap();
+    writeln!(out, "pkgs.mkShell {{").unwrap();
+    writeln!(out, "  name = {};", nix_string(name)).unwrap();
+    writeln!(out, "  packages = with pkgs; [").unwrap();
+    for pkg in packages {
+        writeln!(out, "    {pkg}").unwrap();
+    }
+    writeln!(out, "  ];").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// produce a `flake.nix` which exposes a default devShell
+/// for every system nixpkgs supports.
+pub fn flake_nix(packages: &[String], channel: Option<&str>, name: &str) -> String {
+    let url = match channel {
+        Some(channel) => format!("github:NixOS/nixpkgs/{}", channel_branch(channel)),
+        None => "github:NixOS/nixpkgs".to_owned(),
+    };
+
+    let mut out = String::new();
+    writeln!(out, "{{").unwrap();
+    writeln!(out, "  inputs.nixpkgs.url = \"{url}\";").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "  outputs = {{ self, nixpkgs }}:").unwrap();
+    writeln!(out, "    let").unwrap();
+    writeln!(
+        out,
+        "      forAllSystems = nixpkgs.lib.genAttrs nixpkgs.lib.systems.flakeExposed;"
+    )
+    .unwrap();
+    writeln!(out, "    in {{").unwrap();
+    writeln!(out, "      devShells = forAllSystems (system:").unwrap();
+    writeln!(
+        out,
+        "        let pkgs = nixpkgs.legacyPackages.${{system}}; in {{"
+    )
+    .unwrap();
+    writeln!(out, "          default = pkgs.mkShell {{").unwrap();
+    writeln!(out, "            name = {};", nix_string(name)).unwrap();
+    writeln!(out, "            packages = with pkgs; [").unwrap();
+    for pkg in packages {
+        writeln!(out, "              {pkg}").unwrap();
+    }
+    writeln!(out, "            ];").unwrap();
+    writeln!(out, "          }};").unwrap();
+    writeln!(out, "        }});").unwrap();
+    writeln!(out, "    }};").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pkgs(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_single_package() {
+        let out = shell_nix(&pkgs(&["cargo"]), None, DEFAULT_SHELL_NAME);
+        assert_eq!(
+            out,
+            r#"{ pkgs ? import <nixpkgs> {} }:
+pkgs.mkShell {
+  name = "gnix-shell";
+  packages = with pkgs; [
+    cargo
+  ];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_multiple_packages() {
+        let out = shell_nix(&pkgs(&["cargo", "gleam", "just"]), None, "dev");
+        assert!(out.contains("name = \"dev\";"));
+        assert!(out.contains("    cargo\n    gleam\n    just\n"));
+    }
+
+    #[test]
+    fn test_channel_pinned() {
+        let out = shell_nix(&pkgs(&["cargo"]), Some("24.05"), DEFAULT_SHELL_NAME);
+        assert!(out.starts_with("{ pkgs ? import (fetchTarball \"channel:nixos-24.05\") {} }:"));
+
+        let out = shell_nix(
+            &pkgs(&["cargo"]),
+            Some("nixos-unstable"),
+            DEFAULT_SHELL_NAME,
+        );
+        assert!(out.contains("\"channel:nixos-unstable\""));
+    }
+
+    #[test]
+    fn test_valid_channel() {
+        assert!(is_valid_channel("24.05"));
+        assert!(is_valid_channel("unstable"));
+        assert!(is_valid_channel("nixos-24.05"));
+        assert!(is_valid_channel("nixpkgs-unstable"));
+        assert!(!is_valid_channel("24.05\") {}; x = ("));
+        assert!(!is_valid_channel("stable"));
+        assert!(!is_valid_channel(""));
+    }
+
+    #[test]
+    fn test_name_escaped() {
+        let out = shell_nix(&pkgs(&["cargo"]), None, r#"my "dev" ${shell}\"#);
+        assert!(out.contains(r#"  name = "my \"dev\" \${shell}\\";"#));
+
+        let out = flake_nix(&pkgs(&["cargo"]), None, r#"a"b"#);
+        assert!(out.contains(r#"            name = "a\"b";"#));
+    }
+
+    #[test]
+    fn test_flake() {
+        let out = flake_nix(&pkgs(&["cargo", "gleam"]), Some("24.05"), "dev");
+        assert!(out.contains("inputs.nixpkgs.url = \"github:NixOS/nixpkgs/nixos-24.05\";"));
+        assert!(out.contains("nixpkgs.legacyPackages.${system}"));
+        assert!(out.contains("              cargo\n              gleam\n"));
+
+        let out = flake_nix(&pkgs(&["cargo"]), None, "dev");
+        assert!(out.contains("inputs.nixpkgs.url = \"github:NixOS/nixpkgs\";"));
+    }
+}
@@ -0,0 +1,86 @@
+use nix_installed_list::{CachePackage, CachePackages, Root};
+
+/// how similar a name has to be to an installed package
+/// for `gnix uninstall` to suggest it instead.
+pub const FUZZY_THRESHOLD: f64 = 0.7;
+
+/// the installed packages, named the way that `nix profile
+/// remove` knows them, rather than by their attr path.
+fn installed_by_element_name(root: &Root) -> CachePackages {
+    let mut elements = root.elements.packages.iter().collect::<Vec<_>>();
+    elements.sort_by_key(|(name, _)| name.as_str());
+    CachePackages {
+        packages: elements
+            .into_iter()
+            .map(|(name, package)| CachePackage {
+                name: name.clone(),
+                attr_path: package.attr_path.clone(),
+                url: package.url.clone(),
+                ..Default::default()
+            })
+            .collect(),
+    }
+}
+
+/// `nix profile remove` also takes store paths, indices, flake
+/// references and regexes, none of which should be fuzzy matched.
+fn looks_like_element_name(name: &str) -> bool {
+    !(name.starts_with('/')
+        || name.chars().all(|c| c.is_ascii_digit())
+        || name.contains(['#', ':']))
+}
+
+/// the installed package that `name` was most likely meant to be,
+/// there's no suggestion when `name` is installed exactly as it is.
+pub fn suggest(root: &Root, name: &str) -> Option<String> {
+    if !looks_like_element_name(name) || root.elements.packages.contains_key(name) {
+        return None;
+    }
+    installed_by_element_name(root)
+        .lookup_by_name_fuzzy(name, FUZZY_THRESHOLD)
+        .first()
+        .map(|package| package.name.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nix_installed_list::Package;
+
+    fn root() -> Root {
+        let mut root = Root::default();
+        for name in ["cargo-edit", "ripgrep", "gleam"] {
+            root.elements.packages.insert(
+                name.to_owned(),
+                Package {
+                    attr_path: format!("legacyPackages.x86_64-linux.{name}"),
+                    ..Default::default()
+                },
+            );
+        }
+        root
+    }
+
+    #[test]
+    fn test_suggest() {
+        let root = root();
+        assert_eq!(suggest(&root, "cargo_edit").as_deref(), Some("cargo-edit"));
+        assert_eq!(suggest(&root, "ripgerp").as_deref(), Some("ripgrep"));
+        assert_eq!(suggest(&root, "gleam"), None);
+        assert_eq!(suggest(&root, "firefox"), None);
+    }
+
+    #[test]
+    fn test_no_suggestion_for_other_arguments() {
+        let root = root();
+        for arg in [
+            "/nix/store/abc-ripgrep-14.1.0",
+            "3",
+            "nixpkgs#ripgrep",
+            "flake:nixpkgs#gleam",
+            "regex:ripgre.",
+        ] {
+            assert_eq!(suggest(&root, arg), None, "{arg}");
+        }
+    }
+}
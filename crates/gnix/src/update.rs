@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::cache;
+
+pub const RELEASES_URL: &str = "https://api.github.com/repos/andystopia/newt/releases/latest";
+
+/// how long a check is trusted before asking github again.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    #[error("IO error: {source}")]
+    Io {
+        #[from]
+        source: std::io::Error,
+    },
+    #[error("failed to reach github: {source}")]
+    Http { source: Box<ureq::Error> },
+    #[error("unexpected response from github: {source}")]
+    SerdeJson {
+        #[from]
+        source: serde_json::Error,
+    },
+    #[error("`{tag}` is not a valid version: {source}")]
+    InvalidVersion { tag: String, source: semver::Error },
+}
+
+// ureq's error is large, so it's boxed to
+// keep every other result small.
+impl From<ureq::Error> for UpdateError {
+    fn from(source: ureq::Error) -> Self {
+        UpdateError::Http {
+            source: Box::new(source),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateStatus {
+    UpToDate(Version),
+    Available { current: Version, latest: Version },
+}
+
+/// the result of the last check, kept in
+/// ~/.cache/gnix/update-check.json
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateCheck {
+    /// seconds since the unix epoch
+    pub checked_at: u64,
+    pub latest: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+pub fn update_check_path() -> PathBuf {
+    cache::cache_dir().join("update-check.json")
+}
+
+pub fn current_version() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).expect("the crate version is valid semver")
+}
+
+/// release tags are usually written as v1.2.3
+fn parse_tag(tag: &str) -> Result<Version, UpdateError> {
+    Version::parse(tag.trim().trim_start_matches('v')).map_err(|source| {
+        UpdateError::InvalidVersion {
+            tag: tag.to_owned(),
+            source,
+        }
+    })
+}
+
+/// pull the version out of the body of
+/// github's latest release endpoint.
+pub fn parse_release(body: &str) -> Result<Version, UpdateError> {
+    let release: Release = serde_json::from_str(body)?;
+    parse_tag(&release.tag_name)
+}
+
+pub fn fetch_latest_release() -> Result<String, UpdateError> {
+    let response = ureq::get(RELEASES_URL)
+        .set("Accept", "application/vnd.github+json")
+        .set("User-Agent", concat!("gnix/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(5))
+        .call()?;
+    Ok(response.into_string()?)
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn read_cached(path: &Path, now: SystemTime) -> Option<Version> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let cached: UpdateCheck = serde_json::from_str(&text).ok()?;
+    let age = unix_secs(now).checked_sub(cached.checked_at)?;
+    if age >= CHECK_INTERVAL.as_secs() {
+        return None;
+    }
+    parse_tag(&cached.latest).ok()
+}
+
+/// the latest released version, which comes from the cache at
+/// `cache_path` when it's recent enough, and otherwise from `fetch`,
+/// which should return the body of the latest release endpoint.
+pub fn latest_version(
+    cache_path: &Path,
+    now: SystemTime,
+    fetch: impl FnOnce() -> Result<String, UpdateError>,
+) -> Result<Version, UpdateError> {
+    if let Some(latest) = read_cached(cache_path, now) {
+        return Ok(latest);
+    }
+
+    let latest = parse_release(&fetch()?)?;
+    let check = UpdateCheck {
+        checked_at: unix_secs(now),
+        latest: latest.to_string(),
+    };
+    // failing to cache just means we check again next time.
+    if let Some(parent) = cache_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::write(cache_path, serde_json::to_string(&check)?);
+
+    Ok(latest)
+}
+
+pub fn compare(current: Version, latest: Version) -> UpdateStatus {
+    if latest > current {
+        UpdateStatus::Available { current, latest }
+    } else {
+        UpdateStatus::UpToDate(current)
+    }
+}
+
+pub fn check_for_updates() -> Result<UpdateStatus, UpdateError> {
+    let latest = latest_version(
+        &update_check_path(),
+        SystemTime::now(),
+        fetch_latest_release,
+    )?;
+    Ok(compare(current_version(), latest))
+}
+
+impl std::fmt::Display for UpdateStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateStatus::UpToDate(current) => write!(f, "Up to date: v{current}"),
+            UpdateStatus::Available { current, latest } => {
+                write!(f, "Update available: v{current} → v{latest}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RELEASE_BODY: &str = r#"{
+        "url": "https://api.github.com/repos/andystopia/newt/releases/1",
+        "tag_name": "v1.3.0",
+        "name": "gnix 1.3.0",
+        "draft": false,
+        "prerelease": false
+    }"#;
+
+    fn version(v: &str) -> Version {
+        Version::parse(v).unwrap()
+    }
+
+    #[test]
+    fn test_parse_release() {
+        assert_eq!(parse_release(RELEASE_BODY).unwrap(), version("1.3.0"));
+        assert_eq!(
+            parse_release(r#"{"tag_name": "0.2.0"}"#).unwrap(),
+            version("0.2.0")
+        );
+        assert!(matches!(
+            parse_release(r#"{"tag_name": "nightly"}"#),
+            Err(UpdateError::InvalidVersion { .. })
+        ));
+        assert!(matches!(
+            parse_release(r#"{"message": "Not Found"}"#),
+            Err(UpdateError::SerdeJson { .. })
+        ));
+    }
+
+    #[test]
+    fn test_compare() {
+        let status = compare(version("1.2.3"), version("1.3.0"));
+        assert_eq!(status.to_string(), "Update available: v1.2.3 → v1.3.0");
+
+        let status = compare(version("1.3.0"), version("1.3.0"));
+        assert_eq!(status.to_string(), "Up to date: v1.3.0");
+
+        // a local build ahead of the latest release is up to date too.
+        let status = compare(version("1.4.0"), version("1.3.0"));
+        assert_eq!(status, UpdateStatus::UpToDate(version("1.4.0")));
+    }
+
+    #[test]
+    fn test_latest_version_is_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gnix").join("update-check.json");
+        let now = SystemTime::now();
+
+        let latest = latest_version(&path, now, || Ok(RELEASE_BODY.to_owned())).unwrap();
+        assert_eq!(latest, version("1.3.0"));
+        assert!(path.is_file());
+
+        // within the interval, github isn't asked again.
+        let later = now + Duration::from_secs(60 * 60);
+        let latest = latest_version(&path, later, || panic!("should be cached")).unwrap();
+        assert_eq!(latest, version("1.3.0"));
+
+        // but once it's passed, it is.
+        let much_later = now + CHECK_INTERVAL;
+        let latest = latest_version(&path, much_later, || {
+            Ok(r#"{"tag_name": "v1.4.0"}"#.to_owned())
+        })
+        .unwrap();
+        assert_eq!(latest, version("1.4.0"));
+    }
+
+    #[test]
+    fn test_failed_fetch_is_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("update-check.json");
+        let now = SystemTime::now();
+
+        assert!(latest_version(&path, now, || Ok("<html>".to_owned())).is_err());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,148 @@
+use std::process::Command;
+
+use color_eyre::owo_colors::OwoColorize;
+use nix_installed_list::{Package, Profile};
+
+use crate::install;
+
+/// which packages were upgraded, and which nix wouldn't upgrade.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct UpgradeSummary {
+    pub upgraded: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+/// the command which upgrades a single package, given by the name of
+/// its element in the profile, with `--dry-run`, nix works out what
+/// would change without changing it.
+pub fn upgrade_command(profile: &Profile, name: &str, dry_run: bool) -> Command {
+    let mut nix = profile.nix_profile("upgrade");
+    if dry_run {
+        nix.arg("--dry-run");
+    }
+    nix.arg(name);
+    nix
+}
+
+/// a line for each package which will be upgraded.
+pub fn upgrade_plan(packages: &[(String, Package)]) -> Vec<String> {
+    packages
+        .iter()
+        .map(|(name, package)| {
+            format!(
+                "{} from {}",
+                name.bold(),
+                install::style_src(&package.original_url)
+            )
+        })
+        .collect()
+}
+
+pub fn pretty_print_upgrade_plan(packages: &[(String, Package)]) {
+    println!("would upgrade {} packages:", packages.len());
+    for line in upgrade_plan(packages) {
+        println!("  {line}");
+    }
+}
+
+/// upgrade each package one at a time, so that a
+/// package which fails doesn't stop the rest.
+pub fn upgrade_packages(
+    profile: &Profile,
+    packages: &[(String, Package)],
+    dry_run: bool,
+) -> UpgradeSummary {
+    let mut summary = UpgradeSummary::default();
+    for (name, _) in packages {
+        let status = upgrade_command(profile, name, dry_run).status();
+        match status {
+            Ok(status) if status.success() => summary.upgraded.push(name.clone()),
+            _ => summary.failed.push(name.clone()),
+        }
+    }
+    summary
+}
+
+/// what's printed once the upgrade is done, a dry run has
+/// already printed the plan, so it isn't listed again.
+pub fn summary_lines(summary: &UpgradeSummary, dry_run: bool) -> Vec<String> {
+    let upgraded = if dry_run { "would upgrade" } else { "upgraded" };
+    let mut lines = Vec::new();
+    if !dry_run {
+        for name in &summary.upgraded {
+            lines.push(format!("{} {name}", "upgraded:".green().bold()));
+        }
+    }
+    for name in &summary.failed {
+        lines.push(format!("{} {name}", "failed:".red().bold()));
+    }
+    lines.push(format!(
+        "{} {upgraded}, {} failed",
+        summary.upgraded.len(),
+        summary.failed.len()
+    ));
+    lines
+}
+
+pub fn print_summary(summary: &UpgradeSummary, dry_run: bool) {
+    for line in summary_lines(summary, dry_run) {
+        println!("{line}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn package(name: &str) -> (String, Package) {
+        let package = Package {
+            attr_path: format!("legacyPackages.x86_64-linux.{name}"),
+            original_url: "flake:nixpkgs".to_owned(),
+            ..Default::default()
+        };
+        (name.to_owned(), package)
+    }
+
+    #[test]
+    fn test_dry_run_command() {
+        let cmd = upgrade_command(&Profile::default(), "ripgrep", true);
+        assert_eq!(
+            cmd.get_args().collect::<Vec<_>>(),
+            ["profile", "upgrade", "--dry-run", "ripgrep"]
+        );
+
+        let cmd = upgrade_command(&Profile::default(), "ripgrep", false);
+        assert!(!cmd.get_args().any(|arg| arg == "--dry-run"));
+    }
+
+    #[test]
+    fn test_upgrade_plan() {
+        let plan = upgrade_plan(&[package("ripgrep"), package("gleam")]);
+        assert_eq!(plan.len(), 2);
+        assert!(plan[0].contains("ripgrep"));
+        assert!(!plan[0].contains("legacyPackages"));
+        assert!(plan[0].contains("nixpkgs"));
+        assert!(plan[1].contains("gleam"));
+
+        assert!(upgrade_plan(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_summary_lines() {
+        let summary = UpgradeSummary {
+            upgraded: vec!["ripgrep".to_owned(), "gleam".to_owned()],
+            failed: vec!["broken".to_owned()],
+        };
+        let lines = summary_lines(&summary, false);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("ripgrep"));
+        assert!(lines[2].contains("broken"));
+        assert_eq!(lines[3], "2 upgraded, 1 failed");
+
+        // the plan has already listed them for a dry run
+        let lines = summary_lines(&summary, true);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("broken"));
+        assert_eq!(lines[1], "2 would upgrade, 1 failed");
+    }
+}
@@ -0,0 +1,91 @@
+use color_eyre::owo_colors::OwoColorize;
+use nix_installed_list::Root;
+
+/// an installed package, along with any of its
+/// store paths which are no longer on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedPackage {
+    pub name: String,
+    pub missing: Vec<String>,
+}
+
+impl VerifiedPackage {
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// check every package in the profile, sorted by name.
+pub fn verify_packages(root: &Root) -> Vec<VerifiedPackage> {
+    let mut verified = root
+        .elements
+        .packages
+        .iter()
+        .map(|(name, package)| VerifiedPackage {
+            name: name.clone(),
+            missing: if package.check_store_valid() {
+                Vec::new()
+            } else {
+                package.missing_store_paths()
+            },
+        })
+        .collect::<Vec<_>>();
+    verified.sort_by(|a, b| a.name.cmp(&b.name));
+    verified
+}
+
+pub fn print_verified(package: &VerifiedPackage) {
+    if package.is_valid() {
+        println!("{} {}", "✓".green().bold(), package.name);
+    } else {
+        println!(
+            "{} {} (missing: {})",
+            "✗".red().bold(),
+            package.name,
+            package.missing.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nix_installed_list::Package;
+
+    use super::*;
+
+    #[test]
+    fn test_verify_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let present = dir.path().join("abc-ripgrep").display().to_string();
+        let missing = dir.path().join("xyz-gleam").display().to_string();
+        std::fs::create_dir(&present).unwrap();
+
+        let mut root = Root::default();
+        for (name, store_path) in [("ripgrep", &present), ("gleam", &missing)] {
+            root.elements.packages.insert(
+                name.to_owned(),
+                Package {
+                    store_paths: vec![store_path.clone()],
+                    ..Default::default()
+                },
+            );
+        }
+
+        let verified = verify_packages(&root);
+        assert_eq!(
+            verified,
+            [
+                VerifiedPackage {
+                    name: "gleam".to_owned(),
+                    missing: vec![missing],
+                },
+                VerifiedPackage {
+                    name: "ripgrep".to_owned(),
+                    missing: vec![],
+                },
+            ]
+        );
+        assert!(!verified[0].is_valid());
+        assert!(verified[1].is_valid());
+    }
+}
@@ -0,0 +1,147 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// used when we aren't attached to a terminal,
+/// or can't otherwise figure out how wide it is.
+pub const FALLBACK_WIDTH: usize = 80;
+
+pub fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(w), _)| w as usize)
+        .unwrap_or(FALLBACK_WIDTH)
+}
+
+/// break `text` into lines which are at most `width` columns
+/// wide. words are kept whole where possible, but words which
+/// can't fit on a line of their own (which is common for CJK
+/// text, since it doesn't use spaces) are split between characters.
+pub fn wrap_lines(text: &str, width: usize) -> Vec<String> {
+    // always make progress, even on absurdly narrow terminals.
+    let width = width.max(1);
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+
+    for word in text.split_whitespace() {
+        let word_width = word.width();
+        let separator = usize::from(!line.is_empty());
+
+        if line_width + separator + word_width <= width {
+            if separator == 1 {
+                line.push(' ');
+            }
+            line.push_str(word);
+            line_width += separator + word_width;
+            continue;
+        }
+
+        if !line.is_empty() && word_width <= width {
+            lines.push(std::mem::take(&mut line));
+            line.push_str(word);
+            line_width = word_width;
+            continue;
+        }
+
+        // the word is too long for any line, so fill up
+        // what's left of this line and spill over.
+        if !line.is_empty() {
+            line.push(' ');
+            line_width += 1;
+        }
+        for c in word.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if line_width + char_width > width && line_width > 0 {
+                lines.push(std::mem::take(&mut line).trim_end().to_owned());
+                line_width = 0;
+            }
+            line.push(c);
+            line_width += char_width;
+        }
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// word wrap `text` so that it fits within `width` columns,
+/// where the first `indent` columns of every line are taken
+/// up by indentation. the caller is expected to have printed
+/// something `indent` columns wide before the first line,
+/// continuation lines are indented with spaces.
+pub fn wrap_text(text: &str, width: usize, indent: usize) -> String {
+    let available = width.saturating_sub(indent);
+    wrap_lines(text, available).join(&format!("\n{}", " ".repeat(indent)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wrap_ascii() {
+        let text = "A fast, friendly, functional language for building type-safe systems";
+        assert_eq!(
+            wrap_text(text, 24, 0),
+            "A fast, friendly,\nfunctional language for\nbuilding type-safe\nsystems"
+        );
+        assert_eq!(
+            wrap_text(text, 24, 3),
+            "A fast, friendly,\n   functional language\n   for building\n   type-safe systems"
+        );
+    }
+
+    #[test]
+    fn test_wrap_fits_on_one_line() {
+        assert_eq!(wrap_text("short text", 80, 3), "short text");
+        assert_eq!(wrap_text("", 80, 3), "");
+        assert_eq!(
+            wrap_text("  collapses   whitespace ", 80, 0),
+            "collapses whitespace"
+        );
+    }
+
+    #[test]
+    fn test_wrap_long_word() {
+        assert_eq!(wrap_text("abcdefghij", 4, 0), "abcd\nefgh\nij");
+        assert_eq!(wrap_text("xy abcdefghij", 4, 0), "xy a\nbcde\nfghi\nj");
+    }
+
+    #[test]
+    fn test_wrap_cjk() {
+        // every one of these characters is two columns wide.
+        let text = "日本語のテキスト";
+        let wrapped = wrap_text(text, 6, 0);
+        assert_eq!(wrapped, "日本語\nのテキ\nスト");
+        for line in wrapped.lines() {
+            assert!(line.width() <= 6);
+        }
+
+        // an odd width can't fit a whole extra character.
+        assert_eq!(wrap_text(text, 7, 0), "日本語\nのテキ\nスト");
+        assert_eq!(wrap_text(text, 9, 3), "日本語\n   のテキ\n   スト");
+    }
+
+    #[test]
+    fn test_wrap_mixed_width() {
+        let text = "nix パッケージ manager";
+        let wrapped = wrap_text(text, 10, 0);
+        assert_eq!(wrapped, "nix\nパッケージ\nmanager");
+        for line in wrapped.lines() {
+            assert!(line.width() <= 10);
+        }
+
+        let wrapped = wrap_text(text, 14, 2);
+        for (i, line) in wrapped.lines().enumerate() {
+            let indent = if i == 0 { 2 } else { 0 };
+            assert!(line.width() + indent <= 14, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn test_wrap_degenerate_width() {
+        assert_eq!(wrap_text("ab", 0, 0), "a\nb");
+        assert_eq!(wrap_text("ab", 2, 5), "a\n     b");
+    }
+}
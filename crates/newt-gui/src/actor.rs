@@ -1,3 +1,6 @@
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
 use std::{sync::Arc, thread::JoinHandle};
 
 use floem::{ext_event::create_signal_from_channel, reactive::ReadSignal};
@@ -5,12 +8,75 @@ use parking_lot::Mutex;
 
 pub enum ActorMessage<SendToActor> {
     Shutdown,
-    Custom(SendToActor),
+    /// a message, along with the generation it was sent
+    /// in and the token which cancels it.
+    Custom(u64, CancellationToken, SendToActor),
+}
+
+/// tracks which responses are still wanted, every message gets the
+/// next generation, and anything older than the latest cancelling
+/// send is stale, because a newer request has replaced it.
+#[derive(Default)]
+struct Generations {
+    next: AtomicU64,
+    oldest_wanted: AtomicU64,
+}
+
+impl Generations {
+    fn issue(&self) -> u64 {
+        self.next.fetch_add(1, Ordering::SeqCst)
+    }
+
+    fn is_stale(&self, generation: u64) -> bool {
+        generation < self.oldest_wanted.load(Ordering::SeqCst)
+    }
+}
+
+/// lets whoever sent a message tell the actor to give up on it,
+/// it's up to the actor to check in on it every so often.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// wait for a child process to exit, killing it if the token is
+/// cancelled first, in which case there's no exit status.
+pub fn wait_or_kill(
+    child: &mut Child,
+    token: &CancellationToken,
+) -> std::io::Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if token.is_cancelled() {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
 }
 
 pub struct ActorThread<SendToActor, RecvFromActor> {
     sender: crossbeam::channel::Sender<ActorMessage<SendToActor>>,
-    receiver: crossbeam::channel::Receiver<RecvFromActor>,
+    /// the other end of `sender`, so that we can take back
+    /// messages which the actor hasn't got to yet.
+    pending: crossbeam::channel::Receiver<ActorMessage<SendToActor>>,
+    receiver: crossbeam::channel::Receiver<(u64, RecvFromActor)>,
+    generations: Arc<Generations>,
+    /// the token for the message the actor is working on.
+    current: Arc<Mutex<CancellationToken>>,
     handle: Option<JoinHandle<()>>,
 }
 
@@ -24,13 +90,16 @@ impl<SendToActor, RecvFromActor> Drop for ActorThread<SendToActor, RecvFromActor
 impl<SendToActor: Send + 'static, RecvFromActor: Send + Clone + 'static>
     ActorThread<SendToActor, RecvFromActor>
 {
-    pub fn new<F: Fn(SendToActor) -> RecvFromActor + Send + 'static>(
+    pub fn new<F: Fn(SendToActor, CancellationToken) -> RecvFromActor + Send + 'static>(
         f: F,
     ) -> ActorThread<SendToActor, RecvFromActor> {
         let (send_to_director, recv_from_actor) = crossbeam::channel::unbounded();
         let (send_to_actor, recv_from_director) =
             crossbeam::channel::unbounded::<ActorMessage<SendToActor>>();
 
+        let pending = recv_from_director.clone();
+        let current = Arc::new(Mutex::new(CancellationToken::default()));
+        let actor_current = current.clone();
         let handle = std::thread::spawn(move || {
             let recv = recv_from_director;
             let send = send_to_director;
@@ -41,16 +110,21 @@ impl<SendToActor: Send + 'static, RecvFromActor: Send + Clone + 'static>
                     ActorMessage::Shutdown => {
                         break;
                     }
-                    ActorMessage::Custom(val) => {
-                        let res = f(val);
-                        send.send(res).expect("actor thread couldn't send");
+                    ActorMessage::Custom(generation, token, val) => {
+                        actor_current.lock().clone_from(&token);
+                        let res = f(val, token);
+                        send.send((generation, res))
+                            .expect("actor thread couldn't send");
                     }
                 }
             }
         });
         Self {
             sender: send_to_actor,
+            pending,
             receiver: recv_from_actor,
+            generations: Arc::default(),
+            current,
             handle: Some(handle),
         }
     }
@@ -59,19 +133,157 @@ impl<SendToActor: Send + 'static, RecvFromActor: Send + Clone + 'static>
         &self,
         message: SendToActor,
     ) -> Result<(), crossbeam::channel::SendError<ActorMessage<SendToActor>>> {
-        self.sender.send(ActorMessage::Custom(message))
+        self.send_cancellable(message).map(drop)
+    }
+
+    /// like [`ActorThread::send`], but hands back the token for this
+    /// message, which cancels it whether it's running or still queued.
+    pub fn send_cancellable(
+        &self,
+        message: SendToActor,
+    ) -> Result<CancellationToken, crossbeam::channel::SendError<ActorMessage<SendToActor>>> {
+        let generation = self.generations.issue();
+        let token = CancellationToken::default();
+        self.sender
+            .send(ActorMessage::Custom(generation, token.clone(), message))?;
+        Ok(token)
+    }
+
+    /// send a message which replaces everything sent before it, messages
+    /// which the actor hasn't started on are dropped, and the response
+    /// to any message it's working on now won't be received.
+    pub fn send_cancelling_previous(
+        &self,
+        message: SendToActor,
+    ) -> Result<(), crossbeam::channel::SendError<ActorMessage<SendToActor>>> {
+        // shutdown is only sent on drop, so everything
+        // here is a message we can safely throw away.
+        while self.pending.try_recv().is_ok() {}
+        let generation = self.generations.issue();
+        self.generations
+            .oldest_wanted
+            .store(generation, Ordering::SeqCst);
+        self.sender.send(ActorMessage::Custom(
+            generation,
+            CancellationToken::default(),
+            message,
+        ))
+    }
+
+    /// ask the actor to stop working on the message it's on now.
+    pub fn cancel_current(&self) {
+        self.current.lock().cancel();
     }
 
     /// receive a message from an actor, if there is one
     /// avaiable, otherwise None will be returned
     pub fn recv(&self) -> Option<RecvFromActor> {
-        match self.receiver.try_recv() {
-            Ok(v) => Some(v),
-            Err(crossbeam::channel::TryRecvError::Empty) => None,
-            _ => panic!("failed to receive message from actor"),
+        loop {
+            match self.receiver.try_recv() {
+                Ok((generation, _)) if self.generations.is_stale(generation) => continue,
+                Ok((_, v)) => return Some(v),
+                Err(crossbeam::channel::TryRecvError::Empty) => return None,
+                _ => panic!("failed to receive message from actor"),
+            }
         }
     }
 
+    pub fn recv_blocking(&self) -> RecvFromActor {
+        loop {
+            let (generation, v) = self.receiver.recv().unwrap();
+            if !self.generations.is_stale(generation) {
+                return v;
+            }
+        }
+    }
+
+    pub fn create_channel_from_receiver(&self) -> ReadSignal<Option<RecvFromActor>> {
+        // floem wants a channel, so stale responses are
+        // filtered out on their way into a fresh one.
+        let (send, recv) = crossbeam::channel::unbounded();
+        let receiver = self.receiver.clone();
+        let generations = self.generations.clone();
+        std::thread::spawn(move || {
+            for (generation, v) in receiver {
+                if !generations.is_stale(generation) && send.send(v).is_err() {
+                    break;
+                }
+            }
+        });
+        create_signal_from_channel(recv)
+    }
+}
+
+/// like an `ActorThread`, but with several workers pulling from the
+/// same queue, so that a slow message doesn't hold up the rest.
+/// responses come back in whatever order the workers finish in.
+pub struct ActorPool<SendToActor, RecvFromActor> {
+    sender: crossbeam::channel::Sender<ActorMessage<SendToActor>>,
+    receiver: crossbeam::channel::Receiver<RecvFromActor>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl<SendToActor, RecvFromActor> Drop for ActorPool<SendToActor, RecvFromActor> {
+    fn drop(&mut self) {
+        // each worker stops after taking a single shutdown
+        for _ in &self.handles {
+            self.sender.send(ActorMessage::Shutdown).unwrap();
+        }
+        for handle in self.handles.drain(..) {
+            handle.join().unwrap();
+        }
+    }
+}
+
+impl<SendToActor: Send + 'static, RecvFromActor: Send + Clone + 'static>
+    ActorPool<SendToActor, RecvFromActor>
+{
+    pub fn new<F: Fn(SendToActor) -> RecvFromActor + Send + Sync + 'static>(
+        n: usize,
+        f: F,
+    ) -> ActorPool<SendToActor, RecvFromActor> {
+        assert!(n > 0, "an actor pool needs at least one worker");
+        let (send_to_director, recv_from_actor) = crossbeam::channel::unbounded();
+        let (send_to_actor, recv_from_director) =
+            crossbeam::channel::unbounded::<ActorMessage<SendToActor>>();
+
+        let f = Arc::new(f);
+        let handles = (0..n)
+            .map(|_| {
+                let recv = recv_from_director.clone();
+                let send = send_to_director.clone();
+                let f = f.clone();
+                std::thread::spawn(move || loop {
+                    match recv.recv().expect("actor pool couldn't receive") {
+                        ActorMessage::Shutdown => break,
+                        ActorMessage::Custom(_, _, val) => {
+                            send.send(f(val)).expect("actor pool couldn't send");
+                        }
+                    }
+                })
+            })
+            .collect();
+        Self {
+            sender: send_to_actor,
+            receiver: recv_from_actor,
+            handles,
+        }
+    }
+
+    /// queue up a message for the next free worker.
+    pub fn send(
+        &self,
+        message: SendToActor,
+    ) -> Result<(), crossbeam::channel::SendError<ActorMessage<SendToActor>>> {
+        // the pool doesn't cancel anything, so every
+        // response is wanted and generations don't matter.
+        self.sender.send(ActorMessage::Custom(
+            0,
+            CancellationToken::default(),
+            message,
+        ))
+    }
+
     pub fn recv_blocking(&self) -> RecvFromActor {
         self.receiver.recv().unwrap()
     }
@@ -83,8 +295,117 @@ impl<SendToActor: Send + 'static, RecvFromActor: Send + Clone + 'static>
 
 #[test]
 pub fn double_it() {
-    let actor = ActorThread::new(|f: i32| f * 2);
+    let actor = ActorThread::new(|f: i32, _| f * 2);
 
     actor.send(3).unwrap();
     dbg!(actor.recv_blocking());
 }
+
+#[test]
+fn test_send_cancelling_previous() {
+    let (started_send, started) = crossbeam::channel::unbounded();
+    let (release_send, release) = crossbeam::channel::unbounded::<()>();
+    let actor = ActorThread::new(move |f: i32, _| {
+        started_send.send(()).unwrap();
+        release.recv().unwrap();
+        f * 2
+    });
+
+    // keep the actor busy on 1, while 2 and 3 queue up behind it
+    actor.send(1).unwrap();
+    started.recv().unwrap();
+    actor.send(2).unwrap();
+    actor.send(3).unwrap();
+    actor.send_cancelling_previous(4).unwrap();
+
+    // 2 and 3 never ran, and the answer for 1 is stale
+    release_send.send(()).unwrap();
+    release_send.send(()).unwrap();
+    assert_eq!(actor.recv_blocking(), 8);
+    assert_eq!(started.try_iter().count(), 1);
+    assert_eq!(actor.recv(), None);
+}
+
+#[test]
+fn test_actor_pool() {
+    let pool = ActorPool::new(4, |f: i32| {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        f * 2
+    });
+    for i in 0..10 {
+        pool.send(i).unwrap();
+    }
+    let mut results = (0..10).map(|_| pool.recv_blocking()).collect::<Vec<_>>();
+    results.sort_unstable();
+    assert_eq!(results, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_cancel_current() {
+    let (started_send, started) = crossbeam::channel::unbounded();
+    let actor = ActorThread::new(move |(): (), token: CancellationToken| {
+        started_send.send(token.is_cancelled()).unwrap();
+        // a task which would otherwise run for a very long time
+        for _ in 0..1000 {
+            if token.is_cancelled() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        false
+    });
+
+    actor.send(()).unwrap();
+    assert!(!started.recv().unwrap());
+    actor.cancel_current();
+    assert!(actor.recv_blocking());
+
+    // cancelling only applies to the message that was running,
+    // the next one starts out with a token of its own
+    actor.send(()).unwrap();
+    assert!(!started.recv().unwrap());
+    actor.cancel_current();
+    assert!(actor.recv_blocking());
+}
+
+#[test]
+fn test_send_cancellable() {
+    let (started_send, started) = crossbeam::channel::unbounded();
+    let (release_send, release) = crossbeam::channel::unbounded::<()>();
+    let actor = ActorThread::new(move |f: i32, token: CancellationToken| {
+        started_send.send(f).unwrap();
+        release.recv().unwrap();
+        (f, token.is_cancelled())
+    });
+
+    // cancelling 2 while it's queued behind 1 leaves 1 alone
+    let _one = actor.send_cancellable(1).unwrap();
+    assert_eq!(started.recv().unwrap(), 1);
+    let two = actor.send_cancellable(2).unwrap();
+    two.cancel();
+    release_send.send(()).unwrap();
+    assert_eq!(actor.recv_blocking(), (1, false));
+    release_send.send(()).unwrap();
+    assert_eq!(actor.recv_blocking(), (2, true));
+
+    // and a fresh message gets a fresh token
+    actor.send(3).unwrap();
+    release_send.send(()).unwrap();
+    assert_eq!(actor.recv_blocking(), (3, false));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_wait_or_kill() {
+    let token = CancellationToken::default();
+    let mut child = std::process::Command::new("sleep")
+        .arg("10")
+        .spawn()
+        .unwrap();
+    token.cancel();
+    assert_eq!(wait_or_kill(&mut child, &token).unwrap(), None);
+
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let status = wait_or_kill(&mut child, &CancellationToken::default()).unwrap();
+    assert!(status.unwrap().success());
+}
@@ -0,0 +1,199 @@
+use floem::{
+    cosmic_text::Weight,
+    peniko::Color,
+    reactive::RwSignal,
+    style::{CursorStyle, FlexWrap},
+    unit::Pct,
+    view::View,
+    views::{
+        dyn_container, empty, h_stack, h_stack_from_iter, scroll, static_label, v_stack,
+        v_stack_from_iter, Decorators,
+    },
+};
+use nix_elastic_search::response::{NixPackage, PackageLicense, PackageMaintainer};
+use nix_installed_list::nix_system;
+use nix_search_sort_utils::spdx_id;
+use tap::Pipe;
+
+use crate::search::NixPackageExt;
+use crate::theme::theme;
+use crate::SearchProperties;
+
+/// what to run in a terminal to install the package from `channel`,
+/// the same spec that the install button hands to nix.
+pub fn install_command(pkg: &NixPackage, channel: &str) -> String {
+    format!("nix profile install {}", pkg.nix_install_spec(channel))
+}
+
+/// the full name of a license, along with its SPDX
+/// identifier, if it's one that we know about.
+fn license_text(license: &PackageLicense) -> String {
+    match spdx_id(license) {
+        Some(spdx) => format!("{} ({spdx})", license.full_name),
+        None => license.full_name.clone(),
+    }
+}
+
+fn maintainer_name(maintainer: &PackageMaintainer) -> String {
+    match (&maintainer.name, &maintainer.github) {
+        (Some(name), _) => name.clone(),
+        (None, Some(github)) => format!("@{github}"),
+        (None, None) => maintainer.email.clone().unwrap_or_default(),
+    }
+}
+
+fn section(title: &'static str, body: impl View + 'static) -> impl View {
+    v_stack((
+        static_label(title).style(|s| {
+            s.font_weight(Weight::BOLD)
+                .font_size(theme().scaled(12.0))
+                .padding_bottom(4.0)
+        }),
+        body,
+    ))
+}
+
+fn link(text: String, url: String) -> impl View {
+    static_label(text)
+        .style(|s| s.color(theme().accent).cursor(CursorStyle::Pointer))
+        .on_click_stop(move |_| {
+            let _ = open::that(&url);
+        })
+}
+
+fn chip(text: String, highlight: bool) -> impl View {
+    static_label(text).style(move |s| {
+        s.padding_horiz(8.0)
+            .padding_vert(2.0)
+            .font_size(theme().scaled(10.0))
+            .background(Color::BLACK.with_alpha_factor(0.1))
+            .border(0.5)
+            .border_radius(Pct(100.0))
+            .border_color(theme().bd)
+            .apply_if(highlight, |s| {
+                s.background(theme().accent)
+                    .color(theme().fg_on_accent)
+                    .font_weight(Weight::SEMIBOLD)
+            })
+    })
+}
+
+fn chips(items: Vec<String>, highlighted: &'static str) -> impl View {
+    h_stack_from_iter(
+        items
+            .into_iter()
+            .map(move |item| chip(item.clone(), item == highlighted)),
+    )
+    .style(|s| s.flex_wrap(FlexWrap::Wrap).gap(3.0, 3.0).width_full())
+}
+
+fn package_details(pkg: NixPackage, channel: &str) -> impl View {
+    let command = install_command(&pkg, channel);
+    let copy_command = command.clone();
+
+    let homepages = v_stack_from_iter(
+        pkg.package_homepage
+            .into_iter()
+            .map(|url| link(url.clone(), url)),
+    );
+    let licenses = v_stack_from_iter(
+        pkg.package_license
+            .iter()
+            .map(|license| static_label(license_text(license))),
+    );
+    let maintainers = v_stack_from_iter(pkg.package_maintainers.iter().map(|maintainer| {
+        let name = maintainer_name(maintainer);
+        match &maintainer.github {
+            Some(github) => {
+                link(name, format!("https://github.com/{github}")).pipe(Box::new) as Box<dyn View>
+            }
+            None => static_label(name).pipe(Box::new),
+        }
+    }));
+
+    let install = h_stack((
+        static_label(command).style(|s| {
+            s.flex_grow(1.0)
+                .min_width(0)
+                .padding(6.0)
+                .font_size(theme().scaled(11.0))
+                .background(theme().bg_minus)
+                .border_radius(4.0)
+        }),
+        static_label("Copy")
+            .style(|s| {
+                s.padding_horiz(10.0)
+                    .padding_vert(4.0)
+                    .background(theme().accent)
+                    .color(theme().fg_on_accent)
+                    .border_radius(Pct(100.0))
+                    .font_size(theme().scaled(10.0))
+                    .cursor(CursorStyle::Pointer)
+            })
+            .on_click_stop(move |_| crate::copy_to_clipboard(copy_command.clone())),
+    ))
+    .style(|s| s.gap(5.0, 0.0).items_center());
+
+    v_stack((
+        static_label(pkg.package_attr_name)
+            .style(|s| s.font_weight(Weight::BOLD).font_size(theme().scaled(16.0))),
+        static_label(pkg.package_description.unwrap_or_default()),
+        static_label(pkg.package_long_description.unwrap_or_default())
+            .style(|s| s.color(theme().fg_minus)),
+        section("Homepage", homepages),
+        section("License", licenses),
+        section("Maintainers", maintainers),
+        section("Platforms", chips(pkg.package_platforms, nix_system())),
+        section("Outputs", chips(pkg.package_outputs, "")),
+        section("Install", install),
+    ))
+    .style(|s| s.gap(0.0, 12.0).padding(15.0).min_width(0).width_full())
+}
+
+/// everything we know about the selected package, nothing is
+/// shown at all when there isn't a package selected.
+pub fn package_detail_panel(
+    pkg: RwSignal<Option<NixPackage>>,
+    search_props: RwSignal<SearchProperties>,
+) -> impl View {
+    dyn_container(
+        move || (pkg.get(), search_props.with(|s| s.channel.clone())),
+        |(pkg, channel)| match pkg {
+            Some(pkg) => package_details(pkg, &channel)
+                .pipe(scroll)
+                .style(|s| {
+                    s.width(360.0)
+                        .height_full()
+                        .min_height(0)
+                        .background(theme().bg_plus)
+                        .border_left(1.0)
+                        .border_color(theme().bd)
+                })
+                .pipe(Box::new) as Box<dyn View>,
+            None => Box::new(empty()),
+        },
+    )
+    .style(|s| s.height_full().min_height(0))
+}
+
+#[test]
+fn test_install_command() {
+    let pkg = NixPackage {
+        package_attr_name: "ripgrep".to_owned(),
+        ..Default::default()
+    };
+    assert_eq!(
+        install_command(&pkg, "24.05"),
+        "nix profile install github:NixOS/nixpkgs/nixos-24.05#ripgrep"
+    );
+}
+
+#[test]
+fn test_license_text() {
+    let license = |full_name: &str| PackageLicense {
+        full_name: full_name.to_owned(),
+        url: None,
+    };
+    assert_eq!(license_text(&license("MIT License")), "MIT License (MIT)");
+    assert_eq!(license_text(&license("Unfree")), "Unfree");
+}
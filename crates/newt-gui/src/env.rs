@@ -18,20 +18,42 @@
 //     }
 // }
 
+use std::fmt::Write;
+
 use floem::{
     cosmic_text::Weight,
     reactive::{create_memo, RwSignal},
     style::FontWeight,
     unit::Pct,
     view::View,
-    views::{self, container, dyn_stack, empty, label, v_stack, Container, Decorators},
+    views::{self, container, dyn_stack, empty, h_stack, label, v_stack, Container, Decorators},
 };
+use nix_installed_list::Root;
+use nixhub_version_search::VersionLookup;
 
 use crate::{instr, theme};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Hash)]
 pub enum EnvEntryKind {
-    Simple { attr_name: String },
+    Simple {
+        attr_name: String,
+    },
+    /// a package from a particular commit of nixpkgs,
+    /// rather than whatever the channel has now.
+    Versioned {
+        attr_name: String,
+        nixpkgs_commit: String,
+    },
+}
+
+impl EnvEntryKind {
+    pub fn attr_name(&self) -> &str {
+        match self {
+            EnvEntryKind::Simple { attr_name } | EnvEntryKind::Versioned { attr_name, .. } => {
+                attr_name
+            }
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Hash)]
@@ -45,8 +67,27 @@ impl EnvEntry {
         match &self.kind {
             EnvEntryKind::Simple { attr_name } => {
                 let attr_name = attr_name.to_owned();
-                label(move || attr_name.to_owned())
-                    .style(|s| s.color(theme().fg).font_weight(Weight::SEMIBOLD))
+                Box::new(
+                    label(move || attr_name.to_owned())
+                        .style(|s| s.color(theme().fg).font_weight(Weight::SEMIBOLD)),
+                ) as Box<dyn View>
+            }
+            EnvEntryKind::Versioned {
+                attr_name,
+                nixpkgs_commit,
+            } => {
+                let attr_name = attr_name.to_owned();
+                let short_commit = nixpkgs_commit.chars().take(7).collect::<String>();
+                Box::new(
+                    h_stack((
+                        label(move || attr_name.to_owned())
+                            .style(|s| s.color(theme().fg).font_weight(Weight::SEMIBOLD)),
+                        label(|| "📌".to_owned()).style(|s| s.font_size(theme().scaled(10.0))),
+                        label(move || short_commit.clone())
+                            .style(|s| s.color(theme().fg_minus).font_size(theme().scaled(10.0))),
+                    ))
+                    .style(|s| s.gap(4.0, 0.0).items_center()),
+                )
             }
         }
     }
@@ -57,7 +98,38 @@ pub struct EnvironmentEntries {
     entries: Vec<EnvEntry>,
 }
 
+/// the attribute name of a package in a profile, which
+/// for nixpkgs is stored as `legacyPackages.<system>.<attr>`.
+fn profile_attr_name<'a>(name: &'a str, attr_path: &'a str) -> &'a str {
+    if attr_path.is_empty() {
+        return name;
+    }
+    match attr_path.splitn(3, '.').collect::<Vec<_>>()[..] {
+        ["legacyPackages", _, attr] => attr,
+        _ => attr_path,
+    }
+}
+
 impl EnvironmentEntries {
+    /// everything which is installed in a profile, sorted by name,
+    /// since the profile doesn't keep them in any order.
+    pub fn from_profile(profile: &Root) -> Self {
+        let mut names = profile
+            .elements
+            .packages
+            .iter()
+            .map(|(name, pkg)| profile_attr_name(name, &pkg.attr_path))
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+
+        let mut env = Self::default();
+        for name in names {
+            env.push_simple(name);
+        }
+        env
+    }
+
     pub fn push_simple(&mut self, simple: &str) {
         self.entries.push(EnvEntry {
             kind: EnvEntryKind::Simple {
@@ -66,6 +138,237 @@ impl EnvironmentEntries {
             children: Default::default(),
         })
     }
+
+    pub fn push_versioned(&mut self, attr: &str, commit: &str) {
+        self.entries.push(EnvEntry {
+            kind: EnvEntryKind::Versioned {
+                attr_name: attr.to_owned(),
+                nixpkgs_commit: commit.to_owned(),
+            },
+            children: Default::default(),
+        })
+    }
+
+    /// pin `attr` to the commit that nixhub found a version in.
+    pub fn push_version_lookup(&mut self, attr: &str, lookup: &VersionLookup) {
+        self.push_versioned(attr, &lookup.commit);
+    }
+
+    /// push `simple`, unless there's already an entry for it,
+    /// returns whether it was pushed.
+    pub fn push_simple_if_absent(&mut self, simple: &str) -> bool {
+        if self.contains(simple) {
+            return false;
+        }
+        self.push_simple(simple);
+        true
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<EnvEntry> {
+        (index < self.entries.len()).then(|| self.entries.remove(index))
+    }
+
+    pub fn contains(&self, attr_name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.kind.attr_name() == attr_name)
+    }
+
+    /// the nixpkgs commits which entries are pinned to, each
+    /// one only once, in the order they were first pinned.
+    fn pinned_commits(&self) -> Vec<&str> {
+        let mut commits = Vec::new();
+        for entry in &self.entries {
+            if let EnvEntryKind::Versioned { nixpkgs_commit, .. } = &entry.kind {
+                if !commits.contains(&nixpkgs_commit.as_str()) {
+                    commits.push(nixpkgs_commit.as_str());
+                }
+            }
+        }
+        commits
+    }
+
+    /// a `shell.nix` with every entry in it, pinned entries come from
+    /// their own nixpkgs, which nix will tell you the hash of the first
+    /// time that you use it.
+    pub fn export_shell_nix(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "{GENERATED_HEADER}").unwrap();
+        writeln!(out, "{{ pkgs ? import <nixpkgs> {{}} }}:").unwrap();
+        writeln!(out, "pkgs.mkShell {{").unwrap();
+        writeln!(out, "  packages = with pkgs; [").unwrap();
+        for entry in &self.entries {
+            match &entry.kind {
+                EnvEntryKind::Simple { attr_name } => {
+                    writeln!(out, "    {attr_name}").unwrap();
+                }
+                EnvEntryKind::Versioned {
+                    attr_name,
+                    nixpkgs_commit,
+                } => {
+                    // without a sha256, nix fetches it as it is, which
+                    // is fine, since the commit already pins it down.
+                    writeln!(out, "    (import (builtins.fetchTarball {{").unwrap();
+                    writeln!(
+                        out,
+                        "      url = \"https://github.com/NixOS/nixpkgs/archive/{nixpkgs_commit}.tar.gz\";"
+                    )
+                    .unwrap();
+                    writeln!(out, "    }}) {{}}).{attr_name}").unwrap();
+                }
+            }
+        }
+        writeln!(out, "  ];").unwrap();
+        writeln!(out, "}}").unwrap();
+        out
+    }
+
+    /// a `flake.nix` with a default devShell, each commit that
+    /// an entry is pinned to becomes an input of its own.
+    pub fn export_flake_devshell(&self, flake_description: &str) -> String {
+        let commits = self.pinned_commits();
+        let mut out = String::new();
+        writeln!(out, "{GENERATED_HEADER}").unwrap();
+        writeln!(out, "{{").unwrap();
+        writeln!(out, "  description = {};", nix_string(flake_description)).unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "  inputs = {{").unwrap();
+        writeln!(
+            out,
+            "    nixpkgs.url = \"github:NixOS/nixpkgs/nixos-unstable\";"
+        )
+        .unwrap();
+        for commit in &commits {
+            writeln!(
+                out,
+                "    {}.url = \"github:NixOS/nixpkgs/{commit}\";",
+                pinned_input(commit)
+            )
+            .unwrap();
+        }
+        writeln!(out, "  }};").unwrap();
+        writeln!(out).unwrap();
+        if commits.is_empty() {
+            writeln!(out, "  outputs = {{ self, nixpkgs }}:").unwrap();
+        } else {
+            writeln!(out, "  outputs = {{ self, nixpkgs, ... }}@inputs:").unwrap();
+        }
+        // builtins.currentSystem doesn't exist in pure evaluation,
+        // so there's a shell for every system that nixpkgs supports.
+        writeln!(out, "    let").unwrap();
+        writeln!(
+            out,
+            "      forAllSystems = nixpkgs.lib.genAttrs nixpkgs.lib.systems.flakeExposed;"
+        )
+        .unwrap();
+        writeln!(out, "    in {{").unwrap();
+        writeln!(out, "      devShells = forAllSystems (system:").unwrap();
+        writeln!(out, "        let").unwrap();
+        writeln!(out, "          pkgs = nixpkgs.legacyPackages.${{system}};").unwrap();
+        writeln!(out, "        in {{").unwrap();
+        writeln!(out, "          default = pkgs.mkShell {{").unwrap();
+        writeln!(out, "            packages = [").unwrap();
+        for entry in &self.entries {
+            match &entry.kind {
+                EnvEntryKind::Simple { attr_name } => {
+                    writeln!(out, "              pkgs.{attr_name}").unwrap();
+                }
+                EnvEntryKind::Versioned {
+                    attr_name,
+                    nixpkgs_commit,
+                } => {
+                    writeln!(
+                        out,
+                        "              inputs.{}.legacyPackages.${{system}}.{attr_name}",
+                        pinned_input(nixpkgs_commit)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        writeln!(out, "            ];").unwrap();
+        writeln!(out, "          }};").unwrap();
+        writeln!(out, "        }});").unwrap();
+        writeln!(out, "    }};").unwrap();
+        writeln!(out, "}}").unwrap();
+        out
+    }
+}
+
+/// the first line of every file that we export.
+const GENERATED_HEADER: &str =
+    "# this file was generated by newt, changes will be lost if it's exported again.";
+
+/// the name of the flake input for a pinned nixpkgs commit.
+fn pinned_input(commit: &str) -> String {
+    format!("nixpkgs-{}", commit.chars().take(7).collect::<String>())
+}
+
+/// quote `text` as a nix string literal.
+fn nix_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    format!("\"{escaped}\"")
+}
+
+#[test]
+fn test_environment_entries() {
+    let mut env = EnvironmentEntries::default();
+    env.push_simple("cargo");
+    assert!(env.contains("cargo"));
+    assert!(!env.contains("gleam"));
+
+    assert!(!env.push_simple_if_absent("cargo"));
+    assert!(env.push_simple_if_absent("gleam"));
+    assert_eq!(env.entries.len(), 2);
+
+    let removed = env.remove(0).unwrap();
+    assert_eq!(
+        removed.kind,
+        EnvEntryKind::Simple {
+            attr_name: "cargo".to_owned()
+        }
+    );
+    assert!(!env.contains("cargo"));
+    assert!(env.remove(1).is_none());
+    assert_eq!(env.entries.len(), 1);
+}
+
+#[test]
+fn test_from_profile() {
+    use nix_installed_list::{Elements, Package};
+
+    let package = |attr_path: &str| Package {
+        active: true,
+        attr_path: attr_path.to_owned(),
+        ..Default::default()
+    };
+    let profile = Root {
+        elements: Elements {
+            packages: [
+                ("ripgrep", package("legacyPackages.x86_64-linux.ripgrep")),
+                (
+                    "numpy",
+                    package("legacyPackages.aarch64-darwin.python3Packages.numpy"),
+                ),
+                ("gleam", package("")),
+            ]
+            .into_iter()
+            .map(|(name, pkg)| (name.to_owned(), pkg))
+            .collect(),
+        },
+        version: 3,
+    };
+
+    let env = EnvironmentEntries::from_profile(&profile);
+    let names = env
+        .entries
+        .iter()
+        .map(|entry| entry.kind.attr_name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["gleam", "python3Packages.numpy", "ripgrep"]);
 }
 
 pub fn with_border(view: impl View + 'static, last: bool) -> impl View {
@@ -98,3 +401,86 @@ impl EnvironmentEntries {
         .style(|s| s.flex().flex_col().padding_horiz(10.0))
     }
 }
+
+#[test]
+fn test_export_shell_nix() {
+    let mut env = EnvironmentEntries::default();
+    env.push_simple("cargo");
+    env.push_version_lookup(
+        "python3",
+        &VersionLookup {
+            version: "3.11.9".to_owned(),
+            commit: "0aa475546ed21629c4f5bbf90e38c846a99ec9e9".to_owned(),
+            date: "Aug 9, 2024".to_owned(),
+        },
+    );
+    assert!(env.contains("python3"));
+    assert!(!env.push_simple_if_absent("python3"));
+
+    assert_eq!(
+        env.export_shell_nix(),
+        r#"# this file was generated by newt, changes will be lost if it's exported again.
+{ pkgs ? import <nixpkgs> {} }:
+pkgs.mkShell {
+  packages = with pkgs; [
+    cargo
+    (import (builtins.fetchTarball {
+      url = "https://github.com/NixOS/nixpkgs/archive/0aa475546ed21629c4f5bbf90e38c846a99ec9e9.tar.gz";
+    }) {}).python3
+  ];
+}
+"#
+    );
+}
+
+#[test]
+fn test_export_flake_devshell() {
+    let mut env = EnvironmentEntries::default();
+    env.push_simple("cargo");
+    assert_eq!(
+        env.export_flake_devshell("a \"rust\" shell"),
+        r#"# this file was generated by newt, changes will be lost if it's exported again.
+{
+  description = "a \"rust\" shell";
+
+  inputs = {
+    nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+  };
+
+  outputs = { self, nixpkgs }:
+    let
+      forAllSystems = nixpkgs.lib.genAttrs nixpkgs.lib.systems.flakeExposed;
+    in {
+      devShells = forAllSystems (system:
+        let
+          pkgs = nixpkgs.legacyPackages.${system};
+        in {
+          default = pkgs.mkShell {
+            packages = [
+              pkgs.cargo
+            ];
+          };
+        });
+    };
+}
+"#
+    );
+
+    env.push_versioned("python3", "0aa475546ed21629c4f5bbf90e38c846a99ec9e9");
+    env.push_versioned(
+        "python3Packages.numpy",
+        "0aa475546ed21629c4f5bbf90e38c846a99ec9e9",
+    );
+    let flake = env.export_flake_devshell("pinned");
+    assert!(flake.contains("  outputs = { self, nixpkgs, ... }@inputs:\n"));
+    assert_eq!(
+        flake
+            .matches("    nixpkgs-0aa4755.url = \"github:NixOS/nixpkgs/0aa475546ed21629c4f5bbf90e38c846a99ec9e9\";\n")
+            .count(),
+        1
+    );
+    assert!(
+        flake.contains("              inputs.nixpkgs-0aa4755.legacyPackages.${system}.python3\n")
+    );
+    assert!(!flake.contains("builtins.currentSystem"));
+}
@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+/// how many searches we remember
+pub const MAX_HISTORY: usize = 50;
+
+/// ~/.local/share/newt/search-history.txt, respecting XDG_DATA_HOME
+pub fn history_path() -> PathBuf {
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").unwrap_or_default())
+            .join(".local")
+            .join("share"),
+    };
+    base.join("newt").join("search-history.txt")
+}
+
+/// the saved searches, most recent first, one per line.
+pub fn load_history() -> Vec<String> {
+    std::fs::read_to_string(history_path())
+        .map(|text| parse_history(&text))
+        .unwrap_or_default()
+}
+
+fn parse_history(text: &str) -> Vec<String> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(MAX_HISTORY)
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+pub fn save_history(history: &[String]) -> std::io::Result<()> {
+    let path = history_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, history.join("\n"))
+}
+
+/// remember a search, unless it's the same
+/// as the one we ran most recently.
+pub fn push_history(history: &mut Vec<String>, text: &str) {
+    let text = text.trim();
+    if text.is_empty() || history.first().is_some_and(|last| last == text) {
+        return;
+    }
+    history.insert(0, text.to_owned());
+    history.truncate(MAX_HISTORY);
+}
+
+/// where the cursor goes when the user presses up, None
+/// means that there isn't anything older to go to.
+pub fn older(cursor: Option<usize>, len: usize) -> Option<usize> {
+    let next = cursor.map_or(0, |idx| idx + 1);
+    (next < len).then_some(next)
+}
+
+#[test]
+fn test_push_history() {
+    let mut history = Vec::new();
+    push_history(&mut history, "ripgrep");
+    push_history(&mut history, "ripgrep");
+    push_history(&mut history, "  ");
+    push_history(&mut history, "gleam");
+    push_history(&mut history, "ripgrep");
+    assert_eq!(history, ["ripgrep", "gleam", "ripgrep"]);
+
+    for idx in 0..100 {
+        push_history(&mut history, &idx.to_string());
+    }
+    assert_eq!(history.len(), MAX_HISTORY);
+    assert_eq!(history[0], "99");
+}
+
+#[test]
+fn test_older() {
+    assert_eq!(older(None, 0), None);
+    assert_eq!(older(None, 2), Some(0));
+    assert_eq!(older(Some(0), 2), Some(1));
+    assert_eq!(older(Some(1), 2), None);
+}
+
+#[test]
+fn test_parse_history() {
+    assert_eq!(parse_history("ripgrep\n\ngleam\n"), ["ripgrep", "gleam"]);
+    let long = (0..100).map(|i| i.to_string()).collect::<Vec<_>>();
+    assert_eq!(parse_history(&long.join("\n")).len(), MAX_HISTORY);
+}
@@ -0,0 +1,383 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use floem::{
+    cosmic_text::Weight,
+    peniko::Color,
+    reactive::{create_effect, create_rw_signal, RwSignal},
+    style::CursorStyle,
+    unit::Pct,
+    view::View,
+    views::{
+        dyn_container, h_stack, label, scroll, static_label, v_stack, v_stack_from_iter, Decorators,
+    },
+};
+use nix_installed_list::{CachePackage, CachePackageLookupKey, CachePackages, Profile, Root};
+use once_cell::sync::Lazy;
+use tap::Pipe;
+
+use crate::actor::{self, ActorThread, CancellationToken};
+use crate::env::EnvironmentEntries;
+use crate::theme::theme;
+use crate::{loading_widget_labeled, nix, LOADING_SEGMENTS};
+
+/// an installed package, along with the name that
+/// `nix profile remove` knows it by.
+#[derive(Clone, Debug)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub package: CachePackage,
+}
+
+#[derive(Clone, Debug)]
+pub enum InstalledState {
+    Loading,
+    Loaded {
+        profile: Root,
+        packages: Vec<InstalledPackage>,
+    },
+    Failed(String),
+}
+
+/// how long the details of an installed package
+/// are trusted before they're fetched again.
+const PROFILE_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// ~/.cache/newt/profile-installed.toml, respecting XDG_CACHE_HOME
+fn profile_cache_path(profile: &Profile) -> PathBuf {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".cache"),
+    };
+    base.join("newt").join(profile.cache_file_name())
+}
+
+/// read the profile, and fill in the version and meta of each package,
+/// a package which can't be evaluated is still shown, just without them.
+fn read_profile(token: &CancellationToken) -> Result<(Root, Vec<InstalledPackage>), String> {
+    let source = Profile::default();
+    let profile = source.manifest_parsed().map_err(|e| e.to_string())?;
+    let mut cache = CachePackages::from_manifest_minimal(&profile);
+    let keys = cache.packages.iter().map(|p| p.key()).collect::<Vec<_>>();
+
+    // anything fetched recently can be reused rather than
+    // asking nix again, a broken cache is just ignored.
+    let cache_path = profile_cache_path(&source);
+    if let Ok(cached) = CachePackages::load_with_ttl(&cache_path, PROFILE_CACHE_TTL) {
+        cache.reuse_cached(&cached);
+    }
+    for key in &keys {
+        // each package is a few runs of nix eval, so
+        // this is where giving up early saves the most.
+        if token.is_cancelled() {
+            let _ = cache.save(&cache_path);
+            return Err("reading the profile was cancelled".to_owned());
+        }
+        let _ = cache.enrich_entry(key);
+    }
+    let _ = cache.save(&cache_path);
+
+    let mut packages = profile
+        .elements
+        .packages
+        .iter()
+        .filter_map(|(name, element)| {
+            let key = CachePackageLookupKey {
+                attr_path: element.attr_path.clone(),
+                url: element.url.clone(),
+            };
+            Some(InstalledPackage {
+                name: name.clone(),
+                package: cache.get(&key)?.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok((profile, packages))
+}
+
+pub static THREAD_PROFILE_READER: Lazy<
+    ActorThread<(), Result<(Root, Vec<InstalledPackage>), String>>,
+> = Lazy::new(|| ActorThread::new(|(), token: CancellationToken| read_profile(&token)));
+
+/// removes a package from the profile by its name, and
+/// replies with the name and whether it worked.
+pub static THREAD_UNINSTALLER: Lazy<ActorThread<String, (String, bool)>> = Lazy::new(|| {
+    ActorThread::new(|name: String, token: CancellationToken| {
+        let removed = nix()
+            .args(["profile", "remove", name.as_str()])
+            .spawn()
+            .and_then(|mut child| actor::wait_or_kill(&mut child, &token))
+            .is_ok_and(|status| status.is_some_and(|status| status.success()));
+        (name, removed)
+    })
+});
+
+/// the state of the installed screen, which outlives the screen
+/// itself, since the window is rebuilt whenever the theme changes.
+#[derive(Clone, Copy)]
+pub struct InstalledPackages {
+    state: RwSignal<InstalledState>,
+    uninstalling: RwSignal<HashSet<String>>,
+    /// the package which most recently failed to uninstall.
+    failed: RwSignal<Option<String>>,
+}
+
+impl InstalledPackages {
+    pub fn new() -> Self {
+        let this = Self {
+            state: create_rw_signal(InstalledState::Loading),
+            uninstalling: create_rw_signal(HashSet::new()),
+            failed: create_rw_signal(None),
+        };
+
+        let profile_receiver = THREAD_PROFILE_READER.create_channel_from_receiver();
+        create_effect(move |_| {
+            if let Some(read) = profile_receiver.get() {
+                this.state.set(match read {
+                    Ok((profile, packages)) => InstalledState::Loaded { profile, packages },
+                    Err(err) => InstalledState::Failed(err),
+                });
+            }
+        });
+
+        let uninstall_receiver = THREAD_UNINSTALLER.create_channel_from_receiver();
+        create_effect(move |_| {
+            if let Some((name, removed)) = uninstall_receiver.get() {
+                this.uninstalling.update(|u| {
+                    u.remove(&name);
+                });
+                if removed {
+                    this.failed.set(None);
+                    this.refresh();
+                } else {
+                    this.failed.set(Some(name));
+                }
+            }
+        });
+        this
+    }
+
+    /// read the profile again, anything still being read is abandoned.
+    pub fn refresh(&self) {
+        self.state.set(InstalledState::Loading);
+        THREAD_PROFILE_READER.cancel_current();
+        THREAD_PROFILE_READER.send_cancelling_previous(()).unwrap();
+    }
+
+    fn uninstall(&self, name: String) {
+        if self.uninstalling.with_untracked(|u| u.contains(&name)) {
+            return;
+        }
+        self.uninstalling.update(|u| {
+            u.insert(name.clone());
+        });
+        THREAD_UNINSTALLER.send(name).unwrap();
+    }
+}
+
+fn pill_style(s: floem::style::Style) -> floem::style::Style {
+    s.padding_horiz(12.0)
+        .padding_vert(4.0)
+        .background(theme().accent)
+        .color(theme().fg_on_accent)
+        .border_radius(Pct(100.0))
+        .font_size(theme().scaled(11.0))
+        .font_weight(Weight::SEMIBOLD)
+        .cursor(CursorStyle::Pointer)
+}
+
+fn installed_card(installed: InstalledPackages, each: InstalledPackage) -> impl View {
+    let InstalledPackage { name, package } = each;
+    let version = package
+        .version
+        .clone()
+        .unwrap_or_else(|| "<version unknown>".to_owned());
+    let description = package.description().unwrap_or_default().to_owned();
+    let homepage = package
+        .meta
+        .get("homepage")
+        .and_then(|h| h.as_str())
+        .map(ToOwned::to_owned);
+
+    let uninstalling = {
+        let name = name.clone();
+        move || installed.uninstalling.with(|u| u.contains(&name))
+    };
+    let failed = {
+        let name = name.clone();
+        move || installed.failed.with(|f| f.as_ref() == Some(&name))
+    };
+    let failed_style = failed.clone();
+    let uninstall_button = label(move || {
+        if uninstalling() {
+            "Uninstalling..."
+        } else if failed() {
+            "Retry Uninstall"
+        } else {
+            "Uninstall"
+        }
+        .to_owned()
+    })
+    .style(move |s| {
+        pill_style(s).apply_if(failed_style(), |s| {
+            s.background(crate::tailwind::color("red-700").with_alpha_factor(0.6))
+        })
+    })
+    .on_click_stop({
+        let name = name.clone();
+        move |_| installed.uninstall(name.clone())
+    });
+
+    let title = h_stack((
+        static_label(name).style(|s| s.font_weight(Weight::BOLD).font_size(theme().scaled(14.0))),
+        static_label(version).style(|s| {
+            s.flex_grow(1.0)
+                .font_size(theme().scaled(10.0))
+                .color(theme().fg_minus)
+        }),
+        uninstall_button,
+    ))
+    .style(|s| s.gap(8.0, 0.0).items_center());
+
+    let homepage = match homepage {
+        Some(url) => static_label(url.clone())
+            .style(|s| s.color(theme().accent).cursor(CursorStyle::Pointer))
+            .on_click_stop(move |_| {
+                let _ = open::that(&url);
+            })
+            .pipe(Box::new) as Box<dyn View>,
+        None => Box::new(floem::views::empty()),
+    };
+
+    let store_paths = v_stack_from_iter(package.store_paths.into_iter().map(|path| {
+        static_label(path).style(|s| {
+            s.font_size(theme().scaled(10.0))
+                .color(theme().fg_minus)
+                .padding_horiz(4.0)
+                .background(Color::BLACK.with_alpha_factor(0.1))
+        })
+    }))
+    .style(|s| s.gap(0.0, 2.0));
+
+    v_stack((
+        title,
+        static_label(description),
+        homepage,
+        static_label("Store Paths").style(|s| s.font_bold().font_size(theme().scaled(10.0))),
+        store_paths,
+    ))
+    .style(|s| {
+        s.gap(0.0, 6.0)
+            .padding(12.0)
+            .width_full()
+            .background(theme().bg_plus)
+            .border(1.0)
+            .border_color(theme().bd)
+            .border_radius(10.0)
+    })
+}
+
+/// everything installed in the user's profile, with a button to
+/// make the environment match it, and one to uninstall each package,
+/// the environment itself is drawn as a tree alongside.
+pub fn installed_screen(
+    installed: InstalledPackages,
+    environ: RwSignal<EnvironmentEntries>,
+) -> impl View {
+    let sync_button = static_label("Sync from profile")
+        .style(pill_style)
+        .on_click_stop(move |_| {
+            installed.state.with_untracked(|state| {
+                if let InstalledState::Loaded { profile, .. } = state {
+                    environ.set(EnvironmentEntries::from_profile(profile));
+                }
+            })
+        });
+    let header = h_stack((
+        static_label("Installed").style(|s| {
+            s.font_weight(Weight::BOLD)
+                .font_size(theme().scaled(16.0))
+                .flex_grow(1.0)
+        }),
+        sync_button,
+    ))
+    .style(|s| s.items_center().width_full());
+
+    let body = dyn_container(
+        move || installed.state.get(),
+        move |state| match state {
+            InstalledState::Loading => {
+                loading_widget_labeled(|| "Reading your profile".to_owned(), LOADING_SEGMENTS)
+                    .style(|s| s.margin_top(40.0))
+                    .pipe(Box::new) as Box<dyn View>
+            }
+            InstalledState::Failed(err) => v_stack((
+                static_label("Couldn't read your profile")
+                    .style(|s| s.font_size(theme().scaled(14.0)).font_weight(Weight::BOLD)),
+                static_label(err).style(|s| s.max_width_full()),
+                static_label("Try Again")
+                    .style(pill_style)
+                    .on_click_stop(move |_| installed.refresh()),
+            ))
+            .style(|s| s.gap(0.0, 8.0).items_center().margin_top(40.0))
+            .pipe(Box::new),
+            InstalledState::Loaded { packages, .. } if packages.is_empty() => {
+                static_label("Nothing is installed in your profile")
+                    .style(|s| s.margin_top(40.0))
+                    .pipe(Box::new)
+            }
+            InstalledState::Loaded { packages, .. } => v_stack_from_iter(
+                packages
+                    .into_iter()
+                    .map(move |each| installed_card(installed, each)),
+            )
+            .style(|s| s.gap(0.0, 10.0).width_full())
+            .pipe(scroll)
+            .style(|s| s.width_full().flex_grow(1.0).min_height(0))
+            .pipe(Box::new),
+        },
+    )
+    .style(|s| {
+        s.flex_grow(1.0)
+            .min_width(0)
+            .min_height(0)
+            .flex_col()
+            .items_center()
+    });
+
+    // the environment sits next to the cards, so that
+    // syncing it (or installing) has something to show.
+    let environment = v_stack((
+        static_label("Environment").style(|s| s.font_weight(Weight::BOLD).padding_horiz(10.0)),
+        scroll(EnvironmentEntries::view(environ)).style(|s| s.flex_grow(1.0).min_height(0)),
+    ))
+    .style(|s| {
+        s.gap(0.0, 6.0)
+            .min_width(240.0)
+            .height_full()
+            .min_height(0)
+            .padding_vert(10.0)
+            .background(theme().bg_minus)
+            .border(1.0)
+            .border_color(theme().bd)
+            .border_radius(10.0)
+    });
+
+    let columns = h_stack((body, environment)).style(|s| {
+        s.gap(10.0, 0.0)
+            .width_full()
+            .flex_grow(1.0)
+            .min_height(0)
+            .items_start()
+    });
+
+    v_stack((header, columns)).style(|s| {
+        s.gap(0.0, 10.0)
+            .padding(15.0)
+            .width_full()
+            .height_full()
+            .min_height(0)
+    })
+}
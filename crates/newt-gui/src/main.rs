@@ -1,21 +1,27 @@
 #![allow(dead_code, unused_imports, unused_macros)]
 mod actor;
+mod detail;
 mod env;
+mod history;
+mod installed;
 mod search;
+mod settings;
 mod tailwind;
 mod theme;
 
 use std::borrow::Cow;
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use std::{collections::HashMap, process::Stdio};
 
-use actor::ActorThread;
-use floem::action::exec_after;
+use actor::{ActorThread, CancellationToken};
+use floem::action::{exec_after, TimerToken};
 use floem::cosmic_text::Weight;
 
 use floem::cosmic_text::Style as TextStyle;
 use floem::event::Event;
 use floem::keyboard::{Key, KeyCode, NamedKey, PhysicalKey};
+use floem::menu::{Menu, MenuItem};
 use floem::peniko::Color;
 use floem::reactive::{create_effect, create_trigger};
 use floem::reactive::{create_memo, create_rw_signal, ReadSignal, RwSignal};
@@ -38,14 +44,13 @@ use nix_elastic_search::response::NixPackage;
 use inline_tweak::tweak;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use search::PackageSupport;
+use search::{NixPackageExt, PackageSupport};
 use serde::{Deserialize, Serialize};
 #[allow(unused_imports)]
 use tap::Pipe;
 
 use floem::{
     event::EventListener,
-    style::Position,
     views::{container, h_stack, label, scroll, v_stack, Decorators},
 };
 
@@ -106,9 +111,10 @@ pub struct NixTemplates {
 use theme::theme;
 
 use im::Vector;
+use nix_search_sort_utils::search_by_name_metric;
 use snafu::prelude::*;
 
-use crate::search::{available_on_this_system, search, search_by_name_metric};
+use crate::search::{available_on_this_system, search};
 
 #[derive(Debug, Snafu)]
 pub enum ProgramError {
@@ -287,16 +293,9 @@ struct SelectedFlakeOption {
 
 impl SelectedFlakeOption {
     fn select_flake_source(self, which_flake_source: usize) -> Self {
-        if self.is_flake_source(which_flake_source) {
-            Self {
-                which_flake_source: None,
-                which_template: None,
-            }
-        } else {
-            Self {
-                which_flake_source: Some(which_flake_source),
-                which_template: None,
-            }
+        Self {
+            which_flake_source: toggle_selection(self.which_flake_source, which_flake_source),
+            which_template: None,
         }
     }
 
@@ -326,6 +325,10 @@ fn test_selected_flake() {
     dbg!(selected_flake);
     assert!(selected_flake.is_flake_source(0));
     assert!(!selected_flake.is_flake_source(1));
+
+    selected_flake = selected_flake.select_template(2).select_flake_source(0);
+    assert!(!selected_flake.is_flake_source(0));
+    assert!(!selected_flake.is_template(2));
 }
 
 fn template_list(
@@ -512,6 +515,8 @@ pub enum ApplicationScreen {
     Search,
     Docs,
     Home,
+    Installed,
+    Settings,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
@@ -559,11 +564,15 @@ pub fn vnav() -> impl View {
         .pipe(move |view| vnav_icon(view, active_view, ApplicationScreen::Search));
     let gap = views::empty().style(|s| s.width_full().border(1.0).border_color(theme().bd));
 
+    let installed_icon = views::svg(|| instr!("../../../assets/env-vnav.svg").to_owned())
+        .style(|s| s.width(SVG_SIZE / 1.5).height(SVG_SIZE / 1.5))
+        .pipe(move |view| vnav_icon(view, active_view, ApplicationScreen::Installed));
+
     let help_icon = views::svg(|| instr!("../../../assets/help-vnav.svg").to_owned())
         .style(|s| s.width(10.0).height(SVG_SIZE / 1.5))
         .pipe(move |view| vnav_icon(view, active_view, ApplicationScreen::Docs));
 
-    v_stack((nix_icon, gap, search_icon, help_icon)).style(|s| {
+    v_stack((nix_icon, gap, search_icon, installed_icon, help_icon)).style(|s| {
         s.background(theme().bg_minus)
             .width(80.0)
             .min_width(80.0)
@@ -591,19 +600,60 @@ pub fn nixpkgs_search_window() -> impl View {
     });
 
     let active_package_receiver = THREAD_SEARCHER.create_channel_from_receiver();
+    let install_receiver = THREAD_INSTALLER.create_channel_from_receiver();
+    let installed = installed::InstalledPackages::new();
+    let settings = create_rw_signal(settings::Settings::load());
+    // memos, so that only the settings which change the look
+    // of the window cause it to be rebuilt.
+    let theme_mode = create_memo(move |_| settings.with(|s| s.theme));
+    let font_size = create_memo(move |_| settings.with(|s| s.font_size));
+    let system_theme = theme::watch_system_theme();
+
+    // the hardcoded channels are used until the real ones
+    // arrive, and for good if they can't be fetched.
+    let channels = create_rw_signal(Channels::new());
+    let channel_receiver = THREAD_CHANNEL_LIST.create_channel_from_receiver();
+    create_effect(move |_| match channel_receiver.get() {
+        Some(Ok(fetched)) => channels.set(fetched),
+        Some(Err(err)) => eprintln!("failed to fetch the channel list: {err}"),
+        None => {}
+    });
+    THREAD_CHANNEL_LIST.send(()).unwrap();
+    // the theme isn't reactive, so everything is rebuilt when it changes.
     let view = dyn_container(
-        move || outer_mode.get(),
-        move |mode| {
-            let main_window = construct_nixpkgs_search(active_package_receiver)
-                .pipe(container)
-                .style(|s| {
-                    s.width_full()
-                        .height_full()
-                        .flex()
-                        .flex_row()
-                        .justify_center()
-                        .min_height(0)
-                });
+        move || {
+            (
+                outer_mode.get(),
+                theme_mode.get(),
+                font_size.get(),
+                system_theme.get(),
+            )
+        },
+        move |(mode, ..)| {
+            let screen = match mode.screen {
+                ApplicationScreen::Installed => {
+                    Box::new(installed::installed_screen(installed, environ)) as Box<dyn View>
+                }
+                ApplicationScreen::Settings => settings::settings_panel(settings, channels)
+                    .pipe(container)
+                    .style(|s| s.padding_top(40.0))
+                    .pipe(Box::new),
+                _ => Box::new(construct_nixpkgs_search(
+                    active_package_receiver,
+                    install_receiver,
+                    environ,
+                    settings,
+                    channels,
+                )),
+            };
+            let main_window = screen.pipe(container).style(|s| {
+                s.width_full()
+                    .height_full()
+                    .flex()
+                    .flex_row()
+                    .justify_center()
+                    .min_height(0)
+            });
 
             let close_button = if mode.with_env {
                 views::svg(|| instr!("../../../assets/close-button.svg").to_owned())
@@ -649,8 +699,56 @@ pub fn nixpkgs_search_window() -> impl View {
             //     });
             // let top_bar_content =
             //     h_stack((top_bar_env_active_content,)).style(|s| s.width_full().height_full());
-            let top_bar = drag_window_area(views::empty())
-                .style(|s| s.width_full().min_height(TOPBAR_HEIGHT).justify_between());
+            let settings_button = static_label("⚙")
+                .style(move |s| {
+                    s.font_size(theme().scaled(16.0))
+                        .padding_horiz(12.0)
+                        .color(theme().fg_minus)
+                        .cursor(CursorStyle::Pointer)
+                        .apply_if(mode.screen == ApplicationScreen::Settings, |s| {
+                            s.color(theme().accent)
+                        })
+                })
+                .on_click_stop(move |_| {
+                    outer_mode.update(|mode| {
+                        mode.screen = match mode.screen {
+                            ApplicationScreen::Settings => ApplicationScreen::Search,
+                            _ => ApplicationScreen::Settings,
+                        }
+                    })
+                });
+            let installed_button = static_label("Installed")
+                .style(move |s| {
+                    s.padding_horiz(12.0)
+                        .padding_vert(3.0)
+                        .border_radius(7.0)
+                        .color(theme().fg_minus)
+                        .cursor(CursorStyle::Pointer)
+                        .apply_if(mode.screen == ApplicationScreen::Installed, |s| {
+                            s.background(theme().accent.with_alpha_factor(0.2))
+                                .color(theme().fg)
+                        })
+                })
+                .on_click_stop(move |_| {
+                    // the profile is read again each time the screen is
+                    // opened, since it may have changed outside of newt.
+                    if mode.screen != ApplicationScreen::Installed {
+                        installed.refresh();
+                    }
+                    outer_mode.update(|mode| {
+                        mode.screen = match mode.screen {
+                            ApplicationScreen::Installed => ApplicationScreen::Search,
+                            _ => ApplicationScreen::Installed,
+                        }
+                    })
+                });
+            let top_bar = h_stack((
+                drag_window_area(views::empty())
+                    .style(|s| s.flex_grow(1.0).height_full().min_height(TOPBAR_HEIGHT)),
+                installed_button,
+                settings_button,
+            ))
+            .style(|s| s.width_full().min_height(TOPBAR_HEIGHT).items_center());
             // let env_view = env::EnvironmentEntries::view(environ).style(|s| {
             //     s.min_width(240)
             //         .height_full()
@@ -672,7 +770,12 @@ pub fn nixpkgs_search_window() -> impl View {
             //     Box::new(h_stack((vnav(), view)).style(|s| s.width_full()))
             // } else {
             let view = v_stack((top_bar, main_window))
-                .style(|s| s.width_full().height_full())
+                .style(|s| {
+                    s.width_full()
+                        .height_full()
+                        .background(theme().bg)
+                        .color(theme().fg)
+                })
                 .window_title(|| "NixOS Brewer".to_owned());
 
             Box::new(view.style(|s| s.width_full()))
@@ -713,7 +816,7 @@ mod style {
     pub fn h1(style: Style) -> Style {
         style
             .padding(10.0)
-            .font_size(24.0)
+            .font_size(theme().scaled(24.0))
             .min_width(0)
             .font_weight(Weight::BOLD)
     }
@@ -721,7 +824,7 @@ mod style {
     pub fn h3(style: Style) -> Style {
         style
             .padding(10.0)
-            .font_size(16.0)
+            .font_size(theme().scaled(16.0))
             .font_weight(Weight::BOLD)
             .min_width(0)
     }
@@ -770,7 +873,17 @@ pub fn package_support(support: PackageSupport) -> impl View {
     )
 }
 
-fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
+fn copy_to_clipboard(text: String) {
+    if let Err(err) = floem::clipboard::Clipboard::set_contents(text) {
+        eprintln!("failed to copy to the clipboard: {err:?}");
+    }
+}
+
+fn search_result_card(
+    selected: RwSignal<Selectable<NixPackage>>,
+    search_props: RwSignal<SearchProperties>,
+    installs: RwSignal<HashMap<String, InstallState>>,
+) -> impl View {
     static PYTHON_REGEX: Lazy<Regex> =
         Lazy::new(|| Regex::new(r"python[0-9_]+Packages\.").unwrap());
     dyn_stack(
@@ -779,11 +892,16 @@ fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
         move |(_sel, idx, each)| {
             let version = each.package_pversion.clone();
             let support = available_on_this_system(&each);
+            let install_spec =
+                each.nix_install_spec(&search_props.with_untracked(|s| s.channel.clone()));
+            let homepage = each.package_homepage.first().cloned();
             let outputs = each.package_outputs;
             let card_name = each.package_attr_name;
+            let attr_name = card_name.clone();
 
             let card_name_view = |card_name| {
-                static_label(card_name).style(|s| style::h3(s).min_width(0).font_size(14.0))
+                static_label(card_name)
+                    .style(|s| style::h3(s).min_width(0).font_size(theme().scaled(14.0)))
             };
 
             let title_node = if PYTHON_REGEX.is_match(&card_name) {
@@ -855,7 +973,7 @@ fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
                     .color(theme().fg_on_accent)
                     .border_radius(Pct(100.0))
                     .z_index(40)
-                    .font_size(10.0)
+                    .font_size(theme().scaled(10.0))
                     .font_weight(Weight::SEMIBOLD)
             });
 
@@ -871,8 +989,11 @@ fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
                             .border_color(theme().bd)
                             .padding(5.0)
                     })
-                    .on_click_stop(move |_| {
-                        each.package_homepage.get(0).map(|t| open::that(t));
+                    .on_click_stop({
+                        let homepage = homepage.clone();
+                        move |_| {
+                            homepage.as_ref().map(|t| open::that(t));
+                        }
                     }),
                 // static_label(version).style(style::text_hint),
                 // package_support(support),
@@ -881,9 +1002,64 @@ fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
                 .pipe(h_stack)
                 .style(|s| s.flex_row().gap(10.0, 0.0).align_items(AlignItems::Center));
 
+            let install_state = {
+                let attr_name = attr_name.clone();
+                move || installs.with(|i| i.get(&attr_name).copied())
+            };
+            let unsupported = support == PackageSupport::MostLikelyNot;
+            let install_button = label(move || InstallState::label(install_state()))
+                .style(move |s| {
+                    s.padding_vert(4.0)
+                        .padding_horiz(10.0)
+                        .background(theme().accent)
+                        .color(theme().fg_on_accent)
+                        .border_radius(Pct(100.0))
+                        .cursor(CursorStyle::Pointer)
+                        .font_size(theme().scaled(10.0))
+                        .font_weight(Weight::SEMIBOLD)
+                        .apply_if(unsupported, |s| {
+                            s.background(theme().unavailable)
+                                .cursor(CursorStyle::Default)
+                        })
+                })
+                .on_click_stop({
+                    let attr_name = attr_name.clone();
+                    move |_| {
+                        // clicking again while it's going gives up on it,
+                        // whether it's running or still waiting its turn.
+                        let state = installs.with_untracked(|i| i.get(&attr_name).copied());
+                        if state == Some(InstallState::Installing) {
+                            if let Some(token) = INSTALL_TOKENS.lock().get(&attr_name) {
+                                token.cancel();
+                            }
+                            return;
+                        }
+                        // only retry a failed install, installing twice
+                        // at once would just race on the profile.
+                        if unsupported || !matches!(state, None | Some(InstallState::Failed)) {
+                            return;
+                        }
+                        let channel = search_props.with_untracked(|s| s.channel.clone());
+                        installs.update(|i| {
+                            i.insert(attr_name.clone(), InstallState::Installing);
+                        });
+                        let token = THREAD_INSTALLER
+                            .send_cancellable((
+                                attr_name.clone(),
+                                search::nix_install_spec(&attr_name, &channel),
+                            ))
+                            .unwrap();
+                        INSTALL_TOKENS.lock().insert(attr_name.clone(), token);
+                    }
+                });
+
+            let title_node = tooltip(title_node, || {
+                static_label("Ctrl+C to copy the attr name, right click for more")
+            });
+
             let title_slide = (
                 title_node.style(|s| s.margin_top(-5.0)),
-                h_stack((add_package, homepage_icon)).style(|s| s.gap(5.0, 0.0)),
+                h_stack((add_package, install_button, homepage_icon)).style(|s| s.gap(5.0, 0.0)),
             )
                 .pipe(h_stack)
                 .style(|s| {
@@ -899,13 +1075,14 @@ fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
                 static_label(format!("Version    {version}"))
                     .style(|s| {
                         s.font_weight(Weight::SEMIBOLD)
-                            .font_size(10.0)
+                            .font_size(theme().scaled(10.0))
                             .color(theme().fg_minus)
                     })
                     .pipe(container)
                     .style(|s| s.padding_left(10.0).margin_top(-14.0)),
                 h_stack((
-                    static_label("Variants").style(|s| s.font_bold().font_size(10.0)),
+                    static_label("Variants")
+                        .style(|s| s.font_bold().font_size(theme().scaled(10.0))),
                     dyn_stack(
                         move || outputs.clone(),
                         |k| k.clone(),
@@ -918,7 +1095,7 @@ fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
                                     .border_color(theme().bd)
                             }))
                             .style(|s| {
-                                s.font_size(10.0)
+                                s.font_size(theme().scaled(10.0))
                                     .flex_row()
                                     .gap(5.0, 0.0)
                                     .color(theme().fg_minus)
@@ -1006,6 +1183,30 @@ fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
                 programs_provided,
             ));
 
+            let context_menu = {
+                let attr_name = attr_name.clone();
+                move || {
+                    let (attr_name, install_spec, homepage) =
+                        (attr_name.clone(), install_spec.clone(), homepage.clone());
+                    Menu::new("")
+                        .entry(
+                            MenuItem::new("Copy attr name")
+                                .action(move || copy_to_clipboard(attr_name.clone())),
+                        )
+                        .entry(
+                            MenuItem::new("Copy nix install spec")
+                                .action(move || copy_to_clipboard(install_spec.clone())),
+                        )
+                        .entry(
+                            MenuItem::new("Open homepage")
+                                .enabled(homepage.is_some())
+                                .action(move || {
+                                    homepage.as_ref().map(|t| open::that(t));
+                                }),
+                        )
+                }
+            };
+
             (
                 top_line,
                 description,
@@ -1028,7 +1229,28 @@ fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
                         .background(theme().bg_plus)
                 })
                 .pipe(|b| Box::new(b) as Box<dyn View>)
-                .on_click_stop(move |_| selected.update(|s| s.select(idx)))
+                .keyboard_navigatable()
+                .on_click_stop(move |e| {
+                    let modifiers = match e {
+                        Event::PointerUp(e) => e.modifiers,
+                        _ => Default::default(),
+                    };
+                    selected.update(|s| match s.anchor() {
+                        Some(anchor) if modifiers.shift_key() => s.select_range(anchor, idx),
+                        _ if modifiers.control_key() || modifiers.super_key() => {
+                            s.multi_select(idx)
+                        }
+                        _ => s.select_toggle(idx),
+                    })
+                })
+                .on_event_stop(EventListener::KeyDown, move |e| {
+                    if let Event::KeyDown(ev) = e {
+                        if ev.key.physical_key == KeyCode::KeyC && ev.modifiers.control_key() {
+                            copy_to_clipboard(attr_name.clone());
+                        }
+                    }
+                })
+                .context_menu(context_menu)
         },
     )
     .style(|s| s.min_width(0).width_full().flex_grow(1.0))
@@ -1037,38 +1259,213 @@ fn search_result_card(selected: RwSignal<Selectable<NixPackage>>) -> impl View {
 #[derive(Clone, Debug)]
 pub struct Selectable<T> {
     els: Vec<T>,
-    selected: Option<usize>,
+    selected: BTreeSet<usize>,
+    /// the last place that was clicked on, which
+    /// is where a shift-click range starts from.
+    anchor: Option<usize>,
 }
 
 impl<T> Selectable<T> {
     pub fn new_vec(els: Vec<T>) -> Self {
         Self {
             els,
-            selected: None,
+            selected: BTreeSet::new(),
+            anchor: None,
         }
     }
 
     pub fn new() -> Self {
-        Self {
-            els: Vec::new(),
-            selected: None,
-        }
+        Self::new_vec(Vec::new())
     }
 
     pub fn into_iter(self) -> impl Iterator<Item = (bool, usize, T)> {
         self.els
             .into_iter()
             .enumerate()
-            .map(move |(idx, val)| (self.selected.filter(|s| *s == idx).is_some(), idx, val))
+            .map(move |(idx, val)| (self.selected.contains(&idx), idx, val))
     }
 
+    /// select only `place`
     pub fn select(&mut self, place: usize) {
-        self.selected = Some(place);
+        self.selected.clear();
+        self.selected.insert(place);
+        self.anchor = Some(place);
+    }
+
+    pub fn deselect(&mut self) {
+        self.selected.clear();
+        self.anchor = None;
+    }
+
+    /// select only `place`, unless it's already the only thing
+    /// selected, in which case nothing is selected anymore.
+    pub fn select_toggle(&mut self, place: usize) {
+        match toggle_selection(self.only_selected(), place) {
+            Some(place) => self.select(place),
+            None => self.deselect(),
+        }
+    }
+
+    /// add `place` to the selection, or take
+    /// it out if it's already selected.
+    pub fn multi_select(&mut self, place: usize) {
+        if !self.selected.remove(&place) {
+            self.selected.insert(place);
+        }
+        self.anchor = Some(place);
+    }
+
+    /// select everything from `from` to `to`, inclusive,
+    /// in either direction, and nothing else.
+    pub fn select_range(&mut self, from: usize, to: usize) {
+        let (start, end) = (from.min(to), from.max(to));
+        self.selected = (start..=end)
+            .take_while(|idx| *idx < self.els.len())
+            .collect();
+    }
+
+    pub fn anchor(&self) -> Option<usize> {
+        self.anchor
+    }
+
+    pub fn is_any_selected(&self) -> bool {
+        !self.selected.is_empty()
+    }
+
+    pub fn selected_indices(&self) -> &BTreeSet<usize> {
+        &self.selected
+    }
+
+    pub fn selected_items(&self) -> impl Iterator<Item = &T> {
+        self.selected.iter().filter_map(|idx| self.els.get(*idx))
     }
 
     fn is_selected(&self, idx: usize) -> bool {
-        self.selected.filter(|s| *s == idx).is_some()
+        self.selected.contains(&idx)
+    }
+
+    fn only_selected(&self) -> Option<usize> {
+        match self.selected.len() {
+            1 => self.selected.first().copied(),
+            _ => None,
+        }
     }
+
+    /// the selected item, as long as it's the only one selected.
+    pub fn selected_item(&self) -> Option<&T> {
+        self.els.get(self.only_selected()?)
+    }
+}
+
+/// clicking on what's already selected collapses it,
+/// clicking on anything else selects that instead.
+fn toggle_selection(selected: Option<usize>, place: usize) -> Option<usize> {
+    if selected == Some(place) {
+        None
+    } else {
+        Some(place)
+    }
+}
+
+#[test]
+fn test_selectable_toggle() {
+    let mut selectable = Selectable::new_vec(vec!["a", "b", "c"]);
+    assert!(!selectable.is_selected(0));
+
+    // nothing selected -> selected
+    selectable.select_toggle(1);
+    assert!(selectable.is_selected(1));
+
+    // selected -> something else selected
+    selectable.select_toggle(2);
+    assert!(selectable.is_selected(2));
+    assert!(!selectable.is_selected(1));
+
+    // selected -> nothing selected
+    selectable.select_toggle(2);
+    assert!(!selectable.is_selected(2));
+    assert!(!selectable.is_any_selected());
+
+    // select never deselects, but deselect always does
+    selectable.select(0);
+    selectable.select(0);
+    assert!(selectable.is_selected(0));
+    assert_eq!(selectable.selected_item(), Some(&"a"));
+    selectable.deselect();
+    assert!(!selectable.is_any_selected());
+    selectable.deselect();
+    assert!(!selectable.is_any_selected());
+}
+
+#[test]
+fn test_selectable_multi_select() {
+    let mut selectable = Selectable::new_vec(vec!["a", "b", "c", "d"]);
+    selectable.multi_select(1);
+    selectable.multi_select(3);
+    assert!(selectable.is_any_selected());
+    assert_eq!(
+        selectable
+            .selected_indices()
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        [1, 3]
+    );
+    assert_eq!(
+        selectable.selected_items().collect::<Vec<_>>(),
+        [&"b", &"d"]
+    );
+    assert_eq!(selectable.selected_item(), None);
+    assert_eq!(selectable.anchor(), Some(3));
+
+    // multi selecting again takes it back out
+    selectable.multi_select(1);
+    assert_eq!(selectable.selected_items().collect::<Vec<_>>(), [&"d"]);
+    assert_eq!(selectable.selected_item(), Some(&"d"));
+
+    // select clears everything else
+    selectable.multi_select(0);
+    selectable.select(2);
+    assert_eq!(selectable.selected_items().collect::<Vec<_>>(), [&"c"]);
+
+    let flags = selectable
+        .clone()
+        .into_iter()
+        .map(|(selected, _, _)| selected)
+        .collect::<Vec<_>>();
+    assert_eq!(flags, [false, false, true, false]);
+}
+
+#[test]
+fn test_selectable_select_range() {
+    let mut selectable = Selectable::new_vec(vec!["a", "b", "c", "d"]);
+    selectable.select_range(1, 2);
+    assert_eq!(
+        selectable.selected_items().collect::<Vec<_>>(),
+        [&"b", &"c"]
+    );
+
+    // backwards works too, and replaces the old selection
+    selectable.select_range(3, 2);
+    assert_eq!(
+        selectable.selected_items().collect::<Vec<_>>(),
+        [&"c", &"d"]
+    );
+
+    // past the end is clamped
+    selectable.select_range(2, 10);
+    assert_eq!(
+        selectable
+            .selected_indices()
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        [2, 3]
+    );
+
+    let mut empty = Selectable::<&str>::new();
+    empty.select_range(0, 3);
+    assert!(!empty.is_any_selected());
 }
 
 impl<A> FromIterator<A> for Selectable<A> {
@@ -1081,43 +1478,209 @@ impl<A> FromIterator<A> for Selectable<A> {
 pub enum SearchMode {
     Name,
     Program,
+    Description,
+    /// name, program and description all at once.
+    Combined,
+}
+
+impl SearchMode {
+    /// in the order that the buttons are shown, which
+    /// is also the order of the ctrl+1..4 shortcuts.
+    pub const ALL: [SearchMode; 4] = [
+        SearchMode::Name,
+        SearchMode::Program,
+        SearchMode::Description,
+        SearchMode::Combined,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchMode::Name => "By Name",
+            SearchMode::Program => "By Program",
+            SearchMode::Description => "By Description",
+            SearchMode::Combined => "All",
+        }
+    }
+
+    /// the mode for a ctrl+<digit> shortcut.
+    pub fn from_shortcut(key: PhysicalKey) -> Option<SearchMode> {
+        let idx = match key {
+            PhysicalKey::Code(KeyCode::Digit1) => 0,
+            PhysicalKey::Code(KeyCode::Digit2) => 1,
+            PhysicalKey::Code(KeyCode::Digit3) => 2,
+            PhysicalKey::Code(KeyCode::Digit4) => 3,
+            _ => return None,
+        };
+        Some(SearchMode::ALL[idx])
+    }
+}
+
+#[test]
+fn test_search_mode_shortcuts() {
+    assert_eq!(
+        SearchMode::from_shortcut(PhysicalKey::Code(KeyCode::Digit1)),
+        Some(SearchMode::Name)
+    );
+    assert_eq!(
+        SearchMode::from_shortcut(PhysicalKey::Code(KeyCode::Digit4)),
+        Some(SearchMode::Combined)
+    );
+    assert_eq!(
+        SearchMode::from_shortcut(PhysicalKey::Code(KeyCode::Digit5)),
+        None
+    );
+    assert_eq!(
+        SearchMode::from_shortcut(PhysicalKey::Code(KeyCode::KeyW)),
+        None
+    );
 }
 
 #[derive(Clone, Debug, Hash)]
 pub struct SearchProperties {
     pub mode: SearchMode,
-    pub channel: usize,
+    pub channel: String,
+    pub max_results: u32,
 }
 
+impl SearchProperties {
+    /// the choices for how many results a search brings back.
+    pub const RESULT_COUNTS: [u32; 4] = [10, 25, 50, 100];
+}
+
+impl Default for SearchProperties {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::Name,
+            channel: Channels::new().latest_stable().to_owned(),
+            max_results: settings::DEFAULT_MAX_RESULTS,
+        }
+    }
+}
+
+/// how many stable channels are offered by default, besides unstable.
+pub const MAX_STABLE_CHANNELS: usize = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Channels {
     opts: Vec<String>,
 }
 
 impl Channels {
+    /// the channels we offer when we can't find out which
+    /// ones exist, such as when the user is offline.
     pub fn new() -> Self {
         Self {
             opts: ["24.11", "unstable"].map(ToOwned::to_owned).to_vec(),
         }
     }
+
+    /// every stable channel that nixos publishes, oldest
+    /// first, only the newest [`MAX_STABLE_CHANNELS`] are kept.
+    pub fn from_channel_list_api() -> Result<Channels, nix_channel_list::ChannelRequestError> {
+        let full_channels = nix_channel_list::get_full_channels()?;
+        Ok(Channels::from_full_channels(full_channels).with_max_stable(MAX_STABLE_CHANNELS))
+    }
+
+    fn from_full_channels(mut channels: Vec<String>) -> Self {
+        channels.retain(|c| nix_channel_list::parse_channel_version(c).is_some());
+        channels.sort_by(|a, b| nix_channel_list::compare_channels(a, b));
+        channels.dedup();
+        channels.push("unstable".to_owned());
+        Self { opts: channels }
+    }
+
+    /// keep only the newest `n` stable channels, unstable is always kept.
+    pub fn with_max_stable(mut self, n: usize) -> Self {
+        let n_stable = self
+            .opts
+            .iter()
+            .filter(|c| nix_channel_list::parse_channel_version(c).is_some())
+            .count();
+        let mut to_drop = n_stable.saturating_sub(n);
+        self.opts.retain(|c| {
+            let drop = to_drop > 0 && nix_channel_list::parse_channel_version(c).is_some();
+            to_drop -= drop as usize;
+            !drop
+        });
+        self
+    }
+
+    /// the newest stable channel, or whatever
+    /// channel there is, if none of them are stable.
+    pub fn latest_stable(&self) -> &str {
+        self.opts
+            .iter()
+            .filter(|c| nix_channel_list::parse_channel_version(c).is_some())
+            .max_by(|a, b| nix_channel_list::compare_channels(a, b))
+            .or(self.opts.first())
+            .map_or("unstable", String::as_str)
+    }
+
+    /// `channel`, if we offer it, otherwise the newest stable channel.
+    pub fn resolve(&self, channel: &str) -> String {
+        if self.opts.iter().any(|c| c == channel) {
+            channel.to_owned()
+        } else {
+            self.latest_stable().to_owned()
+        }
+    }
+}
+
+/// finds out which channels exist, once, when the app starts.
+pub static THREAD_CHANNEL_LIST: Lazy<ActorThread<(), Result<Channels, String>>> = Lazy::new(|| {
+    ActorThread::new(|(), _| Channels::from_channel_list_api().map_err(|e| e.to_string()))
+});
+
+#[test]
+fn test_channels_from_full_channels() {
+    let channels = ["24.05", "23.11", "24.11", "9.03", "24.05"].map(ToOwned::to_owned);
+    let channels = Channels::from_full_channels(channels.to_vec());
+    assert_eq!(
+        channels.opts,
+        ["9.03", "23.11", "24.05", "24.11", "unstable"]
+    );
+
+    let channels = channels.with_max_stable(2);
+    assert_eq!(channels.opts, ["24.05", "24.11", "unstable"]);
+    assert_eq!(channels.clone().with_max_stable(0).opts, ["unstable"]);
+    // there's nothing to drop when there are fewer than n
+    assert_eq!(channels.clone().with_max_stable(10), channels);
+}
+
+#[test]
+fn test_channels_resolve() {
+    let channels = Channels::from_full_channels(vec!["24.05".to_owned(), "24.11".to_owned()]);
+    assert_eq!(channels.latest_stable(), "24.11");
+    assert_eq!(channels.resolve("24.05"), "24.05");
+    assert_eq!(channels.resolve("unstable"), "unstable");
+    assert_eq!(channels.resolve("19.09"), "24.11");
+
+    let channels = Channels::from_full_channels(Vec::new());
+    assert_eq!(channels.latest_stable(), "unstable");
 }
 
 pub static THREAD_SEARCHER: Lazy<
     ActorThread<(String, SearchProperties), Result<Selectable<NixPackage>, String>>,
 > = Lazy::new(|| {
     ActorThread::new(
-        |(search_text, search_props): (String, SearchProperties)| match search::search(
+        |(search_text, search_props): (String, SearchProperties), _| match search::search(
             search_text.as_str(),
-            search_props.mode,
-            Channels::new().opts[search_props.channel].clone(),
+            &search_props,
         ) {
             Ok(mut val) => {
-                val.sort_by_cached_key(|v| {
-                    let has_exact_binary_match = v.package_programs.contains(&search_text);
-                    (
-                        has_exact_binary_match,
-                        search_by_name_metric(&search_text.to_owned(), &v.package_attr_name),
-                    )
-                });
+                if search_props.mode == SearchMode::Description {
+                    // the name is of no help when searching by description,
+                    // so just float the literal matches to the top.
+                    val.sort_by_cached_key(|v| search::description_contains(v, &search_text));
+                } else {
+                    val.sort_by_cached_key(|v| {
+                        let has_exact_binary_match = v.package_programs.contains(&search_text);
+                        (
+                            has_exact_binary_match,
+                            search_by_name_metric(&search_text.to_owned(), &v.package_attr_name),
+                        )
+                    });
+                }
 
                 val.reverse();
                 let val = val
@@ -1136,6 +1699,51 @@ pub static THREAD_SEARCHER: Lazy<
     )
 });
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstallState {
+    Installing,
+    Installed,
+    Failed,
+}
+
+impl InstallState {
+    /// the text of the install button, no state
+    /// means that we haven't tried to install it yet.
+    pub fn label(state: Option<InstallState>) -> &'static str {
+        match state {
+            None => "Install",
+            Some(InstallState::Installing) => "Installing...",
+            Some(InstallState::Installed) => "Installed",
+            Some(InstallState::Failed) => "Failed",
+        }
+    }
+}
+
+/// installs packages into the user's profile, replying
+/// with the attr name and whether nix succeeded.
+pub static THREAD_INSTALLER: Lazy<ActorThread<(String, String), (String, bool)>> =
+    Lazy::new(|| {
+        ActorThread::new(
+            |(attr_name, install_spec): (String, String), token: CancellationToken| {
+                // cancelled while it was still queued
+                if token.is_cancelled() {
+                    return (attr_name, false);
+                }
+                let installed = nix()
+                    .args(["profile", "install", install_spec.as_str()])
+                    .spawn()
+                    .and_then(|mut child| actor::wait_or_kill(&mut child, &token))
+                    .is_ok_and(|status| status.is_some_and(|status| status.success()));
+                (attr_name, installed)
+            },
+        )
+    });
+
+/// the token for each install which is queued or running, so
+/// that cancelling one package leaves the others alone.
+pub static INSTALL_TOKENS: Lazy<parking_lot::Mutex<HashMap<String, CancellationToken>>> =
+    Lazy::new(Default::default);
+
 #[derive(Clone, Debug)]
 pub enum SearchingState {
     Idle,
@@ -1150,100 +1758,231 @@ pub struct LoadingWidgetState {
     back_tail: usize,
     front_tail: usize,
     cycles: usize,
+    n_seg: usize,
 }
 
+/// how many snowflakes the loading widget usually has.
+const LOADING_SEGMENTS: usize = 8;
+
 impl LoadingWidgetState {
     pub fn new() -> Self {
+        Self::with_segments(LOADING_SEGMENTS)
+    }
+
+    pub fn with_segments(n: usize) -> Self {
+        assert!(n > 0, "a loading widget needs at least one segment");
         Self {
             back_tail: 0,
-            front_tail: 3,
+            // the lit up tail is 3 long, unless there's too few to fit it.
+            front_tail: 3.min(n - 1),
             cycles: 0,
+            n_seg: n,
         }
     }
-    pub fn next_state(&mut self, n_seg: usize) {
+
+    /// a loading widget which shows how far along something is,
+    /// rather than spinning, the first `completed / total` of
+    /// the segments are lit up.
+    pub fn determinate(completed: usize, total: usize) -> Self {
+        let n_seg = LOADING_SEGMENTS;
+        let front_tail = match total {
+            0 => 0,
+            total => completed.min(total) * n_seg / total,
+        };
+        Self {
+            back_tail: 0,
+            front_tail,
+            // already faded in
+            cycles: 10,
+            n_seg,
+        }
+    }
+
+    pub fn next_state(&mut self) {
         self.back_tail += 1;
         self.front_tail += 1;
-        self.back_tail %= n_seg;
-        self.front_tail %= n_seg;
+        self.back_tail %= self.n_seg;
+        self.front_tail %= self.n_seg;
         self.cycles += 1;
     }
-    pub fn style_node(&self, node_num: usize) -> impl Fn(Style) -> Style {
+
+    /// whether the node is part of the tail, for the first few
+    /// cycles nothing is, while everything fades in.
+    fn is_lit(&self, node_num: usize) -> bool {
         let high = self.front_tail;
         let low = self.back_tail;
+        let in_tail = if high < low {
+            node_num >= low || node_num < high
+        } else {
+            node_num >= low && node_num < high
+        };
+        in_tail && self.cycles >= 10
+    }
 
+    pub fn style_node(&self, node_num: usize) -> impl Fn(Style) -> Style {
+        let lit = self.is_lit(node_num);
         let count = self.cycles;
         move |s| {
-            if high < low {
-                if (node_num >= low || node_num < high) && count >= 10 {
-                    s
-                } else {
-                    s.color(Color::rgb8(155, 155, 155).with_alpha_factor(count as f32 / 10.0))
-                }
+            if lit {
+                s
             } else {
-                if node_num >= low && node_num < high && count >= 10 {
-                    s
-                } else {
-                    s.color(Color::rgb8(155, 155, 155).with_alpha_factor(count as f32 / 10.0))
-                }
+                s.color(Color::rgb8(155, 155, 155).with_alpha_factor(count as f32 / 10.0))
             }
         }
     }
 }
-fn loading_widget() -> impl View {
+
+#[test]
+fn test_loading_widget_state() {
+    let lit = |state: &LoadingWidgetState| {
+        (0..state.n_seg)
+            .filter(|node| state.is_lit(*node))
+            .collect::<Vec<_>>()
+    };
+
+    let mut state = LoadingWidgetState::with_segments(5);
+    // nothing is lit while it fades in
+    assert!(lit(&state).is_empty());
+    for _ in 0..10 {
+        state.next_state();
+    }
+    // 10 steps around 5 segments is back where it started
+    assert_eq!(lit(&state), [0, 1, 2]);
+
+    // the tail wraps around the end
+    state.next_state();
+    state.next_state();
+    state.next_state();
+    assert_eq!(lit(&state), [0, 3, 4]);
+    state.next_state();
+    assert_eq!(lit(&state), [0, 1, 4]);
+
+    let mut state = LoadingWidgetState::with_segments(2);
+    for _ in 0..10 {
+        state.next_state();
+    }
+    assert_eq!(lit(&state), [0]);
+    assert_eq!(LoadingWidgetState::new().n_seg, LOADING_SEGMENTS);
+}
+
+#[test]
+fn test_loading_widget_determinate() {
+    let lit =
+        |state: LoadingWidgetState| (0..state.n_seg).filter(|node| state.is_lit(*node)).count();
+
+    assert_eq!(lit(LoadingWidgetState::determinate(0, 5)), 0);
+    assert_eq!(
+        lit(LoadingWidgetState::determinate(1, 2)),
+        LOADING_SEGMENTS / 2
+    );
+    assert_eq!(lit(LoadingWidgetState::determinate(5, 5)), LOADING_SEGMENTS);
+    assert_eq!(lit(LoadingWidgetState::determinate(7, 5)), LOADING_SEGMENTS);
+    assert_eq!(lit(LoadingWidgetState::determinate(0, 0)), 0);
+}
+
+fn loading_widget(segments: usize) -> impl View {
     const SNOWFLAKE_SIZE: f64 = 12.0;
 
-    let loading_state = create_rw_signal(LoadingWidgetState::new());
+    let loading_state = create_rw_signal(LoadingWidgetState::with_segments(segments));
     let animation_trigger = create_trigger();
 
     create_effect(move |_| {
         animation_trigger.track();
         exec_after(std::time::Duration::from_millis(100), move |_| {
             animation_trigger.notify();
-            loading_state.update(|l| l.next_state(8));
+            loading_state.update(|l| l.next_state());
         });
     });
 
-    h_stack((
-        nix_snowflake_svg()
-            .style(|s| s.width(SNOWFLAKE_SIZE).height(SNOWFLAKE_SIZE))
-            .style(move |s| loading_state.get().style_node(0)(s)),
+    h_stack_from_iter((0..segments).map(|node| {
         nix_snowflake_svg()
             .style(|s| s.width(SNOWFLAKE_SIZE).height(SNOWFLAKE_SIZE))
-            .style(move |s| loading_state.get().style_node(1)(s)),
-        nix_snowflake_svg()
-            .style(|s| s.width(SNOWFLAKE_SIZE).height(SNOWFLAKE_SIZE))
-            .style(move |s| loading_state.get().style_node(2)(s)),
-        nix_snowflake_svg()
-            .style(|s| s.width(SNOWFLAKE_SIZE).height(SNOWFLAKE_SIZE))
-            .style(move |s| loading_state.get().style_node(3)(s)),
-        nix_snowflake_svg()
-            .style(|s| s.width(SNOWFLAKE_SIZE).height(SNOWFLAKE_SIZE))
-            .style(move |s| loading_state.get().style_node(4)(s)),
-        nix_snowflake_svg()
-            .style(|s| s.width(SNOWFLAKE_SIZE).height(SNOWFLAKE_SIZE))
-            .style(move |s| loading_state.get().style_node(5)(s)),
-        nix_snowflake_svg()
-            .style(|s| s.width(SNOWFLAKE_SIZE).height(SNOWFLAKE_SIZE))
-            .style(move |s| loading_state.get().style_node(6)(s)),
-        nix_snowflake_svg()
-            .style(|s| s.width(SNOWFLAKE_SIZE).height(SNOWFLAKE_SIZE))
-            .style(move |s| loading_state.get().style_node(7)(s)),
-    ))
+            .style(move |s| loading_state.get().style_node(node)(s))
+    }))
     .style(|s| s.gap(3.0, 0.0))
 }
 
+/// how far the progress bar moves each step while
+/// it catches up to the real progress, in percent.
+const PROGRESS_STEP: f64 = 5.0;
+
+/// a bar which fills up, left to right, as `completed` catches up to `total`.
+fn progress_widget(completed: RwSignal<usize>, total: RwSignal<usize>) -> impl View {
+    let shown = create_rw_signal(0.0);
+    let step = create_trigger();
+    create_effect(move |_| {
+        step.track();
+        let target = match total.get() {
+            0 => 0.0,
+            total => completed.get().min(total) as f64 * 100.0 / total as f64,
+        };
+        let current = shown.get_untracked();
+        if current == target {
+            return;
+        }
+        exec_after(std::time::Duration::from_millis(50), move |_| {
+            let next = if current < target {
+                (current + PROGRESS_STEP).min(target)
+            } else {
+                (current - PROGRESS_STEP).max(target)
+            };
+            shown.set(next);
+            step.notify();
+        });
+    });
+
+    let bar = views::empty()
+        .style(move |s| {
+            s.width(Pct(shown.get()))
+                .height_full()
+                .background(theme().accent)
+                .border_radius(4.0)
+        })
+        .pipe(container)
+        .style(|s| {
+            s.flex_grow(1.0)
+                .height(8.0)
+                .background(theme().bg_minus)
+                .border_radius(4.0)
+        });
+
+    h_stack((
+        bar,
+        label(move || format!("{:.0}%", shown.get())).style(|s| {
+            s.min_width(36.0)
+                .font_size(theme().scaled(10.0))
+                .font_weight(Weight::SEMIBOLD)
+        }),
+    ))
+    .style(|s| s.gap(8.0, 0.0).items_center().width_full())
+}
+
+/// a loading widget with a line of text above it.
+fn loading_widget_labeled(text: impl Fn() -> String + 'static, segments: usize) -> impl View {
+    (
+        label(text).style(|s| s.font_weight(Weight::BOLD).font_size(theme().scaled(14.0))),
+        loading_widget(segments),
+    )
+        .pipe(v_stack)
+        .style(|s| s.gap(0.0, 10.0).items_center())
+}
+
 fn construct_nixpkgs_search(
     active_package_receiver: ReadSignal<Option<Result<Selectable<NixPackage>, String>>>,
+    install_receiver: ReadSignal<Option<(String, bool)>>,
+    environ: RwSignal<env::EnvironmentEntries>,
+    settings: RwSignal<settings::Settings>,
+    channels: RwSignal<Channels>,
 ) -> impl View {
     let search_text = create_rw_signal(String::new());
     let active_packages = create_rw_signal(Selectable::new());
     let searching_state = create_rw_signal(SearchingState::Idle);
 
-    let n_channels = Channels::new().opts.len();
-
     let search_props = create_rw_signal(SearchProperties {
         mode: SearchMode::Name,
-        channel: 0,
+        channel: channels
+            .with_untracked(|c| settings.with_untracked(|s| c.resolve(&s.default_channel))),
+        max_results: settings.with_untracked(|s| s.max_results),
     });
     create_effect(move |_| {
         if let Some(pkg) = active_package_receiver.get() {
@@ -1261,14 +2000,55 @@ fn construct_nixpkgs_search(
             }
         }
     });
+    let installs = create_rw_signal(HashMap::new());
+    create_effect(move |_| {
+        if let Some((attr_name, installed)) = install_receiver.get() {
+            INSTALL_TOKENS.lock().remove(&attr_name);
+            if installed {
+                environ.update(|env| {
+                    env.push_simple_if_absent(&attr_name);
+                });
+            }
+            let state = if installed {
+                InstallState::Installed
+            } else {
+                InstallState::Failed
+            };
+            installs.update(|i| {
+                i.insert(attr_name, state);
+            });
+        }
+    });
     let search_init = create_trigger();
 
+    let search_history = create_rw_signal(history::load_history());
+    // how far back into the history the user has gone with
+    // the arrow keys, and what they'd typed before they did.
+    let history_cursor = create_rw_signal(None::<usize>);
+    let history_draft = create_rw_signal(String::new());
+
+    // the timer for the search which will run once the user
+    // stops typing, a timer which fires after it has been
+    // replaced here has been cancelled and does nothing.
+    let pending_search = create_rw_signal(None::<TimerToken>);
     create_effect(move |t| {
-        if search_text.get().is_empty() && t.is_some() {
-            searching_state.set(SearchingState::Idle);
-            active_packages.set(Selectable::new());
+        if search_text.get().is_empty() {
+            pending_search.set(None);
+            if t.is_some() {
+                searching_state.set(SearchingState::Idle);
+                active_packages.set(Selectable::new());
+            }
             return;
         }
+        // how long to wait after the last keystroke before searching.
+        let debounce = std::time::Duration::from_millis(settings.with_untracked(|s| s.debounce_ms));
+        let token = exec_after(debounce, move |token| {
+            if pending_search.get_untracked() == Some(token) {
+                pending_search.set(None);
+                search_init.notify();
+            }
+        });
+        pending_search.set(Some(token));
     });
     create_effect(move |_| {
         // i think early exit screws with it.
@@ -1280,11 +2060,18 @@ fn construct_nixpkgs_search(
         if search_text.is_empty() {
             return;
         }
+        // browsing the history shouldn't reorder it
+        if history_cursor.get_untracked().is_none() {
+            search_history.update(|h| history::push_history(h, &search_text));
+            if let Err(err) = history::save_history(&search_history.get_untracked()) {
+                eprintln!("failed to save search history: {err}");
+            }
+        }
         active_packages.set(Selectable::new());
         searching_state.set(SearchingState::Fetching);
 
         THREAD_SEARCHER
-            .send((search_text.to_owned(), search_props.get()))
+            .send_cancelling_previous((search_text.to_owned(), search_props.get()))
             .unwrap();
     });
 
@@ -1309,12 +2096,54 @@ fn construct_nixpkgs_search(
         .on_event(EventListener::KeyDown, move |e| {
             if let Event::KeyDown(ev) = e {
                 if ev.key.logical_key == Key::Named(NamedKey::Enter) {
+                    // skip the debounce and search right away.
+                    pending_search.set(None);
+                    history_cursor.set(None);
                     search_init.notify();
                 }
 
+                if ev.key.logical_key == Key::Named(NamedKey::ArrowUp) {
+                    let cursor = history_cursor.get_untracked();
+                    let history = search_history.get_untracked();
+                    if let Some(next) = history::older(cursor, history.len()) {
+                        if cursor.is_none() {
+                            history_draft.set(search_text.get_untracked());
+                        }
+                        history_cursor.set(Some(next));
+                        search_text.set(history[next].clone());
+                    }
+                }
+
+                if ev.key.logical_key == Key::Named(NamedKey::ArrowDown) {
+                    match history_cursor.get_untracked() {
+                        Some(0) => {
+                            history_cursor.set(None);
+                            search_text.set(history_draft.get_untracked());
+                        }
+                        Some(idx) => {
+                            history_cursor.set(Some(idx - 1));
+                            search_text.set(search_history.get_untracked()[idx - 1].clone());
+                        }
+                        None => {}
+                    }
+                }
+
+                if ev.key.logical_key == Key::Named(NamedKey::Escape)
+                    && history_cursor.get_untracked().is_some()
+                {
+                    history_cursor.set(None);
+                    search_text.set(history_draft.get_untracked());
+                }
+
                 if ev.key.physical_key == KeyCode::KeyW && ev.modifiers.super_key() {
                     quit_app();
                 }
+
+                if ev.modifiers.control_key() {
+                    if let Some(mode) = SearchMode::from_shortcut(ev.key.physical_key) {
+                        search_props.update(|s| s.mode = mode);
+                    }
+                }
             }
             EventPropagation::Stop
         });
@@ -1334,50 +2163,57 @@ fn construct_nixpkgs_search(
             .justify_center()
     };
     let choose_mode = views::dyn_container(
-        move || search_props.get(),
-        move |sp| {
+        move || (search_props.get(), channels.get()),
+        move |(sp, channels)| {
             h_stack((
-                static_label("By Name")
-                    .pipe(views::container)
-                    .style(move |s| {
-                        style_func(s).apply_if(sp.mode == SearchMode::Name, |s| {
-                            s.background(theme().accent)
-                                .border_color(Color::rgba8(0, 0, 0, 0))
-                                .font_weight(Weight::SEMIBOLD)
-                        })
-                    })
-                    .on_click_stop(move |_| search_props.update(|s| s.mode = SearchMode::Name)),
-                static_label("By Program")
-                    .pipe(views::container)
-                    .style(move |s| {
-                        style_func(s).apply_if(sp.mode == SearchMode::Program, |s| {
-                            s.background(theme().accent)
-                                .border_color(Color::rgba8(0, 0, 0, 0))
-                                .font_weight(Weight::SEMIBOLD)
-                        })
-                    })
-                    .on_click_stop(move |_e| search_props.update(|s| s.mode = SearchMode::Program)),
-                views::empty().style(|s| s.flex_grow(1.0)),
-                // create the channel list
                 dyn_stack(
-                    move || 0..n_channels,
-                    |channel_idx| *channel_idx,
-                    move |channel_idx| {
-                        static_label(format!("{}", Channels::new().opts[channel_idx]))
-                            .style(style_func)
+                    move || SearchMode::ALL,
+                    |mode| *mode,
+                    move |mode| {
+                        static_label(mode.label())
+                            .pipe(views::container)
                             .style(move |s| {
-                                s.apply_if(sp.channel == channel_idx, |s| {
+                                style_func(s).apply_if(sp.mode == mode, |s| {
                                     s.background(theme().accent)
                                         .border_color(Color::rgba8(0, 0, 0, 0))
                                         .font_weight(Weight::SEMIBOLD)
                                 })
                             })
-                            .on_click_stop(move |_e| {
-                                search_props.update(|s| s.channel = channel_idx)
-                            })
+                            .on_click_stop(move |_| search_props.update(|s| s.mode = mode))
                     },
                 )
                 .style(|s| s.flex().flex_row().gap(5.0, 0.0)),
+                views::empty().style(|s| s.flex_grow(1.0)),
+                h_stack_from_iter(SearchProperties::RESULT_COUNTS.map(|count| {
+                    static_label(count.to_string())
+                        .style(style_func)
+                        .style(move |s| {
+                            s.apply_if(sp.max_results == count, |s| {
+                                s.background(theme().accent)
+                                    .border_color(Color::rgba8(0, 0, 0, 0))
+                                    .font_weight(Weight::SEMIBOLD)
+                            })
+                        })
+                        .on_click_stop(move |_e| search_props.update(|s| s.max_results = count))
+                }))
+                .style(|s| s.flex().flex_row().gap(5.0, 0.0).margin_right(10.0)),
+                // create the channel list
+                h_stack_from_iter(channels.opts.into_iter().map(|channel| {
+                    let active = sp.channel == channel;
+                    static_label(channel.clone())
+                        .style(style_func)
+                        .style(move |s| {
+                            s.apply_if(active, |s| {
+                                s.background(theme().accent)
+                                    .border_color(Color::rgba8(0, 0, 0, 0))
+                                    .font_weight(Weight::SEMIBOLD)
+                            })
+                        })
+                        .on_click_stop(move |_e| {
+                            search_props.update(|s| s.channel = channel.clone())
+                        })
+                }))
+                .style(|s| s.flex().flex_row().gap(5.0, 0.0)),
             ))
             .style(|s| s.gap(5.0, 0.0).width_full())
             .pipe(Box::new)
@@ -1397,10 +2233,12 @@ fn construct_nixpkgs_search(
                 let nix_repo_svg =
                     views::svg(|| instr!("../../../assets/nix-repro.svg").to_owned())
                         .style(|s| s.width(125).aspect_ratio(1.0).margin_bottom(15.0));
-                let label = static_label("Search 100k+ packages")
-                    .style(|s| s.font_weight(Weight::NORMAL).font_size(14.0));
+                let label = static_label("Search 100k+ packages").style(|s| {
+                    s.font_weight(Weight::NORMAL)
+                        .font_size(theme().scaled(14.0))
+                });
                 let label_top = static_label("Reproducible. Declarative. Reliable.")
-                    .style(|s| s.font_weight(Weight::BOLD).font_size(18.0));
+                    .style(|s| s.font_weight(Weight::BOLD).font_size(theme().scaled(18.0)));
 
                 (nix_repo_svg, label_top, label)
                     .pipe(v_stack)
@@ -1413,40 +2251,39 @@ fn construct_nixpkgs_search(
                     })
                     .pipe(Box::new)
             }
-            SearchingState::Fetching => (
-                static_label("Searching for packages")
-                    .style(|s| s.font_weight(Weight::BOLD).font_size(14.0)),
-                loading_widget(),
-            )
-                .pipe(v_stack)
-                .style(|s| {
-                    s.flex()
-                        .flex_col()
-                        .gap(0.0, 10.0)
-                        .items_center()
-                        .justify_center()
-                        .width_full()
-                        .height_full()
-                })
-                .pipe(Box::new),
-            SearchingState::ResultsAvailable => search_result_card(active_packages)
-                .style(|s| s.flex_col().gap(0, 10).min_width(0))
-                .pipe(container)
-                .style(|s| {
-                    s.padding_vert(15.0)
-                        .padding_left(0.0)
-                        .padding_right(12.0)
-                        .min_width(0)
-                        .width_full()
-                })
-                .pipe(scroll)
-                .style(|s| {
-                    s.min_height(0)
-                        .max_height_full()
-                        .max_width_full()
-                        .width_full()
-                })
-                .pipe(Box::new),
+            SearchingState::Fetching => {
+                loading_widget_labeled(|| "Searching for packages".to_owned(), LOADING_SEGMENTS)
+                    .pipe(container)
+                    .style(|s| {
+                        s.flex()
+                            .flex_col()
+                            .items_center()
+                            .justify_center()
+                            .width_full()
+                            .height_full()
+                    })
+                    .pipe(Box::new)
+            }
+            SearchingState::ResultsAvailable => {
+                search_result_card(active_packages, search_props, installs)
+                    .style(|s| s.flex_col().gap(0, 10).min_width(0))
+                    .pipe(container)
+                    .style(|s| {
+                        s.padding_vert(15.0)
+                            .padding_left(0.0)
+                            .padding_right(12.0)
+                            .min_width(0)
+                            .width_full()
+                    })
+                    .pipe(scroll)
+                    .style(|s| {
+                        s.min_height(0)
+                            .max_height_full()
+                            .max_width_full()
+                            .width_full()
+                    })
+                    .pipe(Box::new)
+            }
             SearchingState::NoResultsAvailable => {
                 let nix_repo_svg =
                     views::svg(|| instr!("../../../assets/nix-repro.svg").to_owned())
@@ -1454,7 +2291,7 @@ fn construct_nixpkgs_search(
                 let label = static_label(
                     "We searched far and wide, but no trace of your query was found :(",
                 )
-                .style(|s| s.font_weight(Weight::BOLD).font_size(14.0));
+                .style(|s| s.font_weight(Weight::BOLD).font_size(theme().scaled(14.0)));
 
                 (nix_repo_svg, label)
                     .pipe(v_stack)
@@ -1469,7 +2306,7 @@ fn construct_nixpkgs_search(
             }
             SearchingState::AnErrorOccurred(err) => (
                 static_label("Oh no! An Error Occurred")
-                    .style(|s| s.font_size(14.0).font_weight(Weight::BOLD)),
+                    .style(|s| s.font_size(theme().scaled(14.0)).font_weight(Weight::BOLD)),
                 static_label(err).style(|s| s.max_width_full()),
             )
                 .pipe(v_stack)
@@ -1488,13 +2325,25 @@ fn construct_nixpkgs_search(
         },
     )
     .style(|s| s.flex().flex_grow(1.0).min_width(0).min_height(0));
-    (search_section, results_section).pipe(v_stack).style(|s| {
+    let search_column = (search_section, results_section).pipe(v_stack).style(|s| {
         s.gap(0.0, 10.0)
             .min_width(0)
             .min_height(0)
             .flex_grow(1.0)
             .max_width(420.0)
-    })
+    });
+
+    let selected_package = create_rw_signal(None);
+    create_effect(move |_| {
+        selected_package.set(active_packages.with(|p| p.selected_item().cloned()));
+    });
+
+    (
+        search_column,
+        detail::package_detail_panel(selected_package, search_props),
+    )
+        .pipe(h_stack)
+        .style(|s| s.gap(15.0, 0.0).min_width(0).min_height(0).height_full())
 }
 
 fn nix_snowflake_svg() -> views::Svg {
@@ -1503,8 +2352,11 @@ fn nix_snowflake_svg() -> views::Svg {
 
 fn create_project_menu(project_name: RwSignal<String>) -> impl View {
     let choice = create_rw_signal(ProjectCreationLocation::ExistingDirectory);
-    let title = label(move || String::from("Create Project"))
-        .style(|s| s.padding(10.0).font_size(24.0).font_weight(Weight::BOLD));
+    let title = label(move || String::from("Create Project")).style(|s| {
+        s.padding(10.0)
+            .font_size(theme().scaled(24.0))
+            .font_weight(Weight::BOLD)
+    });
 
     let existing_or_new_folder_dialog = views::stack((
         radio_button(16.0, choice, ProjectCreationLocation::ExistingDirectory),
@@ -1542,7 +2394,7 @@ fn create_project_menu(project_name: RwSignal<String>) -> impl View {
     .pipe(container);
 
     let in_common_directory = label(move || "Project Location").style(|s| {
-        s.font_size(18.0)
+        s.font_size(theme().scaled(18.0))
             .font_weight(Weight::SEMIBOLD)
             .padding_left(20.0)
             .padding_top(10.0)
@@ -1647,6 +2499,10 @@ fn main() -> Result<(), ProgramError> {
     //     "testing"
     // )?);
 
+    let settings = settings::Settings::load();
+    *theme::THEME_MODE.lock() = settings.theme;
+    theme::THEME.lock().clone_from(&settings.theme());
+
     floem::Application::new()
         .window(
             move |_| {
@@ -4,7 +4,7 @@ use nix_elastic_search::MatchName;
 use nix_elastic_search::MatchProgram;
 use nix_elastic_search::MatchSearch;
 use nix_elastic_search::NixSearchError;
-use once_cell::sync::Lazy;
+use nix_installed_list::nix_system;
 use once_cell::sync::OnceCell;
 use ordered_float::NotNan;
 use serde::Deserialize;
@@ -20,6 +20,7 @@ use crate::DeserializeSnafu;
 use crate::ProcessSnafu;
 use crate::ProgramError;
 use crate::SearchMode;
+use crate::SearchProperties;
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum PackageSupport {
@@ -55,83 +56,181 @@ pub struct FlakeResolved {
 }
 
 use bstr::ByteSlice;
-pub fn search(
-    query: &str,
-    mode: SearchMode,
-    channel: String,
-) -> Result<Vec<nix_elastic_search::response::NixPackage>, NixSearchError> {
-    let (program, name) = match mode {
-        SearchMode::Name => (
-            None,
-            Some(MatchSearch {
-                search: query.to_owned(),
-            }),
-        ),
-        SearchMode::Program => (
-            Some(MatchProgram {
-                program: query.to_owned(),
-            }),
-            None,
-        ),
+/// the query that a search for `query` sends to search.nixos.org.
+pub fn search_query(query: &str, props: &SearchProperties) -> nix_elastic_search::Query {
+    let search = || {
+        Some(MatchSearch {
+            search: query.to_owned(),
+        })
+    };
+    let program = || {
+        Some(MatchProgram {
+            program: query.to_owned(),
+        })
+    };
+    // MatchSearch already covers the name and description fields,
+    // description mode differs only in how the results are ranked.
+    let (program, name) = match props.mode {
+        SearchMode::Name | SearchMode::Description => (None, search()),
+        SearchMode::Program => (program(), None),
+        SearchMode::Combined => (program(), search()),
     };
-    let query = nix_elastic_search::Query {
-        max_results: 25,
-        search_within: nix_elastic_search::SearchWithin::Channel(channel),
+    nix_elastic_search::Query {
+        max_results: props.max_results,
+        search_within: nix_elastic_search::SearchWithin::Channel(props.channel.clone()),
         search: name,
         program,
         name: None,
         version: None,
         query_string: None,
-    };
+    }
+}
 
-    query.send()
+pub fn search(
+    query: &str,
+    props: &SearchProperties,
+) -> Result<Vec<nix_elastic_search::response::NixPackage>, NixSearchError> {
+    search_query(query, props).send()
 }
 
-fn longest_common_subsequence_length(seq1: &[u8], seq2: &[u8]) -> usize {
-    let mut dp = vec![vec![0; seq2.len() + 1]; seq1.len() + 1];
+/// what to hand to `nix profile install` for a
+/// package found on the given channel of nixpkgs.
+pub fn nix_install_spec(attr_name: &str, channel: &str) -> String {
+    format!("github:NixOS/nixpkgs/nixos-{channel}#{attr_name}")
+}
 
-    for i in 1..=seq1.len() {
-        for j in 1..=seq2.len() {
-            if seq1[i - 1] == seq2[j - 1] {
-                dp[i][j] = dp[i - 1][j - 1] + 1;
-            } else {
-                dp[i][j] = dp[i - 1][j].max(dp[i][j - 1]);
-            }
-        }
+/// where the package is defined in nixpkgs on github, `position` is
+/// what search.nixos.org gives us, such as `pkgs/foo/default.nix:12`.
+pub fn nix_github_url(position: &str, channel: &str) -> Option<String> {
+    let (path, line) = match position.rsplit_once(':') {
+        Some((path, line)) if line.parse::<u32>().is_ok() => (path, Some(line)),
+        _ => (position, None),
+    };
+    if path.is_empty() {
+        return None;
     }
+    let mut url = format!("https://github.com/NixOS/nixpkgs/blob/nixos-{channel}/{path}");
+    if let Some(line) = line {
+        url.push_str("#L");
+        url.push_str(line);
+    }
+    Some(url)
+}
 
-    dp[seq1.len()][seq2.len()]
+/// the package's page on search.nixos.org.
+pub fn nix_search_url(attr_name: &str, channel: &str) -> String {
+    Url::parse_with_params(
+        "https://search.nixos.org/packages",
+        [
+            ("channel", channel),
+            ("show", attr_name),
+            ("query", attr_name),
+        ],
+    )
+    .expect("search.nixos.org is a valid url")
+    .to_string()
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
-pub struct QueryQuality {
-    dist: usize,
-    proportionality: isize,
+pub trait NixPackageExt {
+    /// the spec `gnix install` and `nix profile install`
+    /// take for this package, from the given channel.
+    fn nix_install_spec(&self, channel: &str) -> String;
+
+    /// the file the package is defined in, on github, there
+    /// isn't one when search.nixos.org doesn't know where it is.
+    fn nix_github_url(&self, channel: &str) -> Option<String>;
+
+    fn nix_search_url(&self, channel: &str) -> String;
+
+    /// whether any of the package's licenses are non-free.
+    fn is_unfree(&self) -> bool;
 }
 
-pub fn search_by_name_metric(query: &str, name: &str) -> QueryQuality {
-    QueryQuality {
-        // sort first by the longest common subsequence between the queries
-        dist: longest_common_subsequence_length(query.as_bytes(), name.as_bytes()),
-        // next sort how many characters are different between the queries.
-        proportionality: -(query.len().abs_diff(name.len()) as isize),
+impl NixPackageExt for NixPackage {
+    fn nix_install_spec(&self, channel: &str) -> String {
+        nix_install_spec(&self.package_attr_name, channel)
+    }
+
+    fn nix_github_url(&self, channel: &str) -> Option<String> {
+        nix_github_url(self.package_position.as_deref()?, channel)
+    }
+
+    fn nix_search_url(&self, channel: &str) -> String {
+        nix_search_url(&self.package_attr_name, channel)
+    }
+
+    fn is_unfree(&self) -> bool {
+        nix_search_sort_utils::is_unfree(self)
     }
 }
 
-// retrives the active working system. This call is lazy and will
-// not call the shell after the first invocation.
-pub fn nix_system() -> &'static str {
-    static CURRENT_SYSTEM: Lazy<String> = Lazy::new(|| {
-        let mut cmd = std::process::Command::new("/nix/var/nix/profiles/default/bin/nix");
-        cmd.args(&[
-            "eval",
-            "--impure",
-            "--raw",
-            "--expr",
-            "builtins.currentSystem",
-        ]);
-
-        dbg!(cmd.output().unwrap().stdout.as_bstr().to_string())
-    });
-    CURRENT_SYSTEM.as_str()
+#[test]
+fn test_nix_install_spec() {
+    assert_eq!(
+        nix_install_spec("ripgrep", "24.11"),
+        "github:NixOS/nixpkgs/nixos-24.11#ripgrep"
+    );
+    assert_eq!(
+        nix_install_spec("python312Packages.requests", "unstable"),
+        "github:NixOS/nixpkgs/nixos-unstable#python312Packages.requests"
+    );
+
+    let pkg = NixPackage {
+        package_attr_name: "gleam".to_owned(),
+        ..Default::default()
+    };
+    assert_eq!(
+        pkg.nix_install_spec("24.11"),
+        "github:NixOS/nixpkgs/nixos-24.11#gleam"
+    );
+}
+
+/// whether the query turns up verbatim, ignoring
+/// case, in the package's short or long description.
+pub fn description_contains(pkg: &NixPackage, query: &str) -> bool {
+    let query = query.to_lowercase();
+    [&pkg.package_description, &pkg.package_long_description]
+        .into_iter()
+        .flatten()
+        .any(|desc| desc.to_lowercase().contains(&query))
+}
+
+#[test]
+fn test_nix_github_url() {
+    assert_eq!(
+        nix_github_url("pkgs/by-name/ri/ripgrep/package.nix:42", "24.11").as_deref(),
+        Some("https://github.com/NixOS/nixpkgs/blob/nixos-24.11/pkgs/by-name/ri/ripgrep/package.nix#L42")
+    );
+    assert_eq!(
+        nix_github_url("pkgs/development/compilers/gleam/default.nix", "unstable").as_deref(),
+        Some("https://github.com/NixOS/nixpkgs/blob/nixos-unstable/pkgs/development/compilers/gleam/default.nix")
+    );
+    assert_eq!(nix_github_url("", "24.11"), None);
+
+    let pkg = NixPackage::default();
+    assert_eq!(pkg.nix_github_url("24.11"), None);
+}
+
+#[test]
+fn test_nix_search_url() {
+    let pkg = NixPackage {
+        package_attr_name: "python312Packages.requests".to_owned(),
+        ..Default::default()
+    };
+    assert_eq!(
+        pkg.nix_search_url("24.11"),
+        "https://search.nixos.org/packages?channel=24.11&show=python312Packages.requests&query=python312Packages.requests"
+    );
+}
+
+#[test]
+fn test_search_query_max_results() {
+    let props = SearchProperties::default();
+    assert_eq!(search_query("ripgrep", &props).max_results, 25);
+
+    let props = SearchProperties {
+        max_results: 100,
+        ..props
+    };
+    assert_eq!(search_query("ripgrep", &props).max_results, 100);
 }
@@ -0,0 +1,396 @@
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use floem::{
+    cosmic_text::Weight,
+    peniko::Color,
+    reactive::{create_effect, create_rw_signal, RwSignal},
+    style::{CursorStyle, FlexWrap},
+    view::View,
+    views::{
+        dyn_container, h_stack, h_stack_from_iter, label, static_label, text_input, v_stack,
+        Decorators,
+    },
+};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use tap::Pipe;
+
+use crate::theme::{theme, ApplicationThemeMode, Theme, THEME, THEME_MODE};
+use crate::Channels;
+
+#[derive(Debug, Snafu)]
+pub enum SettingsError {
+    #[snafu(display("failed to write settings to {}: {source}", path.display()))]
+    WriteSettings {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("failed to serialize settings: {source}"))]
+    SerializeSettings { source: toml::ser::Error },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FontSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl FontSize {
+    pub const ALL: [FontSize; 3] = [FontSize::Small, FontSize::Medium, FontSize::Large];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FontSize::Small => "Small",
+            FontSize::Medium => "Medium",
+            FontSize::Large => "Large",
+        }
+    }
+
+    pub fn scale(self) -> f32 {
+        match self {
+            FontSize::Small => 0.85,
+            FontSize::Medium => 1.0,
+            FontSize::Large => 1.15,
+        }
+    }
+}
+
+/// how long to wait after the last keystroke before searching.
+pub const DEFAULT_DEBOUNCE_MS: u64 = 350;
+pub const DEBOUNCE_MS_RANGE: RangeInclusive<u64> = 0..=2000;
+/// how much the debounce buttons change it by.
+const DEBOUNCE_STEP_MS: u64 = 50;
+
+pub const DEFAULT_MAX_RESULTS: u32 = 25;
+pub const MAX_RESULTS_RANGE: RangeInclusive<u32> = 1..=100;
+
+/// everything the user can change in the settings screen,
+/// missing keys fall back to their defaults, so older
+/// settings files keep working as we add more.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: ApplicationThemeMode,
+    pub font_size: FontSize,
+    pub debounce_ms: u64,
+    /// the channel which is selected when the search opens.
+    pub default_channel: String,
+    pub max_results: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: ApplicationThemeMode::default(),
+            font_size: FontSize::default(),
+            debounce_ms: DEFAULT_DEBOUNCE_MS,
+            default_channel: Channels::new().latest_stable().to_owned(),
+            max_results: DEFAULT_MAX_RESULTS,
+        }
+    }
+}
+
+/// ~/.config/newt, respecting XDG_CONFIG_HOME
+pub fn config_dir() -> PathBuf {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".config"),
+    };
+    base.join("newt")
+}
+
+pub fn settings_path() -> PathBuf {
+    config_dir().join("settings.toml")
+}
+
+/// a custom theme, which takes the place of the dark or light one.
+pub fn theme_path() -> PathBuf {
+    config_dir().join("theme.toml")
+}
+
+impl Settings {
+    /// the saved settings, a missing or broken settings
+    /// file shouldn't stop the app from opening, so
+    /// we just use the defaults instead.
+    pub fn load() -> Settings {
+        let mut settings = std::fs::read_to_string(settings_path())
+            .ok()
+            .and_then(|text| toml::from_str::<Settings>(&text).ok())
+            .unwrap_or_default();
+        settings.validate();
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let path = settings_path();
+        let mut settings = self.clone();
+        settings.validate();
+        let text = toml::to_string_pretty(&settings).context(SerializeSettingsSnafu)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context(WriteSettingsSnafu { path: &path })?;
+        }
+        std::fs::write(&path, text).context(WriteSettingsSnafu { path })
+    }
+
+    /// pull anything out of range back into it, the settings
+    /// file is easy enough to edit by hand that it could hold anything.
+    pub fn validate(&mut self) {
+        self.debounce_ms = self
+            .debounce_ms
+            .clamp(*DEBOUNCE_MS_RANGE.start(), *DEBOUNCE_MS_RANGE.end());
+        self.max_results = self
+            .max_results
+            .clamp(*MAX_RESULTS_RANGE.start(), *MAX_RESULTS_RANGE.end());
+        self.default_channel = self.default_channel.trim().to_owned();
+        if self.default_channel.is_empty() {
+            self.default_channel = Settings::default().default_channel;
+        }
+    }
+
+    /// the theme to draw with, a hand written theme wins
+    /// over the dark or light one.
+    pub fn theme(&self) -> Theme {
+        self.theme_from(Theme::load_from_file(&theme_path()).ok())
+    }
+
+    /// the font size applies whether the theme is custom or not.
+    fn theme_from(&self, custom: Option<Theme>) -> Theme {
+        Theme {
+            font_scale: self.font_size.scale(),
+            ..custom.unwrap_or_else(|| self.theme.theme())
+        }
+    }
+}
+
+/// change the settings and save them, swapping out the global theme to
+/// match, views only pick the new theme up when they're rebuilt, which
+/// happens whenever the theme or font size in `settings` changes.
+pub fn update(settings: RwSignal<Settings>, f: impl FnOnce(&mut Settings)) {
+    let old = settings.get_untracked();
+    let mut new = old.clone();
+    f(&mut new);
+    new.validate();
+    if new.theme != old.theme || new.font_size != old.font_size {
+        *THEME_MODE.lock() = new.theme;
+        THEME.lock().clone_from(&new.theme());
+    }
+    if let Err(err) = new.save() {
+        eprintln!("{err}");
+    }
+    settings.set(new);
+}
+
+fn option_style(s: floem::style::Style, active: bool) -> floem::style::Style {
+    s.padding_vert(3.0)
+        .padding_horiz(12.0)
+        .background(theme().bg_plus)
+        .border_color(theme().bd)
+        .border(1.0)
+        .border_radius(15.0)
+        .cursor(CursorStyle::Pointer)
+        .apply_if(active, |s| {
+            s.background(theme().accent)
+                .color(theme().fg_on_accent)
+                .border_color(Color::rgba8(0, 0, 0, 0))
+                .font_weight(Weight::SEMIBOLD)
+        })
+}
+
+fn setting_row(name: &'static str, control: impl View + 'static) -> impl View {
+    h_stack((
+        static_label(name).style(|s| s.font_weight(Weight::BOLD).min_width(120.0)),
+        control,
+    ))
+    .style(|s| s.items_center())
+}
+
+/// a row of options, with the one that's set highlighted.
+fn choices<T: Clone + PartialEq + 'static>(
+    settings: RwSignal<Settings>,
+    options: impl IntoIterator<Item = T>,
+    name: impl Fn(&T) -> String,
+    current: impl Fn(&Settings) -> T + Copy + 'static,
+    pick: impl Fn(&mut Settings, T) + Copy + 'static,
+) -> impl View {
+    h_stack_from_iter(options.into_iter().map(|option| {
+        let text = name(&option);
+        let active = {
+            let option = option.clone();
+            move || settings.with(|s| current(s) == option)
+        };
+        static_label(text)
+            .style(move |s| option_style(s, active()))
+            .on_click_stop(move |_| {
+                let option = option.clone();
+                update(settings, |s| pick(s, option));
+            })
+    }))
+    .style(|s| s.flex_wrap(FlexWrap::Wrap).gap(5.0, 5.0))
+}
+
+fn debounce_stepper(settings: RwSignal<Settings>) -> impl View {
+    h_stack((
+        static_label("-")
+            .style(|s| option_style(s, false))
+            .on_click_stop(move |_| {
+                update(settings, |s| {
+                    s.debounce_ms = s.debounce_ms.saturating_sub(DEBOUNCE_STEP_MS)
+                })
+            }),
+        label(move || format!("{} ms", settings.with(|s| s.debounce_ms)))
+            .style(|s| s.min_width(60.0).justify_center()),
+        static_label("+")
+            .style(|s| option_style(s, false))
+            .on_click_stop(move |_| update(settings, |s| s.debounce_ms += DEBOUNCE_STEP_MS)),
+    ))
+    .style(|s| s.items_center().gap(5.0, 0.0))
+}
+
+/// the max results, typed in, anything that isn't a number is
+/// ignored, and anything out of range is clamped as it's typed.
+fn max_results_input(settings: RwSignal<Settings>) -> impl View {
+    let text = create_rw_signal(settings.get_untracked().max_results.to_string());
+    create_effect(move |_| {
+        if let Ok(n) = text.get().trim().parse::<u32>() {
+            if settings.with_untracked(|s| s.max_results != n) {
+                update(settings, |s| s.max_results = n);
+            }
+        }
+    });
+    // keep the text in step with resets and clamping.
+    create_effect(move |_| {
+        let n = settings.with(|s| s.max_results);
+        if text.with_untracked(|t| t.trim().parse::<u32>() != Ok(n)) {
+            text.set(n.to_string());
+        }
+    });
+    text_input(text).style(|s| crate::style::text_input(s).width(80.0).padding_horiz(10.0))
+}
+
+pub fn settings_panel(settings: RwSignal<Settings>, channels: RwSignal<Channels>) -> impl View {
+    let theme_row = choices(
+        settings,
+        ApplicationThemeMode::ALL,
+        |mode| mode.label().to_owned(),
+        |s| s.theme,
+        |s, mode| s.theme = mode,
+    );
+    let font_size_row = choices(
+        settings,
+        FontSize::ALL,
+        |size| size.label().to_owned(),
+        |s| s.font_size,
+        |s, size| s.font_size = size,
+    );
+    // the channels can arrive after the screen is opened
+    let channel_row = dyn_container(
+        move || channels.get(),
+        move |channels| {
+            choices(
+                settings,
+                channels.opts,
+                Clone::clone,
+                |s| s.default_channel.clone(),
+                |s, channel| s.default_channel = channel,
+            )
+            .pipe(Box::new) as Box<dyn View>
+        },
+    );
+
+    let reset = static_label("Reset to defaults")
+        .style(|s| option_style(s, true))
+        .on_click_stop(move |_| update(settings, |s| *s = Settings::default()));
+
+    v_stack((
+        static_label("Settings")
+            .style(|s| s.font_weight(Weight::BOLD).font_size(theme().scaled(16.0))),
+        setting_row("Theme", theme_row),
+        setting_row("Font Size", font_size_row),
+        setting_row("Search Delay", debounce_stepper(settings)),
+        setting_row("Channel", channel_row),
+        setting_row("Max Results", max_results_input(settings)),
+        reset,
+    ))
+    .style(|s| {
+        s.gap(0.0, 12.0)
+            .padding(15.0)
+            .color(theme().fg)
+            .background(theme().bg_plus)
+            .border(1.0)
+            .border_color(theme().bd)
+            .border_radius(10.0)
+    })
+}
+
+#[test]
+fn test_settings_toml_round_trip() {
+    let settings = Settings {
+        theme: ApplicationThemeMode::System,
+        font_size: FontSize::Large,
+        debounce_ms: 200,
+        default_channel: "unstable".to_owned(),
+        max_results: 50,
+    };
+    let text = toml::to_string_pretty(&settings).unwrap();
+    assert_eq!(
+        text.trim(),
+        "theme = \"system\"\nfont_size = \"large\"\ndebounce_ms = 200\n\
+         default_channel = \"unstable\"\nmax_results = 50"
+    );
+    assert_eq!(toml::from_str::<Settings>(&text).unwrap(), settings);
+
+    // settings saved before font sizes existed
+    let settings = toml::from_str::<Settings>(r#"theme = "light""#).unwrap();
+    assert_eq!(settings.font_size, FontSize::Medium);
+
+    // an empty (or outdated) file is just the defaults
+    assert_eq!(toml::from_str::<Settings>("").unwrap(), Settings::default());
+}
+
+#[test]
+fn test_settings_theme_from() {
+    let settings = Settings {
+        theme: ApplicationThemeMode::Light,
+        font_size: FontSize::Large,
+        ..Settings::default()
+    };
+    let theme = settings.theme_from(None);
+    assert_eq!(theme.accent, Theme::light().accent);
+    assert_eq!(theme.font_scale, FontSize::Large.scale());
+
+    // a custom theme keeps its colours, but not its font scale
+    let custom = Theme {
+        accent: Color::rgba8(1, 2, 3, 4),
+        font_scale: 2.0,
+        ..Theme::dark()
+    };
+    let theme = settings.theme_from(Some(custom));
+    assert_eq!(theme.accent, Color::rgba8(1, 2, 3, 4));
+    assert_eq!(theme.font_scale, FontSize::Large.scale());
+}
+
+#[test]
+fn test_settings_validate() {
+    let mut settings = Settings {
+        debounce_ms: 60_000,
+        default_channel: "  ".to_owned(),
+        max_results: 0,
+        ..Settings::default()
+    };
+    settings.validate();
+    assert_eq!(settings.debounce_ms, *DEBOUNCE_MS_RANGE.end());
+    assert_eq!(settings.max_results, *MAX_RESULTS_RANGE.start());
+    assert_eq!(
+        settings.default_channel,
+        Settings::default().default_channel
+    );
+
+    // settings which are already fine are left alone
+    let mut settings = Settings::default();
+    settings.validate();
+    assert_eq!(settings, Settings::default());
+}
@@ -1,7 +1,13 @@
 use std::cell::Cell;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
 
+use floem::ext_event::create_signal_from_channel;
 use floem::peniko::Color;
+use floem::reactive::ReadSignal;
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 
 use crate::tailwind;
 
@@ -19,6 +25,9 @@ pub struct Theme {
     pub fg_on_accent: Color,
     pub accent_dim: Color,
     pub unavailable: Color,
+    /// how much bigger (or smaller) to make text than
+    /// the sizes which are written throughout the views.
+    pub font_scale: f32,
 }
 
 impl Theme {
@@ -36,11 +45,232 @@ impl Theme {
             accent: Color::rgb8(11, 132, 255),
             accent_dim: Color::rgb8(30, 64, 175),
             unavailable: Color::rgb8(100, 116, 139),
+            font_scale: 1.0,
         }
     }
+
+    pub const fn light() -> Theme {
+        Theme {
+            bg: Color::rgb8(245, 246, 247),
+            bg_plus: Color::WHITE,
+            bg_plus2: Color::rgb8(235, 237, 240),
+            bd: Color::rgb8(200, 204, 208),
+            bg_minus: Color::rgb8(232, 234, 237),
+            fg_on_accent: Color::rgb8(245, 245, 245),
+            fg_minus: Color::rgb8(70, 74, 80),
+            fg: Color::rgb8(33, 36, 40),
+            fg_plus: Color::BLACK,
+            accent: Color::rgb8(0, 102, 214),
+            accent_dim: Color::rgb8(147, 197, 253),
+            unavailable: Color::rgb8(148, 163, 184),
+            font_scale: 1.0,
+        }
+    }
+
+    /// the dark or light theme, whichever the operating system
+    /// prefers. to opt out, pick dark or light in the settings
+    /// panel, or set `theme = "dark"` in ~/.config/newt/settings.toml
+    pub fn system() -> Theme {
+        match dark_light::detect() {
+            dark_light::Mode::Light => Theme::light(),
+            dark_light::Mode::Dark | dark_light::Mode::Default => Theme::dark(),
+        }
+    }
+
+    pub fn scaled(&self, size: f32) -> f32 {
+        size * self.font_scale
+    }
+}
+
+#[test]
+fn test_scaled_font_size() {
+    let theme = Theme {
+        font_scale: 1.5,
+        ..Theme::dark()
+    };
+    assert_eq!(theme.scaled(14.0), 21.0);
+    assert_eq!(Theme::dark().scaled(14.0), 14.0);
+}
+
+/// `Color` can't be serialized, so this is `Theme`
+/// with each color as its RGBA components instead.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SerializableTheme {
+    pub bg: [u8; 4],
+    pub bg_plus: [u8; 4],
+    pub bg_plus2: [u8; 4],
+    pub bg_minus: [u8; 4],
+    pub fg_minus: [u8; 4],
+    pub bd: [u8; 4],
+    pub fg: [u8; 4],
+    pub fg_plus: [u8; 4],
+    pub accent: [u8; 4],
+    pub fg_on_accent: [u8; 4],
+    pub accent_dim: [u8; 4],
+    pub unavailable: [u8; 4],
+    #[serde(default = "default_font_scale")]
+    pub font_scale: f32,
+}
+
+fn default_font_scale() -> f32 {
+    1.0
+}
+
+fn rgba(color: Color) -> [u8; 4] {
+    [color.r, color.g, color.b, color.a]
+}
+
+fn color([r, g, b, a]: [u8; 4]) -> Color {
+    Color::rgba8(r, g, b, a)
+}
+
+impl From<Theme> for SerializableTheme {
+    fn from(theme: Theme) -> Self {
+        SerializableTheme {
+            bg: rgba(theme.bg),
+            bg_plus: rgba(theme.bg_plus),
+            bg_plus2: rgba(theme.bg_plus2),
+            bg_minus: rgba(theme.bg_minus),
+            fg_minus: rgba(theme.fg_minus),
+            bd: rgba(theme.bd),
+            fg: rgba(theme.fg),
+            fg_plus: rgba(theme.fg_plus),
+            accent: rgba(theme.accent),
+            fg_on_accent: rgba(theme.fg_on_accent),
+            accent_dim: rgba(theme.accent_dim),
+            unavailable: rgba(theme.unavailable),
+            font_scale: theme.font_scale,
+        }
+    }
+}
+
+impl From<SerializableTheme> for Theme {
+    fn from(theme: SerializableTheme) -> Self {
+        Theme {
+            bg: color(theme.bg),
+            bg_plus: color(theme.bg_plus),
+            bg_plus2: color(theme.bg_plus2),
+            bg_minus: color(theme.bg_minus),
+            fg_minus: color(theme.fg_minus),
+            bd: color(theme.bd),
+            fg: color(theme.fg),
+            fg_plus: color(theme.fg_plus),
+            accent: color(theme.accent),
+            fg_on_accent: color(theme.fg_on_accent),
+            accent_dim: color(theme.accent_dim),
+            unavailable: color(theme.unavailable),
+            font_scale: theme.font_scale,
+        }
+    }
+}
+
+impl Theme {
+    pub fn load_from_file(path: &Path) -> Result<Theme, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str::<SerializableTheme>(&text)?.into())
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let text = toml::to_string_pretty(&SerializableTheme::from(self.clone()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_theme_file_round_trip() {
+    let path = std::env::temp_dir()
+        .join(format!("newt-theme-{}", std::process::id()))
+        .join("theme.toml");
+    let theme = Theme {
+        accent: Color::rgba8(1, 2, 3, 4),
+        font_scale: 1.15,
+        ..Theme::light()
+    };
+    theme.save_to_file(&path).unwrap();
+    let loaded = Theme::load_from_file(&path).unwrap();
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    assert_eq!(
+        SerializableTheme::from(loaded.clone()),
+        SerializableTheme::from(theme)
+    );
+    assert_eq!(rgba(loaded.accent), [1, 2, 3, 4]);
+    assert!(Theme::load_from_file(&path).is_err());
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApplicationThemeMode {
+    #[default]
+    Dark,
+    Light,
+    /// whatever the operating system is using, this
+    /// follows along if the OS changes its mind.
+    System,
+}
+
+impl ApplicationThemeMode {
+    pub const ALL: [ApplicationThemeMode; 3] = [
+        ApplicationThemeMode::Dark,
+        ApplicationThemeMode::Light,
+        ApplicationThemeMode::System,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ApplicationThemeMode::Dark => "Dark",
+            ApplicationThemeMode::Light => "Light",
+            ApplicationThemeMode::System => "System",
+        }
+    }
+
+    pub fn theme(self) -> Theme {
+        match self {
+            ApplicationThemeMode::Dark => Theme::dark(),
+            ApplicationThemeMode::Light => Theme::light(),
+            ApplicationThemeMode::System => Theme::system(),
+        }
+    }
+}
+
+/// how often we ask the OS whether it's switched between dark and light.
+const SYSTEM_THEME_POLL: Duration = Duration::from_secs(2);
+
+/// keep `THEME` in step with the OS while the mode is `System`, the
+/// signal fires (with whether the OS is dark) each time it switches,
+/// which is the cue to rebuild the views.
+pub fn watch_system_theme() -> ReadSignal<Option<bool>> {
+    let (send, recv) = crossbeam::channel::unbounded();
+    std::thread::spawn(move || {
+        let mut was_dark = None;
+        loop {
+            if *THEME_MODE.lock() == ApplicationThemeMode::System {
+                let is_dark = dark_light::detect() != dark_light::Mode::Light;
+                if was_dark.is_some_and(|was_dark| was_dark != is_dark) {
+                    // built the same way as everywhere else, so
+                    // that a custom theme still wins over the OS.
+                    *THEME.lock() = crate::settings::Settings::load().theme();
+                    if send.send(is_dark).is_err() {
+                        break;
+                    }
+                }
+                was_dark = Some(is_dark);
+            } else {
+                was_dark = None;
+            }
+            std::thread::sleep(SYSTEM_THEME_POLL);
+        }
+    });
+    create_signal_from_channel(recv)
 }
 
 pub fn theme() -> Theme {
     THEME.lock().clone()
 }
 pub static THEME: parking_lot::Mutex<Theme> = Mutex::new(Theme::dark());
+pub static THEME_MODE: parking_lot::Mutex<ApplicationThemeMode> =
+    Mutex::new(ApplicationThemeMode::Dark);
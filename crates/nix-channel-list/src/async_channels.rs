@@ -0,0 +1,40 @@
+use crate::{
+    full_channels, latest_stable_channel, parse_channel_list, ChannelRequestError, CHANNEL_LIST_URL,
+};
+
+/// like [`crate::get_channel_text`], but doesn't block the
+/// thread, so it can be used from a GUI or within tokio.
+pub async fn get_channel_text_async() -> Result<String, ChannelRequestError> {
+    let response = reqwest::get(CHANNEL_LIST_URL).await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ChannelRequestError::HttpStatus {
+            code: status.as_u16(),
+            body: response.text().await.unwrap_or_default(),
+        });
+    }
+    Ok(response.text().await?)
+}
+
+/// like [`crate::get_full_channels`], but async.
+pub async fn get_full_channels_async() -> Result<Vec<String>, ChannelRequestError> {
+    let channel_list = parse_channel_list(&get_channel_text_async().await?)?;
+    Ok(full_channels(channel_list))
+}
+
+/// like [`crate::get_latest_stable_channel`], but async.
+pub async fn get_latest_stable_channel_async() -> Result<String, ChannelRequestError> {
+    latest_stable_channel(get_full_channels_async().await?)
+        .ok_or(ChannelRequestError::NoStableChannels)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_full_channels_async() {
+        let channels = get_full_channels_async().await.unwrap();
+        assert!(!channels.is_empty());
+    }
+}
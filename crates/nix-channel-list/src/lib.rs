@@ -1,6 +1,15 @@
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use thiserror::Error;
 
+#[cfg(feature = "async")]
+mod async_channels;
+
+#[cfg(feature = "async")]
+pub use async_channels::{
+    get_channel_text_async, get_full_channels_async, get_latest_stable_channel_async,
+};
+
 #[derive(Error, Debug)]
 pub enum ChannelRequestError {
     #[error("IO error: {source}")]
@@ -9,22 +18,71 @@ pub enum ChannelRequestError {
         source: std::io::Error,
     },
     #[error("Ureq error: {source}")]
-    Ureq {
+    Ureq { source: Box<ureq::Error> },
+    #[cfg(feature = "async")]
+    #[error("Reqwest error: {source}")]
+    Reqwest {
         #[from]
-        source: ureq::Error,
+        source: reqwest::Error,
     },
     #[error("Xml error: {source}")]
     XmlError {
         #[from]
         source: quick_xml::de::DeError,
     },
+    #[error("couldn't connect to {url}")]
+    Timeout { url: String },
+    #[error("the server responded with {code}: {body}")]
+    HttpStatus { code: u16, body: String },
+    #[error("no stable channels were found")]
+    NoStableChannels,
+    #[error("Chrono parse error: {source}, while parsing: {raw}")]
+    ChronoParseError {
+        raw: String,
+        source: chrono::ParseError,
+    },
+    #[error("Xml error: {source}, while parsing: {raw_xml}")]
+    XmlParseFailedWithContext {
+        /// the start of the document which failed to parse,
+        /// at most [`XML_CONTEXT_LEN`] bytes of it.
+        raw_xml: String,
+        source: quick_xml::de::DeError,
+    },
 }
 
+// ureq's error is large, so it's boxed to
+// keep every other result small.
+impl From<ureq::Error> for ChannelRequestError {
+    fn from(source: ureq::Error) -> Self {
+        ChannelRequestError::Ureq {
+            source: Box::new(source),
+        }
+    }
+}
+
+/// how much of a malformed document is kept around
+/// in [`ChannelRequestError::XmlParseFailedWithContext`].
+pub const XML_CONTEXT_LEN: usize = 500;
+
+/// the S3 bucket listing of every channel.
+const CHANNEL_LIST_URL: &str = "https://nix-channels.s3.amazonaws.com/?delimiter=/";
+
+/// fetch the raw S3 bucket listing.
 pub fn get_channel_text() -> Result<String, ChannelRequestError> {
-    let channel_details = ureq::get("https://nix-channels.s3.amazonaws.com/?delimiter=/")
-        .call()?
-        .into_string()?;
-    Ok(channel_details)
+    get_text(CHANNEL_LIST_URL)
+}
+
+/// the body of `url`, a response which isn't a success
+/// is a [`ChannelRequestError::HttpStatus`].
+fn get_text(url: &str) -> Result<String, ChannelRequestError> {
+    match ureq::get(url).call() {
+        Ok(response) => Ok(response.into_string()?),
+        Err(ureq::Error::Status(code, response)) => Err(ChannelRequestError::HttpStatus {
+            code,
+            body: response.into_string().unwrap_or_default(),
+        }),
+        Err(err) => Err(err.into()),
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,14 +97,31 @@ pub struct CommonPrefix {
     prefix: String,
 }
 
+/// parse an S3 bucket listing, on failure, the start of the
+/// document is kept in the error so that it can be diagnosed.
+pub fn parse_channel_list(raw_xml: &str) -> Result<ListBucketResult, ChannelRequestError> {
+    quick_xml::de::from_str(raw_xml).map_err(|source| {
+        let mut end = raw_xml.len().min(XML_CONTEXT_LEN);
+        while !raw_xml.is_char_boundary(end) {
+            end -= 1;
+        }
+        ChannelRequestError::XmlParseFailedWithContext {
+            raw_xml: raw_xml[..end].to_owned(),
+            source,
+        }
+    })
+}
+
 pub fn get_channel_list() -> Result<ListBucketResult, ChannelRequestError> {
-    let channel_details = get_channel_text()?;
-    let parsed = quick_xml::de::from_str(&channel_details)?;
-    Ok(parsed)
+    parse_channel_list(&get_channel_text()?)
 }
 
 pub fn get_full_channels() -> Result<Vec<String>, ChannelRequestError> {
-    let channel_list = get_channel_list()?;
+    Ok(full_channels(get_channel_list()?))
+}
+
+/// pick the nixos-XX.YY channels out of the listing.
+fn full_channels(channel_list: ListBucketResult) -> Vec<String> {
     let mut channels = Vec::new();
     for prefix in channel_list.common_prefixes {
         let name = prefix.prefix;
@@ -67,7 +142,135 @@ pub fn get_full_channels() -> Result<Vec<String>, ChannelRequestError> {
             channels.push(year_month.to_string());
         }
     }
-    Ok(channels)
+    channels
+}
+
+/// parse a channel version such as 24.05 into (24, 5),
+/// anything else, such as unstable, is `None`.
+pub fn parse_channel_version(s: &str) -> Option<(u32, u32)> {
+    let (year, month) = s.trim().split_once('.')?;
+    Some((year.parse().ok()?, month.parse().ok()?))
+}
+
+/// order channels by version, oldest first. channels which
+/// aren't a version, such as unstable, sort after all of them.
+pub fn compare_channels(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (parse_channel_version(a), parse_channel_version(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+/// the most recent stable channel, such as 24.11
+pub fn get_latest_stable_channel() -> Result<String, ChannelRequestError> {
+    latest_stable_channel(get_full_channels()?).ok_or(ChannelRequestError::NoStableChannels)
+}
+
+fn latest_stable_channel(channels: Vec<String>) -> Option<String> {
+    channels
+        .into_iter()
+        .filter_map(|c| Some((parse_channel_version(&c)?, c)))
+        .max_by_key(|(version, _)| *version)
+        .map(|(_, c)| c)
+}
+
+/// where a channel, such as 24.05 or unstable, is published.
+pub fn get_nixos_channel_url(channel: &str) -> String {
+    format!("https://channels.nixos.org/nixos-{channel}")
+}
+
+/// whether the channel exists and can be reached.
+pub fn is_channel_accessible(channel: &str) -> Result<bool, ChannelRequestError> {
+    is_url_accessible(&get_nixos_channel_url(channel))
+}
+
+/// send a HEAD request to `url`, a 2xx response means it's
+/// accessible, any other response means it isn't, and not
+/// getting a response at all is a [`ChannelRequestError::Timeout`].
+pub fn is_url_accessible(url: &str) -> Result<bool, ChannelRequestError> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(std::time::Duration::from_secs(10))
+        .build();
+    match agent.head(url).call() {
+        Ok(response) => Ok((200..300).contains(&response.status())),
+        Err(ureq::Error::Status(_, _)) => Ok(false),
+        Err(ureq::Error::Transport(transport))
+            if matches!(
+                transport.kind(),
+                ureq::ErrorKind::Dns | ureq::ErrorKind::ConnectionFailed | ureq::ErrorKind::Io
+            ) =>
+        {
+            Err(ChannelRequestError::Timeout {
+                url: url.to_owned(),
+            })
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// a channel, along with when it was last updated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelInfo {
+    pub version: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PrefixListing {
+    #[serde(default)]
+    contents: Vec<ObjectDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ObjectDetails {
+    key: String,
+    last_modified: String,
+}
+
+/// the most recent modification time of any object in
+/// the listing of a single channel's prefix.
+pub fn parse_last_modified(raw_xml: &str) -> Result<Option<DateTime<Utc>>, ChannelRequestError> {
+    let listing: PrefixListing = quick_xml::de::from_str(raw_xml)?;
+    let mut latest = None;
+    for object in listing.contents {
+        let modified = DateTime::parse_from_rfc3339(&object.last_modified)
+            .map_err(|source| ChannelRequestError::ChronoParseError {
+                raw: format!("{}: {}", object.key, object.last_modified),
+                source,
+            })?
+            .with_timezone(&Utc);
+        latest = latest.max(Some(modified));
+    }
+    Ok(latest)
+}
+
+pub fn get_channel_last_modified(
+    version: &str,
+) -> Result<Option<DateTime<Utc>>, ChannelRequestError> {
+    let response = ureq::get("https://nix-channels.s3.amazonaws.com/")
+        .query("prefix", &format!("nixos-{version}/"))
+        .call()?;
+    parse_last_modified(&response.into_string()?)
+}
+
+/// like [`get_full_channels`], but with when each channel was
+/// last updated. this makes a request per channel.
+pub fn get_channels_with_dates() -> Result<Vec<ChannelInfo>, ChannelRequestError> {
+    get_full_channels()?
+        .into_iter()
+        .map(|version| {
+            let last_modified = get_channel_last_modified(&version)?;
+            Ok(ChannelInfo {
+                version,
+                last_modified,
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -80,6 +283,173 @@ mod tests {
         assert!(channel_text.contains("nixos-21.05"));
     }
 
+    #[test]
+    fn test_malformed_xml_has_context() {
+        let malformed = "<ListBucketResult><CommonPrefixes><Prefix>nixos-24.05/</Pre";
+        let err = parse_channel_list(malformed).unwrap_err();
+
+        let debug = format!("{err:?}");
+        assert!(debug.contains("XmlParseFailedWithContext"));
+        assert!(debug.contains("<Prefix>nixos-24.05/</Pre"));
+    }
+
+    #[test]
+    fn test_malformed_xml_context_is_truncated() {
+        let malformed = format!("<ListBucketResult>{}", "é".repeat(1000));
+        let Err(ChannelRequestError::XmlParseFailedWithContext { raw_xml, .. }) =
+            parse_channel_list(&malformed)
+        else {
+            panic!("expected a parse failure with context");
+        };
+        assert!(raw_xml.len() <= XML_CONTEXT_LEN);
+        assert!(raw_xml.starts_with("<ListBucketResult>"));
+    }
+
+    #[test]
+    fn test_parse_channel_list() -> Result<(), Box<dyn std::error::Error>> {
+        let xml = "<ListBucketResult>\
+            <CommonPrefixes><Prefix>nixos-24.05/</Prefix></CommonPrefixes>\
+            <CommonPrefixes><Prefix>nixos-unstable/</Prefix></CommonPrefixes>\
+            </ListBucketResult>";
+        let parsed = parse_channel_list(xml)?;
+        assert_eq!(parsed.common_prefixes.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_last_modified() -> Result<(), Box<dyn std::error::Error>> {
+        let xml = "<ListBucketResult>\
+            <Contents><Key>nixos-24.05/git-revision</Key>\
+            <LastModified>2024-12-31T10:00:00.000Z</LastModified></Contents>\
+            <Contents><Key>nixos-24.05/nixexprs.tar.xz</Key>\
+            <LastModified>2025-01-02T08:30:00.000Z</LastModified></Contents>\
+            </ListBucketResult>";
+        let last_modified = parse_last_modified(xml)?.unwrap();
+        assert_eq!(last_modified.to_rfc3339(), "2025-01-02T08:30:00+00:00");
+
+        let empty = "<ListBucketResult><Name>nix-channels</Name></ListBucketResult>";
+        assert_eq!(parse_last_modified(empty)?, None);
+
+        let bad = "<ListBucketResult><Contents><Key>nixos-24.05/git-revision</Key>\
+            <LastModified>yesterday</LastModified></Contents></ListBucketResult>";
+        assert!(matches!(
+            parse_last_modified(bad),
+            Err(ChannelRequestError::ChronoParseError { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_channel_version() {
+        assert_eq!(parse_channel_version("23.11"), Some((23, 11)));
+        assert_eq!(parse_channel_version("24.05"), Some((24, 5)));
+        assert_eq!(parse_channel_version("unstable"), None);
+        assert_eq!(parse_channel_version("24"), None);
+        assert_eq!(parse_channel_version("24.05.1"), None);
+    }
+
+    #[test]
+    fn test_compare_channels() {
+        let mut channels = ["unstable", "24.05", "9.03", "23.11", "24.11"];
+        channels.sort_by(|a, b| compare_channels(a, b));
+        assert_eq!(channels, ["9.03", "23.11", "24.05", "24.11", "unstable"]);
+    }
+
+    #[test]
+    fn test_latest_stable_channel() {
+        let channels = ["23.11", "24.05", "unstable", "9.03"].map(ToOwned::to_owned);
+        assert_eq!(
+            latest_stable_channel(channels.to_vec()).as_deref(),
+            Some("24.05")
+        );
+        assert_eq!(latest_stable_channel(vec!["unstable".to_owned()]), None);
+    }
+
+    /// answer a single request with `status_line`, returning
+    /// the url of the server.
+    fn mock_server(status_line: &'static str) -> String {
+        mock_response(status_line, "")
+    }
+
+    /// like [`mock_server`], but the response has a body.
+    fn mock_response(status_line: &'static str, body: &'static str) -> String {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            // read up to the blank line which ends the headers.
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 {status_line}\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        });
+        format!("http://{addr}/nixos-24.05")
+    }
+
+    #[test]
+    fn test_channel_url() {
+        assert_eq!(
+            get_nixos_channel_url("24.05"),
+            "https://channels.nixos.org/nixos-24.05"
+        );
+        assert_eq!(
+            get_nixos_channel_url("unstable"),
+            "https://channels.nixos.org/nixos-unstable"
+        );
+    }
+
+    #[test]
+    fn test_url_accessible() {
+        assert!(is_url_accessible(&mock_server("200 OK")).unwrap());
+        assert!(is_url_accessible(&mock_server("204 No Content")).unwrap());
+        assert!(!is_url_accessible(&mock_server("404 Not Found")).unwrap());
+        assert!(!is_url_accessible(&mock_server("500 Internal Server Error")).unwrap());
+    }
+
+    #[test]
+    fn test_get_text_status() {
+        let url = mock_response("200 OK", "<ListBucketResult/>");
+        assert_eq!(get_text(&url).unwrap(), "<ListBucketResult/>");
+
+        let url = mock_response("503 Service Unavailable", "slow down");
+        assert!(matches!(
+            get_text(&url),
+            Err(ChannelRequestError::HttpStatus { code: 503, body }) if body == "slow down"
+        ));
+    }
+
+    #[test]
+    fn test_url_unreachable() {
+        // nothing is listening once the listener is dropped.
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let url = format!("http://{addr}/nixos-24.05");
+        assert!(matches!(
+            is_url_accessible(&url),
+            Err(ChannelRequestError::Timeout { url: u }) if u == url
+        ));
+    }
+
+    #[test]
+    fn test_channels_with_dates() -> Result<(), Box<dyn std::error::Error>> {
+        let channels = get_channels_with_dates()?;
+        assert!(!channels.is_empty());
+        assert!(channels.iter().all(|c| c.last_modified.is_some()));
+        Ok(())
+    }
+
     #[test]
     fn test_get_channel_list() -> Result<(), Box<dyn std::error::Error>> {
         let channel_list = get_channel_list()?;
@@ -0,0 +1,51 @@
+use crate::{eval_args, version_from_json, Package};
+
+fn nix_async() -> tokio::process::Command {
+    tokio::process::Command::new("/nix/var/nix/profiles/default/bin/nix")
+}
+
+/// evaluate an attribute of the package without blocking the
+/// runtime, anything which goes wrong evaluates to null.
+async fn eval_json(package: &Package, attr: &str) -> serde_json::Value {
+    let Ok(output) = nix_async().args(eval_args(package, attr)).output().await else {
+        return serde_json::Value::Null;
+    };
+    if output.stdout.is_empty() {
+        return serde_json::Value::Null;
+    }
+    serde_json::from_slice(&output.stdout).unwrap_or(serde_json::Value::Null)
+}
+
+/// like [`crate::get_version`], but for use within tokio.
+pub async fn get_version_async(package: &Package) -> Option<String> {
+    version_from_json(eval_json(package, "version").await)
+}
+
+/// like [`crate::get_meta`], but for use within tokio.
+pub async fn get_meta_async(package: &Package) -> serde_json::Value {
+    eval_json(package, "meta").await
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_version_async_does_not_block() {
+        let package = Package {
+            attr_path: "legacyPackages.x86_64-linux.hello".to_owned(),
+            url: "github:NixOS/nixpkgs/nixos-24.05".to_owned(),
+            ..Default::default()
+        };
+
+        // whether or not nix is around to answer, the
+        // future has to finish rather than hang.
+        let version = tokio::time::timeout(Duration::from_secs(120), get_version_async(&package));
+        let meta = tokio::time::timeout(Duration::from_secs(120), get_meta_async(&package));
+        let (version, meta) = tokio::join!(version, meta);
+        assert!(version.is_ok());
+        assert!(meta.is_ok());
+    }
+}
@@ -0,0 +1,866 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{missing_paths, nix, Profile, ProfileError, Root};
+
+/// profile entries are identified by the flake they
+/// came from, and the attribute within that flake.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CachePackageLookupKey {
+    pub attr_path: String,
+    pub url: String,
+}
+
+/// an installed package, along with the information
+/// about it which has to be fetched with `nix eval`.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct CachePackage {
+    pub name: String,
+    pub version: Option<String>,
+    #[serde(with = "meta_serde")]
+    pub meta: toml_edit::Table,
+    pub attr_path: String,
+    pub url: String,
+    pub original_url: String,
+    pub store_paths: Vec<String>,
+    /// whether name, version, and meta have been
+    /// fetched yet, or are just placeholders.
+    #[serde(default)]
+    pub enriched: bool,
+    /// when name, version, and meta were fetched,
+    /// written out as an RFC 3339 string.
+    #[serde(default)]
+    pub cached_at: Option<DateTime<Utc>>,
+}
+
+impl CachePackage {
+    pub fn key(&self) -> CachePackageLookupKey {
+        CachePackageLookupKey {
+            attr_path: self.attr_path.clone(),
+            url: self.url.clone(),
+        }
+    }
+
+    /// the nixpkgs commit the package was installed from,
+    /// if it was installed from a pinned nixpkgs.
+    pub fn nixpkgs_rev(&self) -> Option<&str> {
+        match package_source(&self.url) {
+            PackageSource::PinnedNixpkgs { rev } => Some(rev),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.meta.get("description").and_then(|d| d.as_str())
+    }
+
+    /// whether this entry was fetched more than `ttl` ago,
+    /// entries which were never fetched can't go stale.
+    pub fn is_expired(&self, ttl: Duration, now: DateTime<Utc>) -> bool {
+        let Some(cached_at) = self.cached_at else {
+            return false;
+        };
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+        now.signed_duration_since(cached_at) > ttl
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct CachePackages {
+    #[serde(rename = "package", default)]
+    pub packages: Vec<CachePackage>,
+}
+
+impl CachePackages {
+    /// read the cache from a TOML file, a missing
+    /// file is the same as an empty cache.
+    pub fn load(path: &Path) -> Result<CachePackages, ProfileError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(toml_edit::de::from_str(&text)?)
+    }
+
+    /// load the cache for `profile` from `cache_dir`, dropping
+    /// any entries which were fetched more than `ttl` ago.
+    pub fn load_for_profile(
+        cache_dir: &Path,
+        profile: &Profile,
+        ttl: Duration,
+    ) -> Result<CachePackages, ProfileError> {
+        Self::load_with_ttl(&cache_dir.join(profile.cache_file_name()), ttl)
+    }
+
+    /// like [`CachePackages::load`], but entries which were
+    /// fetched more than `ttl` ago are dropped.
+    pub fn load_with_ttl(path: &Path, ttl: Duration) -> Result<CachePackages, ProfileError> {
+        let mut cache = Self::load(path)?;
+        cache.evict_expired(ttl);
+        Ok(cache)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ProfileError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_toml_string()?)?;
+        Ok(())
+    }
+
+    /// the cache as it's written to disk.
+    pub fn to_toml_string(&self) -> Result<String, ProfileError> {
+        Ok(toml_edit::ser::to_string_pretty(self)?)
+    }
+
+    /// remove every entry which was fetched more than
+    /// `ttl` ago, returning how many were removed.
+    pub fn evict_expired(&mut self, ttl: Duration) -> usize {
+        let now = Utc::now();
+        let before = self.packages.len();
+        self.packages.retain(|p| !p.is_expired(ttl, now));
+        before - self.packages.len()
+    }
+
+    /// remove every entry which has a store path that no longer
+    /// exists, returning how many were removed.
+    pub fn evict_invalid_store_paths(&mut self) -> usize {
+        let before = self.packages.len();
+        self.packages
+            .retain(|p| missing_paths(&p.store_paths).is_empty());
+        before - self.packages.len()
+    }
+
+    /// copy over the fetched details of any entry which
+    /// `cached` already knows about, so they don't need
+    /// to be fetched again.
+    pub fn reuse_cached(&mut self, cached: &CachePackages) {
+        for package in &mut self.packages {
+            if package.enriched {
+                continue;
+            }
+            if let Some(hit) = cached.get(&package.key()).filter(|p| p.enriched) {
+                *package = hit.clone();
+            }
+        }
+    }
+
+    /// build the cache from the profile manifest, fetching
+    /// everything up front. this runs `nix eval` several
+    /// times per package, so it can be quite slow.
+    pub fn from_manifest(root: &Root) -> Result<CachePackages, ProfileError> {
+        let mut cache = Self::from_manifest_minimal(root);
+        for package in &mut cache.packages {
+            package.enrich(nix_eval_json)?;
+        }
+        Ok(cache)
+    }
+
+    /// build the cache from the profile manifest without
+    /// running any subprocesses, names are just the attr path,
+    /// and version and meta are left empty until
+    /// [`CachePackages::enrich_entry`] is called.
+    pub fn from_manifest_minimal(root: &Root) -> CachePackages {
+        let mut elements = root.elements.packages.iter().collect::<Vec<_>>();
+        elements.sort_by_key(|(name, _)| name.as_str());
+
+        let packages = elements
+            .into_iter()
+            .map(|(_, package)| CachePackage {
+                name: package.attr_path.clone(),
+                version: None,
+                meta: toml_edit::Table::new(),
+                attr_path: package.attr_path.clone(),
+                url: package.url.clone(),
+                original_url: package.original_url.clone(),
+                store_paths: package.store_paths.clone(),
+                enriched: false,
+                cached_at: None,
+            })
+            .collect();
+
+        CachePackages { packages }
+    }
+
+    pub fn len(&self) -> usize {
+        self.packages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// combine two caches, such as those from different
+    /// machines. when both have the same package, the more
+    /// recently fetched one wins, or ours if that's unknown.
+    pub fn merge(self, other: CachePackages) -> CachePackages {
+        let mut merged = self;
+        for theirs in other.packages {
+            let key = theirs.key();
+            match merged.packages.iter_mut().find(|p| p.key() == key) {
+                Some(ours) => {
+                    if let (Some(ours_at), Some(theirs_at)) = (ours.cached_at, theirs.cached_at) {
+                        if theirs_at > ours_at {
+                            *ours = theirs;
+                        }
+                    }
+                }
+                None => merged.packages.push(theirs),
+            }
+        }
+        merged
+    }
+
+    pub fn get(&self, key: &CachePackageLookupKey) -> Option<&CachePackage> {
+        self.packages
+            .iter()
+            .find(|p| p.attr_path == key.attr_path && p.url == key.url)
+    }
+
+    /// the packages with a name similar to `name`, most similar first.
+    /// `threshold` is between 0.0 and 1.0, and only packages which
+    /// score above it are returned, so 1.0 never matches anything.
+    pub fn lookup_by_name_fuzzy(&self, name: &str, threshold: f64) -> Vec<&CachePackage> {
+        let mut scored = self
+            .packages
+            .iter()
+            .map(|p| (strsim::normalized_levenshtein(name, &p.name), p))
+            .filter(|(score, _)| *score > threshold)
+            .collect::<Vec<_>>();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        scored.into_iter().map(|(_, p)| p).collect()
+    }
+
+    fn get_mut(&mut self, key: &CachePackageLookupKey) -> Result<&mut CachePackage, ProfileError> {
+        self.packages
+            .iter_mut()
+            .find(|p| p.attr_path == key.attr_path && p.url == key.url)
+            .ok_or_else(|| ProfileError::UnknownPackage {
+                attr_path: key.attr_path.clone(),
+            })
+    }
+
+    /// fetch the name, version, and meta of a single entry,
+    /// entries which have already been enriched are left alone.
+    pub fn enrich_entry(&mut self, key: &CachePackageLookupKey) -> Result<(), ProfileError> {
+        self.enrich_entry_with(key, nix_eval_json)
+    }
+
+    /// like [`CachePackages::enrich_entry`], but with the
+    /// evaluation of `<url>#<attr_path>.<attr>` supplied by the caller.
+    pub fn enrich_entry_with(
+        &mut self,
+        key: &CachePackageLookupKey,
+        eval: impl FnMut(&CachePackageLookupKey, &str) -> Result<serde_json::Value, ProfileError>,
+    ) -> Result<(), ProfileError> {
+        self.get_mut(key)?.enrich(eval)
+    }
+}
+
+impl CachePackage {
+    fn enrich(
+        &mut self,
+        mut eval: impl FnMut(&CachePackageLookupKey, &str) -> Result<serde_json::Value, ProfileError>,
+    ) -> Result<(), ProfileError> {
+        if self.enriched {
+            return Ok(());
+        }
+        let key = self.key();
+
+        if let serde_json::Value::String(pname) = eval(&key, "pname")? {
+            self.name = pname;
+        }
+        self.version = match eval(&key, "version")? {
+            serde_json::Value::String(s) => Some(s),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+        self.meta = match eval(&key, "meta")? {
+            serde_json::Value::Object(meta) => meta_serde::json_to_toml_table(&meta),
+            _ => toml_edit::Table::new(),
+        };
+        self.enriched = true;
+        self.cached_at = Some(Utc::now());
+        Ok(())
+    }
+}
+
+/// where a package in the profile came from, as
+/// far as a `shell.nix` is concerned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PackageSource<'a> {
+    /// whatever `<nixpkgs>` is.
+    Nixpkgs,
+    /// nixpkgs at a particular commit.
+    PinnedNixpkgs { rev: &'a str },
+    /// some other flake.
+    Flake { url: Cow<'a, str> },
+}
+
+/// the query parameters which nix adds when it locks a flake, the
+/// rest, such as ?dir=, are part of what the flake is.
+const LOCK_PARAMS: &[&str] = &["narHash", "lastModified"];
+
+/// `url` without the parameters in [`LOCK_PARAMS`].
+fn without_lock_params(url: &str) -> Cow<'_, str> {
+    let Some((base, query)) = url.split_once('?') else {
+        return Cow::Borrowed(url);
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|param| {
+            let key = param.split_once('=').map_or(*param, |(key, _)| key);
+            !LOCK_PARAMS.contains(&key)
+        })
+        .collect();
+    if kept.is_empty() {
+        Cow::Borrowed(base)
+    } else {
+        Cow::Owned(format!("{base}?{}", kept.join("&")))
+    }
+}
+
+fn package_source(url: &str) -> PackageSource<'_> {
+    // locked urls carry things like ?narHash=... on the end.
+    let base = url.split_once('?').map_or(url, |(base, _)| base);
+    let rev = ["github:NixOS/nixpkgs/", "github:nixos/nixpkgs/"]
+        .iter()
+        .find_map(|prefix| base.strip_prefix(prefix));
+
+    match rev {
+        Some(rev) if rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit()) => {
+            PackageSource::PinnedNixpkgs { rev }
+        }
+        Some(_) => PackageSource::Nixpkgs,
+        None if base == "flake:nixpkgs" || base == "github:NixOS/nixpkgs" => PackageSource::Nixpkgs,
+        None => PackageSource::Flake {
+            url: without_lock_params(url),
+        },
+    }
+}
+
+/// `text` as a nix string literal.
+fn nix_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    format!("\"{escaped}\"")
+}
+
+/// profile attr paths are qualified with the system, such as
+/// legacyPackages.x86_64-linux.ripgrep, but within nixpkgs,
+/// the package is just ripgrep.
+fn package_attr_name(attr_path: &str) -> &str {
+    ["legacyPackages.", "packages."]
+        .iter()
+        .find_map(|prefix| attr_path.strip_prefix(prefix))
+        .and_then(|rest| rest.split_once('.'))
+        .map_or(attr_path, |(_system, name)| name)
+}
+
+impl CachePackages {
+    /// produce a `shell.nix` which provides every package in
+    /// the cache. packages installed from a particular nixpkgs
+    /// commit are pinned to that commit with `fetchTarball`.
+    pub fn export_nix_expression(&self) -> String {
+        let mut pins = Vec::new();
+        let mut inputs = Vec::new();
+        for package in &self.packages {
+            let name = package_attr_name(&package.attr_path);
+            match package_source(&package.url) {
+                PackageSource::Nixpkgs => inputs.push(format!("pkgs.{name}")),
+                PackageSource::PinnedNixpkgs { rev } => {
+                    let binding = format!("nixpkgs-{}", &rev[..7]);
+                    if !pins.iter().any(|(b, _)| *b == binding) {
+                        pins.push((binding.clone(), rev));
+                    }
+                    inputs.push(format!("{binding}.{name}"));
+                }
+                PackageSource::Flake { url } => inputs.push(format!(
+                    "(builtins.getFlake {}).{}",
+                    nix_string(&url),
+                    package.attr_path
+                )),
+            }
+        }
+
+        let mut out = String::new();
+        writeln!(out, "{{ pkgs ? import <nixpkgs> {{}} }}:").unwrap();
+        if !pins.is_empty() {
+            writeln!(out, "let").unwrap();
+            for (binding, rev) in &pins {
+                writeln!(
+                    out,
+                    "  {binding} = import (fetchTarball \"https://github.com/NixOS/nixpkgs/archive/{rev}.tar.gz\") {{}};"
+                )
+                .unwrap();
+            }
+            writeln!(out, "in").unwrap();
+        }
+        writeln!(out, "pkgs.mkShell {{").unwrap();
+        writeln!(out, "  buildInputs = [").unwrap();
+        for input in inputs {
+            writeln!(out, "    {input}").unwrap();
+        }
+        writeln!(out, "  ];").unwrap();
+        writeln!(out, "}}").unwrap();
+        out
+    }
+}
+
+/// evaluate an attribute of a package as JSON, missing
+/// attributes evaluate to null rather than failing.
+fn nix_eval_json(
+    key: &CachePackageLookupKey,
+    attr: &str,
+) -> Result<serde_json::Value, ProfileError> {
+    let output = nix()
+        .arg("eval")
+        .arg("--json")
+        .arg(format!("{}#{}", key.url, key.attr_path))
+        .arg("--apply")
+        .arg(format!("p: p.{attr} or null"))
+        .output()?;
+
+    if !output.status.success() {
+        return Err(ProfileError::NixEval {
+            attr_path: format!("{}.{attr}", key.attr_path),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// package meta is stored as a toml table, so that it can live
+/// in the on-disk cache, but it's (de)serialized by way of json,
+/// so that it can also be written out as json.
+mod meta_serde {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use toml_edit::{Item, Table, Value};
+
+    pub fn serialize<S: Serializer>(table: &Table, serializer: S) -> Result<S::Ok, S::Error> {
+        table_to_json(table).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Table, D::Error> {
+        match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::Object(map) => Ok(json_to_toml_table(&map)),
+            _ => Ok(Table::new()),
+        }
+    }
+
+    /// toml has no null, so nulls are dropped.
+    pub fn json_to_toml_table(map: &serde_json::Map<String, serde_json::Value>) -> Table {
+        map.iter()
+            .filter_map(|(k, v)| Some((k.as_str(), Item::Value(json_to_toml(v)?))))
+            .collect()
+    }
+
+    fn json_to_toml(value: &serde_json::Value) -> Option<Value> {
+        Some(match value {
+            serde_json::Value::Null => return None,
+            serde_json::Value::Bool(b) => Value::from(*b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::from(i),
+                None => Value::from(n.as_f64()?),
+            },
+            serde_json::Value::String(s) => Value::from(s.as_str()),
+            serde_json::Value::Array(a) => a.iter().filter_map(json_to_toml).collect(),
+            serde_json::Value::Object(o) => o
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str(), json_to_toml(v)?)))
+                .collect(),
+        })
+    }
+
+    fn table_to_json(table: &Table) -> serde_json::Value {
+        table
+            .iter()
+            .filter_map(|(k, item)| Some((k.to_owned(), item_to_json(item)?)))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    fn item_to_json(item: &Item) -> Option<serde_json::Value> {
+        match item {
+            Item::None => None,
+            Item::Value(value) => Some(toml_to_json(value)),
+            Item::Table(table) => Some(table_to_json(table)),
+            Item::ArrayOfTables(tables) => Some(tables.iter().map(table_to_json).collect()),
+        }
+    }
+
+    fn toml_to_json(value: &Value) -> serde_json::Value {
+        match value {
+            Value::String(s) => s.value().as_str().into(),
+            Value::Integer(i) => (*i.value()).into(),
+            Value::Float(f) => (*f.value()).into(),
+            Value::Boolean(b) => (*b.value()).into(),
+            Value::Datetime(d) => d.value().to_string().into(),
+            Value::Array(a) => a.iter().map(toml_to_json).collect(),
+            Value::InlineTable(t) => t
+                .iter()
+                .map(|(k, v)| (k.to_owned(), toml_to_json(v)))
+                .collect::<serde_json::Map<_, _>>()
+                .into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Package;
+
+    fn root() -> Root {
+        let mut root = Root::default();
+        for (name, attr_path) in [
+            ("ripgrep", "legacyPackages.x86_64-linux.ripgrep"),
+            ("gleam", "legacyPackages.x86_64-linux.gleam"),
+        ] {
+            root.elements.packages.insert(
+                name.to_owned(),
+                Package {
+                    active: true,
+                    attr_path: attr_path.to_owned(),
+                    original_url: "flake:nixpkgs".to_owned(),
+                    url: "github:NixOS/nixpkgs/abc123".to_owned(),
+                    store_paths: vec![format!("/nix/store/xyz-{name}")],
+                    ..Default::default()
+                },
+            );
+        }
+        root
+    }
+
+    fn fake_eval(
+        key: &CachePackageLookupKey,
+        attr: &str,
+    ) -> Result<serde_json::Value, ProfileError> {
+        let name = key.attr_path.rsplit('.').next().unwrap();
+        Ok(match attr {
+            "pname" => serde_json::json!(name),
+            "version" => serde_json::json!("1.0.0"),
+            "meta" => serde_json::json!({
+                "description": format!("the {name} package"),
+                "broken": false,
+                "maintainers": [{ "name": "someone", "email": null }],
+                "homepage": null,
+            }),
+            _ => serde_json::Value::Null,
+        })
+    }
+
+    #[test]
+    fn test_from_manifest_minimal() {
+        let cache = CachePackages::from_manifest_minimal(&root());
+        assert_eq!(cache.packages.len(), 2);
+
+        // sorted by the name of the profile element.
+        let gleam = &cache.packages[0];
+        assert_eq!(gleam.name, "legacyPackages.x86_64-linux.gleam");
+        assert_eq!(gleam.name, gleam.attr_path);
+        assert_eq!(gleam.version, None);
+        assert!(gleam.meta.is_empty());
+        assert!(!gleam.enriched);
+        assert_eq!(gleam.store_paths, ["/nix/store/xyz-gleam"]);
+    }
+
+    #[test]
+    fn test_enrich_entry() {
+        let mut cache = CachePackages::from_manifest_minimal(&root());
+        let key = cache.packages[1].key();
+
+        cache.enrich_entry_with(&key, fake_eval).unwrap();
+
+        let ripgrep = cache.get(&key).unwrap();
+        assert!(ripgrep.enriched);
+        assert_eq!(ripgrep.name, "ripgrep");
+        assert_eq!(ripgrep.version.as_deref(), Some("1.0.0"));
+        assert_eq!(ripgrep.description(), Some("the ripgrep package"));
+        assert!(!ripgrep.meta.contains_key("homepage"));
+
+        // only the requested entry is touched.
+        assert!(!cache.packages[0].enriched);
+
+        // enriching twice doesn't evaluate anything again.
+        cache
+            .enrich_entry_with(&key, |_, _| panic!("already enriched"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_enrich_unknown_entry() {
+        let mut cache = CachePackages::from_manifest_minimal(&root());
+        let key = CachePackageLookupKey {
+            attr_path: "legacyPackages.x86_64-linux.missing".to_owned(),
+            url: "github:NixOS/nixpkgs/abc123".to_owned(),
+        };
+        assert!(matches!(
+            cache.enrich_entry_with(&key, fake_eval),
+            Err(ProfileError::UnknownPackage { .. })
+        ));
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let mut cache = CachePackages::from_manifest_minimal(&root());
+        let key = cache.packages[0].key();
+        cache.enrich_entry_with(&key, fake_eval).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gnix").join("profile-installed.toml");
+        cache.save(&path).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("[[package]]"));
+        let cached_at = cache.packages[0].cached_at.unwrap();
+        assert!(written.contains(&format!(
+            "cached_at = \"{}\"",
+            cached_at.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+        )));
+
+        let back = CachePackages::load(&path).unwrap();
+        assert_eq!(back.packages.len(), 2);
+        let gleam = back.get(&key).unwrap();
+        assert_eq!(gleam.description(), Some("the gleam package"));
+        assert_eq!(gleam.cached_at, Some(cached_at));
+        assert_eq!(back.packages[1].cached_at, None);
+
+        let missing = CachePackages::load(&dir.path().join("missing.toml")).unwrap();
+        assert!(missing.packages.is_empty());
+    }
+
+    #[test]
+    fn test_evict_expired() {
+        let mut cache = CachePackages::from_manifest_minimal(&root());
+        for package in &mut cache.packages {
+            package.enrich(fake_eval).unwrap();
+        }
+        cache.packages[0].cached_at = Some(Utc::now() - chrono::Duration::hours(25));
+        cache.packages.push(CachePackage {
+            attr_path: "never.fetched".to_owned(),
+            ..Default::default()
+        });
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(cache.evict_expired(day), 1);
+        assert_eq!(cache.packages.len(), 2);
+        assert_eq!(cache.packages[0].name, "ripgrep");
+        assert_eq!(cache.evict_expired(day), 0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile-installed.toml");
+        cache.packages[0].cached_at = Some(Utc::now() - chrono::Duration::hours(2));
+        cache.save(&path).unwrap();
+        let loaded = CachePackages::load_with_ttl(&path, Duration::from_secs(60 * 60)).unwrap();
+        assert_eq!(loaded.packages.len(), 1);
+        assert_eq!(loaded.packages[0].attr_path, "never.fetched");
+    }
+
+    #[test]
+    fn test_reuse_cached() {
+        let mut cached = CachePackages::from_manifest_minimal(&root());
+        let key = cached.packages[1].key();
+        cached.enrich_entry_with(&key, fake_eval).unwrap();
+
+        let mut fresh = CachePackages::from_manifest_minimal(&root());
+        fresh.reuse_cached(&cached);
+        assert!(fresh.get(&key).unwrap().enriched);
+        assert_eq!(fresh.get(&key).unwrap().name, "ripgrep");
+        assert!(!fresh.packages[0].enriched);
+    }
+
+    #[test]
+    fn test_export_nix_expression() {
+        let cache = CachePackages::from_manifest_minimal(&root());
+        let out = cache.export_nix_expression();
+        assert_eq!(
+            out,
+            r#"{ pkgs ? import <nixpkgs> {} }:
+pkgs.mkShell {
+  buildInputs = [
+    pkgs.gleam
+    pkgs.ripgrep
+  ];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_export_pinned_nix_expression() {
+        let mut cache = CachePackages::from_manifest_minimal(&root());
+        let rev = "0123456789abcdef0123456789abcdef01234567";
+        cache.packages[0].url = format!("github:NixOS/nixpkgs/{rev}?narHash=sha256-abc");
+        cache.packages.push(CachePackage {
+            attr_path: "packages.x86_64-linux.default".to_owned(),
+            url: "github:someone/tool/fedcba".to_owned(),
+            ..Default::default()
+        });
+        cache.packages.push(CachePackage {
+            attr_path: "packages.x86_64-linux.cli".to_owned(),
+            url: "github:someone/mono/fedcba?dir=cli&narHash=sha256-abc&lastModified=1".to_owned(),
+            ..Default::default()
+        });
+
+        let out = cache.export_nix_expression();
+        assert!(out.contains(&format!(
+            "  nixpkgs-0123456 = import (fetchTarball \"https://github.com/NixOS/nixpkgs/archive/{rev}.tar.gz\") {{}};"
+        )));
+        assert!(out.contains("    nixpkgs-0123456.gleam\n"));
+        assert!(out.contains("    pkgs.ripgrep\n"));
+        assert!(out.contains(
+            "    (builtins.getFlake \"github:someone/tool/fedcba\").packages.x86_64-linux.default\n"
+        ));
+        assert!(out.contains(
+            "    (builtins.getFlake \"github:someone/mono/fedcba?dir=cli\").packages.x86_64-linux.cli\n"
+        ));
+    }
+
+    #[test]
+    fn test_without_lock_params() {
+        assert_eq!(without_lock_params("github:a/b"), "github:a/b");
+        assert_eq!(
+            without_lock_params("github:a/b?narHash=sha256-abc&lastModified=1"),
+            "github:a/b"
+        );
+        assert_eq!(
+            without_lock_params("git+https://x.org/r?ref=main&narHash=sha256-abc&dir=sub"),
+            "git+https://x.org/r?ref=main&dir=sub"
+        );
+        assert_eq!(nix_string(r#"a"${b}\"#), r#""a\"\${b}\\""#);
+    }
+
+    #[test]
+    fn test_nixpkgs_rev() {
+        let rev = "0123456789abcdef0123456789abcdef01234567";
+        let package = |url: &str| CachePackage {
+            url: url.to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            package(&format!("github:NixOS/nixpkgs/{rev}?narHash=sha256-abc")).nixpkgs_rev(),
+            Some(rev)
+        );
+        assert_eq!(
+            package("github:NixOS/nixpkgs/nixos-24.05").nixpkgs_rev(),
+            None
+        );
+        assert_eq!(package("github:someone/tool/fedcba").nixpkgs_rev(), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut ours = CachePackages::from_manifest_minimal(&root());
+        let mut theirs = CachePackages::from_manifest_minimal(&root());
+        theirs.packages.push(CachePackage {
+            attr_path: "legacyPackages.x86_64-linux.just".to_owned(),
+            url: "github:NixOS/nixpkgs/abc123".to_owned(),
+            ..Default::default()
+        });
+
+        // gleam: theirs is newer, ripgrep: ours is newer
+        let now = Utc::now();
+        ours.packages[0].cached_at = Some(now - chrono::Duration::hours(2));
+        theirs.packages[0].cached_at = Some(now);
+        theirs.packages[0].name = "theirs".to_owned();
+        ours.packages[1].cached_at = Some(now);
+        theirs.packages[1].cached_at = Some(now - chrono::Duration::hours(2));
+        theirs.packages[1].name = "theirs".to_owned();
+
+        let merged = ours.clone().merge(theirs.clone());
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged.packages[0].name, "theirs");
+        assert_eq!(
+            merged.packages[1].name,
+            "legacyPackages.x86_64-linux.ripgrep"
+        );
+        assert_eq!(
+            merged.packages[2].attr_path,
+            "legacyPackages.x86_64-linux.just"
+        );
+
+        // without timestamps, ours is kept.
+        ours.packages[0].cached_at = None;
+        let merged = ours.merge(theirs);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged.packages[0].name, "legacyPackages.x86_64-linux.gleam");
+    }
+
+    #[test]
+    fn test_evict_invalid_store_paths() {
+        let store = tempfile::tempdir().unwrap();
+        let mut cache = CachePackages::from_manifest_minimal(&root());
+        for (i, package) in cache.packages.iter_mut().enumerate() {
+            let path = store.path().join(format!("{i}-{}", package.name));
+            package.store_paths = vec![path.to_string_lossy().into_owned()];
+        }
+        std::fs::create_dir(&cache.packages[1].store_paths[0]).unwrap();
+
+        assert_eq!(cache.evict_invalid_store_paths(), 1);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.packages[0].attr_path.ends_with("ripgrep"));
+        assert_eq!(cache.evict_invalid_store_paths(), 0);
+    }
+
+    #[test]
+    fn test_len() {
+        assert!(CachePackages::default().is_empty());
+        let cache = CachePackages::from_manifest_minimal(&root());
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.is_empty());
+        assert_eq!(cache.clone().merge(cache).len(), 2);
+    }
+
+    #[test]
+    fn test_lookup_by_name_fuzzy() {
+        let cache = CachePackages {
+            packages: ["cargo-edit", "cargo-watch", "ripgrep", "gleam"]
+                .into_iter()
+                .map(|name| CachePackage {
+                    name: name.to_owned(),
+                    ..Default::default()
+                })
+                .collect(),
+        };
+        let names = |name: &str, threshold: f64| {
+            cache
+                .lookup_by_name_fuzzy(name, threshold)
+                .into_iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("cargo_edit", 0.8), ["cargo-edit"]);
+        assert_eq!(names("ripgrpe", 0.7), ["ripgrep"]);
+        assert_eq!(names("glean", 0.7), ["gleam"]);
+        assert_eq!(names("cargo-wacth", 0.5), ["cargo-watch", "cargo-edit"]);
+        assert!(names("firefox", 0.7).is_empty());
+        assert!(names("gleam", 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_meta_json_round_trip() {
+        let mut cache = CachePackages::from_manifest_minimal(&root());
+        let key = cache.packages[0].key();
+        cache.enrich_entry_with(&key, fake_eval).unwrap();
+
+        let json = serde_json::to_string(&cache).unwrap();
+        let back: CachePackages = serde_json::from_str(&json).unwrap();
+        let gleam = back.get(&key).unwrap();
+        assert_eq!(gleam.description(), Some("the gleam package"));
+        assert_eq!(
+            gleam.meta.get("broken").and_then(|b| b.as_bool()),
+            Some(false)
+        );
+        assert!(gleam.enriched);
+    }
+}
@@ -1,8 +1,20 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "detect-system")]
+use std::sync::OnceLock;
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+#[cfg(feature = "async")]
+mod async_eval;
+mod cache_packages;
+
+#[cfg(feature = "async")]
+pub use async_eval::{get_meta_async, get_version_async};
+
+pub use cache_packages::{CachePackage, CachePackageLookupKey, CachePackages};
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Root {
     pub elements: Elements,
@@ -27,6 +39,152 @@ pub struct Package {
     pub url: String,
 }
 
+/// the store paths which are no longer on disk, which
+/// happens when the store has been garbage collected.
+fn missing_paths(store_paths: &[String]) -> Vec<String> {
+    store_paths
+        .iter()
+        .filter(|path| !Path::new(path).exists())
+        .cloned()
+        .collect()
+}
+
+impl Package {
+    /// whether every one of the package's store paths exists.
+    pub fn check_store_valid(&self) -> bool {
+        self.store_paths.iter().all(|path| Path::new(path).exists())
+    }
+
+    pub fn missing_store_paths(&self) -> Vec<String> {
+        missing_paths(&self.store_paths)
+    }
+
+    /// the size, in bytes, of the package's first store
+    /// path along with everything it depends on.
+    pub fn closure_size_bytes(&self) -> Result<u64, ProfileError> {
+        let Some(store_path) = self.store_paths.first() else {
+            return Err(ProfileError::StoreError {
+                message: format!("`{}` has no store paths", self.attr_path),
+            });
+        };
+        let output = nix()
+            .arg("path-info")
+            .arg("--json")
+            .arg("--closure-size")
+            .arg(store_path)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(ProfileError::StoreError {
+                message: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        parse_closure_size(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// each of the package's store paths, along with the name of
+    /// the output it is, such as `out` or `man`.
+    pub fn output_paths(&self) -> Result<Vec<(String, String)>, ProfileError> {
+        if self.store_paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let output = nix()
+            .arg("path-info")
+            .arg("--json")
+            .args(&self.store_paths)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(ProfileError::StoreError {
+                message: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        parse_output_paths(&String::from_utf8_lossy(&output.stdout), &self.store_paths)
+    }
+
+    /// whether the package has an output called `name`, which
+    /// means asking nix, unless the profile lists its outputs.
+    pub fn has_output(&self, name: &str) -> bool {
+        match &self.outputs {
+            Some(outputs) => outputs.iter().any(|output| output == name),
+            None => self
+                .output_paths()
+                .is_ok_and(|paths| paths.iter().any(|(output, _)| output == name)),
+        }
+    }
+}
+
+/// a store path without the /nix/store/<hash>- in front of it.
+fn store_path_name(store_path: &str) -> &str {
+    let file_name = store_path.rsplit('/').next().unwrap_or(store_path);
+    file_name
+        .split_once('-')
+        .map_or(file_name, |(_, name)| name)
+}
+
+/// the output a store path is, outputs other than `out` are
+/// named after the derivation that built them, plus a suffix,
+/// such as ripgrep-14.1.0.drv building ripgrep-14.1.0-man.
+fn output_name(store_path: &str, deriver: Option<&str>) -> String {
+    let name = store_path_name(store_path);
+    let Some(drv_name) = deriver.and_then(|drv| store_path_name(drv).strip_suffix(".drv")) else {
+        return "out".to_owned();
+    };
+    match name.strip_prefix(drv_name) {
+        Some(suffix) => suffix.strip_prefix('-').unwrap_or("out").to_owned(),
+        None => "out".to_owned(),
+    }
+}
+
+/// pair each of `store_paths` with the name of its output, using the
+/// derivers in `nix path-info --json`, which (like the closure size)
+/// comes as either a list of objects, or an object keyed by path.
+pub fn parse_output_paths(
+    json: &str,
+    store_paths: &[String],
+) -> Result<Vec<(String, String)>, ProfileError> {
+    let info: serde_json::Value = serde_json::from_str(json)?;
+    let deriver = |store_path: &str| {
+        let entry = match &info {
+            serde_json::Value::Array(entries) => entries
+                .iter()
+                .find(|entry| entry.get("path").and_then(|p| p.as_str()) == Some(store_path)),
+            serde_json::Value::Object(entries) => entries.get(store_path),
+            _ => None,
+        };
+        entry
+            .and_then(|entry| entry.get("deriver"))
+            .and_then(|deriver| deriver.as_str())
+    };
+    Ok(store_paths
+        .iter()
+        .map(|store_path| {
+            (
+                output_name(store_path, deriver(store_path)),
+                store_path.clone(),
+            )
+        })
+        .collect())
+}
+
+/// read the closure size out of `nix path-info --json --closure-size`.
+/// older versions of nix print a list of objects, each with a `path`,
+/// newer ones print an object keyed by path, we accept either.
+pub fn parse_closure_size(json: &str) -> Result<u64, ProfileError> {
+    let info: serde_json::Value = serde_json::from_str(json)?;
+    let entry = match &info {
+        serde_json::Value::Array(entries) => entries.first(),
+        serde_json::Value::Object(entries) => entries.values().next(),
+        _ => None,
+    };
+    entry
+        .and_then(|entry| entry.get("closureSize"))
+        .and_then(|size| size.as_u64())
+        .ok_or_else(|| ProfileError::StoreError {
+            message: format!("no closure size in path info: {json}"),
+        })
+}
+
 #[derive(Debug, Error)]
 pub enum ProfileError {
     #[error("IO error: {source}")]
@@ -39,26 +197,203 @@ pub enum ProfileError {
         #[from]
         source: serde_json::Error,
     },
+    #[error("failed to read the package cache: {source}")]
+    TomlDe {
+        #[from]
+        source: toml_edit::de::Error,
+    },
+    #[error("failed to write the package cache: {source}")]
+    TomlSer {
+        #[from]
+        source: toml_edit::ser::Error,
+    },
+    #[error("failed to query the nix store: {message}")]
+    StoreError { message: String },
+    #[error("{} has no generation {generation}", profile.display())]
+    UnknownGeneration { profile: PathBuf, generation: u64 },
+    #[error("failed to evaluate `{attr_path}`: {stderr}")]
+    NixEval { attr_path: String, stderr: String },
+    #[error("`{attr_path}` is not in the profile")]
+    UnknownPackage { attr_path: String },
 }
 pub fn nix() -> std::process::Command {
     std::process::Command::new("/nix/var/nix/profiles/default/bin/nix")
 }
 
-pub fn manifest() -> Result<String, ProfileError> {
-    let mut nix = nix();
-    let cmd = nix.arg("profile").arg("list").arg("--json");
+/// the system nix is building for, such as x86_64-linux
+/// or aarch64-darwin.
+pub fn try_nix_system() -> Result<String, ProfileError> {
+    let output = nix()
+        .arg("eval")
+        .arg("--impure")
+        .arg("--raw")
+        .arg("--expr")
+        .arg("builtins.currentSystem")
+        .output()?;
 
-    let output = cmd.output()?;
+    if !output.status.success() {
+        return Err(ProfileError::NixEval {
+            attr_path: "builtins.currentSystem".to_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
 
-    let stdout = output.stdout;
+/// like [`try_nix_system`], but nix is only asked once, if it can't
+/// be asked at all, this is empty, which won't match any platform.
+#[cfg(feature = "detect-system")]
+pub fn nix_system() -> &'static str {
+    static SYSTEM: OnceLock<String> = OnceLock::new();
+    SYSTEM.get_or_init(|| try_nix_system().unwrap_or_default())
+}
 
-    Ok(String::from_utf8_lossy(&stdout).into_owned())
+/// a nix profile, by default, the user's own profile,
+/// but any other profile can be given by its path.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Profile {
+    path: Option<PathBuf>,
+}
+
+impl Profile {
+    pub fn at(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: Some(path.as_ref().to_owned()),
+        }
+    }
+
+    /// the path of the profile, or none for the default one.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// `nix profile <subcommand>`, pointed at this profile.
+    pub fn nix_profile(&self, subcommand: &str) -> std::process::Command {
+        let mut nix = nix();
+        nix.arg("profile").arg(subcommand);
+        if let Some(path) = &self.path {
+            nix.arg("--profile").arg(path);
+        }
+        nix
+    }
+
+    pub fn manifest(&self) -> Result<String, ProfileError> {
+        let output = self.nix_profile("list").arg("--json").output()?;
+
+        let stdout = output.stdout;
+
+        Ok(String::from_utf8_lossy(&stdout).into_owned())
+    }
+
+    pub fn manifest_parsed(&self) -> Result<Root, ProfileError> {
+        let output = self.manifest()?;
+        let root: Root = serde_json::from_str(&output)?;
+        Ok(root)
+    }
+
+    /// the name of the file this profile's package cache is kept
+    /// in, every profile gets its own, so they don't mix.
+    pub fn cache_file_name(&self) -> String {
+        let Some(path) = &self.path else {
+            return "profile-installed.toml".to_owned();
+        };
+        let slug = path
+            .to_string_lossy()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>();
+        format!("profile-installed-{}.toml", slug.trim_matches('-'))
+    }
+}
+
+pub fn manifest() -> Result<String, ProfileError> {
+    Profile::default().manifest()
 }
 
 pub fn manifest_parsed() -> Result<Root, ProfileError> {
-    let output = manifest()?;
-    let root: Root = serde_json::from_str(&output)?;
-    Ok(root)
+    Profile::default().manifest_parsed()
+}
+
+pub fn manifest_with_profile(path: impl AsRef<Path>) -> Result<String, ProfileError> {
+    Profile::at(path).manifest()
+}
+
+pub fn manifest_parsed_with_profile(path: impl AsRef<Path>) -> Result<Root, ProfileError> {
+    Profile::at(path).manifest_parsed()
+}
+
+/// the profile which `~/.nix-profile` points to, such as
+/// ~/.local/state/nix/profiles/profile
+pub fn default_profile_path() -> Result<PathBuf, ProfileError> {
+    let home = PathBuf::from(std::env::var_os("HOME").unwrap_or_default());
+    Ok(std::fs::read_link(home.join(".nix-profile"))?)
+}
+
+/// every generation of a profile is kept as a symlink
+/// next to it, named `<profile>-<generation>-link`.
+fn generation_link(profile_path: &Path, generation: u64) -> PathBuf {
+    let mut name = profile_path.file_name().unwrap_or_default().to_owned();
+    name.push(format!("-{generation}-link"));
+    profile_path.with_file_name(name)
+}
+
+fn parse_generation(profile_name: &str, link_name: &str) -> Option<u64> {
+    link_name
+        .strip_prefix(profile_name)?
+        .strip_prefix('-')?
+        .strip_suffix("-link")?
+        .parse()
+        .ok()
+}
+
+/// the generations of a profile which still exist, oldest first.
+pub fn get_profile_generations(profile_path: &Path) -> Result<Vec<u64>, ProfileError> {
+    let profile_name = profile_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let dir = match profile_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut generations = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        if let Some(generation) = parse_generation(&profile_name, &name.to_string_lossy()) {
+            generations.push(generation);
+        }
+    }
+    generations.sort_unstable();
+    Ok(generations)
+}
+
+/// the generation which the profile currently points to.
+pub fn current_generation(profile_path: &Path) -> Result<u64, ProfileError> {
+    let profile_name = profile_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let target = std::fs::read_link(profile_path)?;
+    let target = target.file_name().unwrap_or_default().to_string_lossy();
+    parse_generation(&profile_name, &target).ok_or_else(|| ProfileError::StoreError {
+        message: format!(
+            "{} doesn't point to a generation of the profile",
+            profile_path.display()
+        ),
+    })
+}
+
+/// the manifest of the profile as it was at `generation`.
+pub fn manifest_at_generation(profile_path: &Path, generation: u64) -> Result<Root, ProfileError> {
+    let link = generation_link(profile_path, generation);
+    if std::fs::symlink_metadata(&link).is_err() {
+        return Err(ProfileError::UnknownGeneration {
+            profile: profile_path.to_owned(),
+            generation,
+        });
+    }
+    Profile::at(link).manifest_parsed()
 }
 
 pub fn get_name(package: &Package) -> serde_json::Value {
@@ -78,14 +413,32 @@ pub fn get_name(package: &Package) -> serde_json::Value {
     meta
 }
 
+/// the arguments to `nix eval` which print an
+/// attribute of the package as JSON.
+fn eval_args(package: &Package, attr: &str) -> [String; 5] {
+    [
+        "eval".to_owned(),
+        "--raw".to_owned(),
+        format!("{}#{}.{attr}", package.url, package.attr_path),
+        "--apply".to_owned(),
+        "builtins.toJSON".to_owned(),
+    ]
+}
+
+fn version_from_json(version: serde_json::Value) -> Option<String> {
+    match version {
+        serde_json::Value::Null => None,
+        serde_json::Value::Bool(_) => None,
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::String(s) => Some(s),
+        serde_json::Value::Array(_) => None,
+        serde_json::Value::Object(_) => None,
+    }
+}
+
 pub fn get_version(package: &Package) -> Option<String> {
     let mut nix = nix();
-    let cmd = nix
-        .arg("eval")
-        .arg("--raw")
-        .arg(format!("{}#{}.version", package.url, package.attr_path))
-        .arg("--apply")
-        .arg("builtins.toJSON");
+    let cmd = nix.args(eval_args(package, "version"));
 
     let output = cmd.output().unwrap();
 
@@ -96,24 +449,12 @@ pub fn get_version(package: &Package) -> Option<String> {
     }
 
     let meta: serde_json::Value = serde_json::from_slice(&stdout).unwrap();
-    match meta {
-        serde_json::Value::Null => None,
-        serde_json::Value::Bool(_) => None,
-        serde_json::Value::Number(number) => Some(number.to_string()),
-        serde_json::Value::String(s) => Some(s),
-        serde_json::Value::Array(_) => None,
-        serde_json::Value::Object(_) => None,
-    }
+    version_from_json(meta)
 }
 
 pub fn get_meta(package: &Package) -> serde_json::Value {
     let mut nix = nix();
-    let cmd = nix
-        .arg("eval")
-        .arg("--raw")
-        .arg(format!("{}#{}.meta", package.url, package.attr_path))
-        .arg("--apply")
-        .arg("builtins.toJSON");
+    let cmd = nix.args(eval_args(package, "meta"));
 
     let output = cmd.output().unwrap();
 
@@ -129,7 +470,11 @@ pub fn get_meta(package: &Package) -> serde_json::Value {
 
 #[cfg(test)]
 mod test {
-    use crate::{get_meta, get_version, manifest, manifest_parsed};
+    use crate::{
+        current_generation, generation_link, get_meta, get_profile_generations, get_version,
+        manifest, manifest_at_generation, manifest_parsed, parse_closure_size, parse_output_paths,
+        Package, Profile, ProfileError,
+    };
 
     #[test]
     pub fn test_manifest() {
@@ -142,4 +487,159 @@ mod test {
         let output = manifest_parsed().unwrap();
         println!("{:?}", output);
     }
+
+    #[test]
+    pub fn test_store_paths() {
+        let store = tempfile::tempdir().unwrap();
+        let present = store.path().join("abc-ripgrep-14.1.0");
+        std::fs::create_dir(&present).unwrap();
+        let present = present.to_string_lossy().into_owned();
+        let missing = store
+            .path()
+            .join("def-ripgrep-14.1.0-man")
+            .to_string_lossy()
+            .into_owned();
+
+        let mut package = Package {
+            store_paths: vec![present.clone()],
+            ..Default::default()
+        };
+        assert!(package.check_store_valid());
+        assert!(package.missing_store_paths().is_empty());
+
+        package.store_paths.push(missing.clone());
+        assert!(!package.check_store_valid());
+        assert_eq!(package.missing_store_paths(), [missing]);
+    }
+
+    #[test]
+    pub fn test_parse_closure_size() {
+        let old_format = r#"[{"path":"/nix/store/abc-ripgrep-14.1.0","narSize":4862552,"closureSize":35462048,"valid":true}]"#;
+        assert_eq!(parse_closure_size(old_format).unwrap(), 35462048);
+
+        let new_format =
+            r#"{"/nix/store/abc-ripgrep-14.1.0":{"narSize":4862552,"closureSize":35462048}}"#;
+        assert_eq!(parse_closure_size(new_format).unwrap(), 35462048);
+
+        assert!(matches!(
+            parse_closure_size(r#"[{"path":"/nix/store/abc","valid":false}]"#),
+            Err(ProfileError::StoreError { .. })
+        ));
+        assert!(matches!(
+            parse_closure_size("not json"),
+            Err(ProfileError::SerdeJson { .. })
+        ));
+        assert!(matches!(
+            Package::default().closure_size_bytes(),
+            Err(ProfileError::StoreError { .. })
+        ));
+    }
+
+    #[test]
+    pub fn test_parse_output_paths() {
+        let store_paths = vec![
+            "/nix/store/abc-ripgrep-14.1.0".to_owned(),
+            "/nix/store/def-ripgrep-14.1.0-man".to_owned(),
+        ];
+        let expected = [
+            ("out".to_owned(), store_paths[0].clone()),
+            ("man".to_owned(), store_paths[1].clone()),
+        ];
+
+        let new_format = r#"{
+            "/nix/store/abc-ripgrep-14.1.0": {"deriver": "/nix/store/xyz-ripgrep-14.1.0.drv", "narSize": 4862552},
+            "/nix/store/def-ripgrep-14.1.0-man": {"deriver": "/nix/store/xyz-ripgrep-14.1.0.drv", "narSize": 15840}
+        }"#;
+        assert_eq!(
+            parse_output_paths(new_format, &store_paths).unwrap(),
+            expected
+        );
+
+        let old_format = r#"[
+            {"path": "/nix/store/def-ripgrep-14.1.0-man", "deriver": "/nix/store/xyz-ripgrep-14.1.0.drv"},
+            {"path": "/nix/store/abc-ripgrep-14.1.0", "deriver": "/nix/store/xyz-ripgrep-14.1.0.drv"}
+        ]"#;
+        assert_eq!(
+            parse_output_paths(old_format, &store_paths).unwrap(),
+            expected
+        );
+
+        // without a deriver, there's nothing to tell the outputs apart
+        let outputs = parse_output_paths("{}", &store_paths).unwrap();
+        assert!(outputs.iter().all(|(output, _)| output == "out"));
+
+        let package = Package {
+            outputs: Some(vec!["out".to_owned(), "man".to_owned()]),
+            ..Default::default()
+        };
+        assert!(package.has_output("man"));
+        assert!(!package.has_output("dev"));
+        assert!(Package::default().output_paths().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_profile_generations() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let profile = dir.path().join("profile");
+        for generation in [3, 12, 4] {
+            let store_path = dir.path().join(format!("abc-profile-{generation}"));
+            std::fs::create_dir(&store_path).unwrap();
+            symlink(
+                &store_path,
+                dir.path().join(format!("profile-{generation}-link")),
+            )
+            .unwrap();
+        }
+        symlink("profile-12-link", &profile).unwrap();
+        // neither of these are generations of `profile`.
+        std::fs::create_dir(dir.path().join("profile-old")).unwrap();
+        symlink("profile-1-link", dir.path().join("other-profile-1-link")).unwrap();
+
+        assert_eq!(get_profile_generations(&profile).unwrap(), [3, 4, 12]);
+        assert_eq!(current_generation(&profile).unwrap(), 12);
+        assert_eq!(
+            generation_link(&profile, 4),
+            dir.path().join("profile-4-link")
+        );
+        assert!(matches!(
+            manifest_at_generation(&profile, 7),
+            Err(ProfileError::UnknownGeneration { generation: 7, .. })
+        ));
+    }
+
+    #[test]
+    pub fn test_profile_command() {
+        let cmd = Profile::default().nix_profile("list");
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["profile", "list"]);
+
+        let cmd = Profile::at("/nix/var/nix/profiles/per-user/me/dev").nix_profile("list");
+        assert_eq!(
+            cmd.get_args().collect::<Vec<_>>(),
+            [
+                "profile",
+                "list",
+                "--profile",
+                "/nix/var/nix/profiles/per-user/me/dev"
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_profile_cache_file_name() {
+        assert_eq!(
+            Profile::default().cache_file_name(),
+            "profile-installed.toml"
+        );
+        assert_eq!(
+            Profile::at("/home/me/.local/state/nix/profiles/dev").cache_file_name(),
+            "profile-installed-home-me--local-state-nix-profiles-dev.toml"
+        );
+        assert_ne!(
+            Profile::at("/a/b").cache_file_name(),
+            Profile::at("/a/c").cache_file_name()
+        );
+    }
 }
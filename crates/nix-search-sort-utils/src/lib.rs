@@ -0,0 +1,743 @@
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexMap;
+use nix_elastic_search::response::{NixPackage, PackageLicense};
+use semver::Version;
+
+/// the length of the longest (not necessarily contiguous)
+/// subsequence which both sequences share.
+pub fn longest_common_subsequence_length(seq1: &[u8], seq2: &[u8]) -> usize {
+    let mut dp = vec![vec![0; seq2.len() + 1]; seq1.len() + 1];
+
+    for i in 1..=seq1.len() {
+        for j in 1..=seq2.len() {
+            if seq1[i - 1] == seq2[j - 1] {
+                dp[i][j] = dp[i - 1][j - 1] + 1;
+            } else {
+                dp[i][j] = dp[i - 1][j].max(dp[i][j - 1]);
+            }
+        }
+    }
+
+    dp[seq1.len()][seq2.len()]
+}
+
+/// how well a name matches a query, a larger
+/// `QueryQuality` is a better match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct QueryQuality {
+    /// the longest common subsequence of the query and the name
+    pub dist: usize,
+    /// minus the difference in length between the query and the name
+    pub proportionality: isize,
+}
+
+/// rank a name against a query, by the longest common subsequence
+/// first, and then by how close in length the two are.
+pub fn search_by_name_metric(query: &str, name: &str) -> QueryQuality {
+    QueryQuality {
+        // sort first by the longest common subsequence between the queries
+        dist: longest_common_subsequence_length(query.as_bytes(), name.as_bytes()),
+        // next sort how many characters are different between the queries.
+        proportionality: -(query.len().abs_diff(name.len()) as isize),
+    }
+}
+
+/// a score between 0.0 and 1.0 for how well `pkg` matches `query`, made up
+/// of the longest common subsequence, the difference in length, and
+/// whether the query appears in the attr name, each scaled to [0, 1].
+pub fn score_package(query: &str, pkg: &NixPackage) -> f64 {
+    let name = &pkg.package_attr_name;
+    let longest = query.len().max(name.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    let longest = longest as f64;
+
+    let quality = search_by_name_metric(query, name);
+    let subsequence = quality.dist as f64 / longest;
+    let length = 1.0 - quality.proportionality.unsigned_abs() as f64 / longest;
+    let substring = if !query.is_empty() && name.contains(query) {
+        1.0
+    } else {
+        0.0
+    };
+
+    0.5 * subsequence + 0.25 * length + 0.25 * substring
+}
+
+/// how new a version is, valid semver sorts by its version,
+/// and anything else scores 0 so that it sorts after it.
+fn score_version(version_str: &str) -> (u8, Version) {
+    match Version::parse(version_str) {
+        Ok(version) => (1, version),
+        Err(_) => (0, Version::new(0, 0, 0)),
+    }
+}
+
+/// newest version first. nixpkgs versions aren't always semver, such as
+/// 2023-04-01 or unstable-20240101, these go last, in lexicographic order.
+pub fn sort_packages_by_version_newness(pkgs: &mut [NixPackage]) {
+    pkgs.sort_by_cached_key(|pkg| {
+        let (valid, version) = score_version(&pkg.package_pversion);
+        (
+            std::cmp::Reverse((valid, version)),
+            pkg.package_pversion.clone(),
+        )
+    });
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum PackageSupport {
+    Supported,
+    MostLikelyNot,
+    NoneListed,
+}
+
+/// whether `pkg` lists `system` among its platforms.
+pub fn platform_support(system: &str, pkg: &NixPackage) -> PackageSupport {
+    if pkg.package_platforms.is_empty() {
+        PackageSupport::NoneListed
+    } else if pkg.package_platforms.iter().any(|p| p == system) {
+        PackageSupport::Supported
+    } else {
+        PackageSupport::MostLikelyNot
+    }
+}
+
+/// whether `pkg` might build on `system`, packages which
+/// don't list any platforms get the benefit of the doubt.
+fn may_support_platform(system: &str, pkg: &NixPackage) -> bool {
+    platform_support(system, pkg) != PackageSupport::MostLikelyNot
+}
+
+/// remove the packages which list their platforms,
+/// but don't list `system`, such as aarch64-darwin.
+pub fn filter_packages_by_platform(system: &str, pkgs: &mut Vec<NixPackage>) {
+    pkgs.retain(|pkg| may_support_platform(system, pkg));
+}
+
+/// split packages into those which may support `system`, and those
+/// which definitely don't, keeping the order within each.
+pub fn partition_packages_by_platform(
+    system: &str,
+    pkgs: Vec<NixPackage>,
+) -> (Vec<NixPackage>, Vec<NixPackage>) {
+    pkgs.into_iter()
+        .partition(|pkg| may_support_platform(system, pkg))
+}
+
+/// SPDX ids of licenses which are commonly considered non-free,
+/// they're source available, but restrict how they can be used.
+pub const NONFREE_SPDX_IDS: &[&str] = &[
+    "BUSL-1.1",
+    "SSPL-1.0",
+    "Elastic-2.0",
+    "CC-BY-NC-4.0",
+    "CC-BY-NC-SA-4.0",
+    "CC-BY-NC-ND-4.0",
+    "Commons-Clause",
+];
+
+/// nixpkgs reports licenses by their human readable name,
+/// this maps the names of the common ones back to the
+/// identifier that SPDX (and tools like cargo-deny) use.
+static SPDX_IDS: phf::Map<&'static str, &'static str> = phf::phf_map! {
+    "MIT License" => "MIT",
+    "MIT No Attribution" => "MIT-0",
+    "Apache License 2.0" => "Apache-2.0",
+    "GNU General Public License v2.0 only" => "GPL-2.0-only",
+    "GNU General Public License v2.0 or later" => "GPL-2.0-or-later",
+    "GNU General Public License v3.0 only" => "GPL-3.0-only",
+    "GNU General Public License v3.0 or later" => "GPL-3.0-or-later",
+    "GNU Library General Public License v2 only" => "LGPL-2.0-only",
+    "GNU Library General Public License v2 or later" => "LGPL-2.0-or-later",
+    "GNU Lesser General Public License v2.1 only" => "LGPL-2.1-only",
+    "GNU Lesser General Public License v2.1 or later" => "LGPL-2.1-or-later",
+    "GNU Lesser General Public License v3.0 only" => "LGPL-3.0-only",
+    "GNU Lesser General Public License v3.0 or later" => "LGPL-3.0-or-later",
+    "GNU Affero General Public License v3.0 only" => "AGPL-3.0-only",
+    "GNU Affero General Public License v3.0 or later" => "AGPL-3.0-or-later",
+    "BSD 2-clause \"Simplified\" License" => "BSD-2-Clause",
+    "BSD 3-clause \"New\" or \"Revised\" License" => "BSD-3-Clause",
+    "BSD Zero Clause License" => "0BSD",
+    "ISC License" => "ISC",
+    "Mozilla Public License 1.1" => "MPL-1.1",
+    "Mozilla Public License 2.0" => "MPL-2.0",
+    "The Unlicense" => "Unlicense",
+    "Creative Commons Zero v1.0 Universal" => "CC0-1.0",
+    "Creative Commons Attribution 4.0 International" => "CC-BY-4.0",
+    "Creative Commons Attribution Share Alike 4.0 International" => "CC-BY-SA-4.0",
+    "zlib License" => "Zlib",
+    "Boost Software License 1.0" => "BSL-1.0",
+    "Eclipse Public License 1.0" => "EPL-1.0",
+    "Eclipse Public License 2.0" => "EPL-2.0",
+    "Artistic License 2.0" => "Artistic-2.0",
+    "PostgreSQL License" => "PostgreSQL",
+    "Python Software Foundation License version 2" => "Python-2.0",
+    "Do What The F*ck You Want To Public License" => "WTFPL",
+    "Universal Permissive License v1.0" => "UPL-1.0",
+    "OpenSSL License" => "OpenSSL",
+    "Academic Free License v3.0" => "AFL-3.0",
+    "Common Development and Distribution License 1.0" => "CDDL-1.0",
+    "European Union Public License 1.2" => "EUPL-1.2",
+    "SIL Open Font License 1.1" => "OFL-1.1",
+    "Vim License" => "Vim",
+    "X11 License" => "X11",
+    "curl License" => "curl",
+    "Business Source License 1.1" => "BUSL-1.1",
+    "Server Side Public License" => "SSPL-1.0",
+    "Elastic License 2.0" => "Elastic-2.0",
+    "Creative Commons Attribution Non Commercial 4.0 International" => "CC-BY-NC-4.0",
+    "Creative Commons Attribution Non Commercial Share Alike 4.0 International" => "CC-BY-NC-SA-4.0",
+    "Creative Commons Attribution Non Commercial No Derivatives 4.0 International" => "CC-BY-NC-ND-4.0",
+    "Commons Clause License 1.0" => "Commons-Clause",
+};
+
+fn spdx_id_by_name(full_name: &str) -> Option<&'static str> {
+    SPDX_IDS.get(full_name).copied()
+}
+
+/// the SPDX identifier of a license, nixpkgs links SPDX licenses
+/// to spdx.org, such as https://spdx.org/licenses/MIT.html, and
+/// failing that, we look the full name up.
+pub fn spdx_id(license: &PackageLicense) -> Option<&str> {
+    license
+        .url
+        .as_deref()
+        .and_then(|url| {
+            url.strip_prefix("https://spdx.org/licenses/")?
+                .strip_suffix(".html")
+        })
+        .or_else(|| spdx_id_by_name(&license.full_name))
+}
+
+/// whether a license is non-free, nixpkgs' own unfree licenses
+/// don't have an SPDX id, but they're all named "Unfree ...".
+pub fn is_nonfree_license(full_name: &str) -> bool {
+    full_name.starts_with("Unfree")
+        || spdx_id_by_name(full_name).is_some_and(|spdx| NONFREE_SPDX_IDS.contains(&spdx))
+}
+
+/// whether any of a package's licenses are non-free.
+pub fn is_unfree(pkg: &NixPackage) -> bool {
+    pkg.package_license
+        .iter()
+        .any(|license| is_nonfree_license(&license.full_name))
+}
+
+/// SPDX ids of the licenses we know about which the
+/// open source initiative has approved.
+pub const OSI_APPROVED_SPDX_IDS: &[&str] = &[
+    "MIT",
+    "MIT-0",
+    "Apache-2.0",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "LGPL-2.0-only",
+    "LGPL-2.0-or-later",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "0BSD",
+    "ISC",
+    "MPL-1.1",
+    "MPL-2.0",
+    "Unlicense",
+    "Zlib",
+    "BSL-1.0",
+    "EPL-1.0",
+    "EPL-2.0",
+    "Artistic-2.0",
+    "PostgreSQL",
+    "Python-2.0",
+    "UPL-1.0",
+    "AFL-3.0",
+    "CDDL-1.0",
+    "EUPL-1.2",
+    "OFL-1.1",
+];
+
+/// whether a license is one the open source initiative has approved.
+pub fn is_open_source(license: &PackageLicense) -> bool {
+    spdx_id(license).is_some_and(|spdx| OSI_APPROVED_SPDX_IDS.contains(&spdx))
+}
+
+/// remove the packages which have a non-free license.
+pub fn filter_packages_to_free_only(pkgs: Vec<NixPackage>) -> Vec<NixPackage> {
+    pkgs.into_iter().filter(|pkg| !is_unfree(pkg)).collect()
+}
+
+/// keep only the first package with each attr name, searching
+/// several channels can find the same package more than once.
+pub fn deduplicate_packages_by_attr_name(pkgs: Vec<NixPackage>) -> Vec<NixPackage> {
+    let mut seen = HashSet::new();
+    pkgs.into_iter()
+        .filter(|pkg| seen.insert(pkg.package_attr_name.clone()))
+        .collect()
+}
+
+/// what makes two packages the same package, they can still differ
+/// in everything else, such as when they come from different channels,
+/// or list their platforms in a different order.
+pub fn semantic_key(pkg: &NixPackage) -> (&str, &str, &str) {
+    (
+        &pkg.package_attr_name,
+        &pkg.package_pversion,
+        &pkg.package_pname,
+    )
+}
+
+/// keep only the first of each package with the same [`semantic_key`],
+/// unlike [`deduplicate_packages_by_attr_name`], different versions of
+/// a package are all kept.
+pub fn deduplicate_packages_by_semantic_key(pkgs: Vec<NixPackage>) -> Vec<NixPackage> {
+    let mut seen = HashSet::new();
+    pkgs.into_iter()
+        .filter(|pkg| {
+            let (attr_name, version, pname) = semantic_key(pkg);
+            seen.insert((attr_name.to_owned(), version.to_owned(), pname.to_owned()))
+        })
+        .collect()
+}
+
+/// like [`deduplicate_packages_by_attr_name`], but keeps the package with
+/// the (lexicographically) larger version, in the place of the first one.
+pub fn deduplicate_packages_preferring_newer(pkgs: Vec<NixPackage>) -> Vec<NixPackage> {
+    let mut index_of: HashMap<String, usize> = HashMap::new();
+    let mut out: Vec<NixPackage> = Vec::with_capacity(pkgs.len());
+    for pkg in pkgs {
+        match index_of.get(&pkg.package_attr_name) {
+            Some(&i) => {
+                if pkg.package_pversion > out[i].package_pversion {
+                    out[i] = pkg;
+                }
+            }
+            None => {
+                index_of.insert(pkg.package_attr_name.clone(), out.len());
+                out.push(pkg);
+            }
+        }
+    }
+    out
+}
+
+/// packages which aren't in an attribute set are grouped under this.
+pub const TOP_LEVEL: &str = "top-level";
+
+/// the attribute set a package is in, such as python3Packages
+/// for python3Packages.requests, or [`TOP_LEVEL`] for ripgrep.
+pub fn get_attr_set_prefix(attr_name: &str) -> &str {
+    match attr_name.split_once('.') {
+        Some((prefix, _)) => prefix,
+        None => TOP_LEVEL,
+    }
+}
+
+/// group packages by their attribute set, top-level packages come
+/// first, then the rest alphabetically. packages keep their order
+/// within a group.
+pub fn group_packages_by_attr_set(pkgs: Vec<NixPackage>) -> IndexMap<String, Vec<NixPackage>> {
+    let mut groups: IndexMap<String, Vec<NixPackage>> = IndexMap::new();
+    for pkg in pkgs {
+        let prefix = get_attr_set_prefix(&pkg.package_attr_name).to_owned();
+        groups.entry(prefix).or_default().push(pkg);
+    }
+    groups.sort_by(|a, _, b, _| (a != TOP_LEVEL, a).cmp(&(b != TOP_LEVEL, b)));
+    groups
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn package(name: &str, version: &str) -> NixPackage {
+        NixPackage {
+            package_attr_name: name.to_owned(),
+            package_pversion: version.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn on(name: &str, platforms: &[&str]) -> NixPackage {
+        NixPackage {
+            package_attr_name: name.to_owned(),
+            package_platforms: platforms.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn names(pkgs: &[NixPackage]) -> Vec<&str> {
+        pkgs.iter().map(|p| p.package_attr_name.as_str()).collect()
+    }
+
+    fn platform_packages() -> Vec<NixPackage> {
+        vec![
+            on("everywhere", &["x86_64-linux", "aarch64-darwin"]),
+            on("linux-only", &["x86_64-linux"]),
+            on("unlisted", &[]),
+            on("mac-only", &["aarch64-darwin"]),
+        ]
+    }
+
+    fn versions(pkgs: &[NixPackage]) -> Vec<&str> {
+        pkgs.iter().map(|p| p.package_pversion.as_str()).collect()
+    }
+
+    #[test]
+    fn test_longest_common_subsequence() {
+        assert_eq!(longest_common_subsequence_length(b"cargo", b"cargo"), 5);
+        assert_eq!(longest_common_subsequence_length(b"rg", b"ripgrep"), 2);
+        assert_eq!(longest_common_subsequence_length(b"abc", b"xyz"), 0);
+        assert_eq!(longest_common_subsequence_length(b"", b"cargo"), 0);
+    }
+
+    #[test]
+    fn test_search_by_name_metric() {
+        assert!(
+            search_by_name_metric("cargo", "cargo") > search_by_name_metric("cargo", "cargo-edit")
+        );
+        assert_eq!(
+            search_by_name_metric("cargo", "cargo-edit"),
+            QueryQuality {
+                dist: 5,
+                proportionality: -5
+            }
+        );
+    }
+
+    #[test]
+    fn test_score_package() {
+        let cargo = score_package("cargo", &package("cargo", "1.82.0"));
+        let cargo_edit = score_package("cargo", &package("cargo-edit", "0.13.0"));
+        let unrelated = score_package("cargo", &package("ripgrep", "14.1.1"));
+        assert_eq!(cargo, 1.0);
+        assert!(cargo > cargo_edit);
+        assert!(cargo_edit > unrelated);
+
+        for score in [cargo, cargo_edit, unrelated] {
+            assert!((0.0..=1.0).contains(&score));
+        }
+        assert_eq!(score_package("", &package("", "")), 0.0);
+    }
+
+    #[test]
+    fn test_score_version() {
+        assert_eq!(score_version("1.0.0"), (1, Version::new(1, 0, 0)));
+        assert_eq!(score_version("2023-04-01").0, 0);
+        assert_eq!(score_version("unstable-20240101").0, 0);
+    }
+
+    #[test]
+    fn test_sort_by_version_newness() {
+        let mut pkgs = vec![
+            package("a", "unstable-20240101"),
+            package("b", "1.0.0"),
+            package("c", "2023-04-01"),
+            package("d", "1.10.0"),
+            package("e", "1.2.0"),
+        ];
+        sort_packages_by_version_newness(&mut pkgs);
+        assert_eq!(
+            versions(&pkgs),
+            [
+                "1.10.0",
+                "1.2.0",
+                "1.0.0",
+                "2023-04-01",
+                "unstable-20240101"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_platform_support() {
+        let pkgs = platform_packages();
+        assert_eq!(
+            platform_support("x86_64-linux", &pkgs[1]),
+            PackageSupport::Supported
+        );
+        assert_eq!(
+            platform_support("x86_64-linux", &pkgs[2]),
+            PackageSupport::NoneListed
+        );
+        assert_eq!(
+            platform_support("x86_64-linux", &pkgs[3]),
+            PackageSupport::MostLikelyNot
+        );
+    }
+
+    #[test]
+    fn test_filter_by_platform() {
+        let mut pkgs = platform_packages();
+        filter_packages_by_platform("aarch64-darwin", &mut pkgs);
+        // unlisted has no platforms, so it's kept.
+        assert_eq!(names(&pkgs), ["everywhere", "unlisted", "mac-only"]);
+
+        let mut pkgs = platform_packages();
+        filter_packages_by_platform("riscv64-linux", &mut pkgs);
+        assert_eq!(names(&pkgs), ["unlisted"]);
+    }
+
+    #[test]
+    fn test_partition_by_platform() {
+        let (supported, unsupported) =
+            partition_packages_by_platform("x86_64-linux", platform_packages());
+        assert_eq!(names(&supported), ["everywhere", "linux-only", "unlisted"]);
+        assert_eq!(names(&unsupported), ["mac-only"]);
+
+        let (supported, unsupported) = partition_packages_by_platform("x86_64-linux", vec![]);
+        assert!(supported.is_empty());
+        assert!(unsupported.is_empty());
+    }
+
+    fn overlapping() -> Vec<NixPackage> {
+        vec![
+            package("ripgrep", "14.1.0"),
+            package("gleam", "1.4.1"),
+            package("ripgrep", "14.1.1"),
+            package("lazygit", "0.44.1"),
+            package("gleam", "1.2.0"),
+        ]
+    }
+
+    #[test]
+    fn test_deduplicate_keeps_first() {
+        let pkgs = deduplicate_packages_by_attr_name(overlapping());
+        assert_eq!(names(&pkgs), ["ripgrep", "gleam", "lazygit"]);
+        assert_eq!(versions(&pkgs), ["14.1.0", "1.4.1", "0.44.1"]);
+    }
+
+    #[test]
+    fn test_deduplicate_preferring_newer() {
+        let pkgs = deduplicate_packages_preferring_newer(overlapping());
+        assert_eq!(names(&pkgs), ["ripgrep", "gleam", "lazygit"]);
+        assert_eq!(versions(&pkgs), ["14.1.1", "1.4.1", "0.44.1"]);
+
+        assert!(deduplicate_packages_preferring_newer(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_semantic_key() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let hash = |pkg: &NixPackage| {
+            let mut hasher = DefaultHasher::new();
+            semantic_key(pkg).hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let mut unstable = package("ripgrep", "14.1.1");
+        unstable.package_platforms = vec!["x86_64-linux".to_owned(), "aarch64-darwin".to_owned()];
+        let mut stable = package("ripgrep", "14.1.1");
+        stable.package_platforms = vec!["aarch64-darwin".to_owned(), "x86_64-linux".to_owned()];
+        stable.package_description = Some("a faster grep".to_owned());
+
+        assert_ne!(unstable, stable);
+        assert_eq!(semantic_key(&unstable), semantic_key(&stable));
+        assert_eq!(hash(&unstable), hash(&stable));
+        assert_ne!(
+            semantic_key(&unstable),
+            semantic_key(&package("ripgrep", "14.1.0"))
+        );
+
+        let mut pkgs = overlapping();
+        pkgs.push(package("ripgrep", "14.1.1"));
+        let pkgs = deduplicate_packages_by_semantic_key(pkgs);
+        assert_eq!(
+            versions(&pkgs),
+            ["14.1.0", "1.4.1", "14.1.1", "0.44.1", "1.2.0"]
+        );
+    }
+
+    #[test]
+    fn test_attr_set_prefix() {
+        assert_eq!(
+            get_attr_set_prefix("python3Packages.requests"),
+            "python3Packages"
+        );
+        assert_eq!(
+            get_attr_set_prefix("haskellPackages.pandoc.data"),
+            "haskellPackages"
+        );
+        assert_eq!(get_attr_set_prefix("ripgrep"), TOP_LEVEL);
+    }
+
+    #[test]
+    fn test_group_by_attr_set() {
+        let groups = group_packages_by_attr_set(vec![
+            package("python3Packages.requests", "2.32.3"),
+            package("ripgrep", "14.1.1"),
+            package("nodePackages.prettier", "3.3.3"),
+            package("python3Packages.flask", "3.0.3"),
+            package("lazygit", "0.44.1"),
+        ]);
+
+        let keys = groups.keys().map(String::as_str).collect::<Vec<_>>();
+        assert_eq!(keys, [TOP_LEVEL, "nodePackages", "python3Packages"]);
+        assert_eq!(names(&groups[TOP_LEVEL]), ["ripgrep", "lazygit"]);
+        assert_eq!(
+            names(&groups["python3Packages"]),
+            ["python3Packages.requests", "python3Packages.flask"]
+        );
+
+        assert!(group_packages_by_attr_set(vec![]).is_empty());
+    }
+
+    fn licensed(name: &str, licenses: &[&str]) -> NixPackage {
+        NixPackage {
+            package_attr_name: name.to_owned(),
+            package_license: licenses
+                .iter()
+                .map(|full_name| PackageLicense {
+                    full_name: full_name.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filter_packages_to_free_only() {
+        let pkgs = vec![
+            licensed("ripgrep", &["MIT License", "The Unlicense"]),
+            licensed("vscode", &["Unfree"]),
+            licensed("steam", &["Unfree redistributable"]),
+            licensed("mongodb", &["Server Side Public License"]),
+            licensed("terraform", &["MIT License", "Business Source License 1.1"]),
+            licensed("unlicensed", &[]),
+        ];
+        assert!(!is_unfree(&pkgs[0]));
+        assert!(is_unfree(&pkgs[4]));
+        assert_eq!(
+            names(&filter_packages_to_free_only(pkgs)),
+            ["ripgrep", "unlicensed"]
+        );
+    }
+
+    #[test]
+    fn test_spdx_ids() {
+        let license = |full_name: &str| PackageLicense {
+            full_name: full_name.to_owned(),
+            url: None,
+        };
+
+        // the fullName of each license, as nixpkgs' lib.licenses has it
+        for (full_name, spdx) in [
+            ("MIT License", "MIT"),
+            ("MIT No Attribution", "MIT-0"),
+            ("Apache License 2.0", "Apache-2.0"),
+            ("GNU General Public License v2.0 only", "GPL-2.0-only"),
+            (
+                "GNU General Public License v2.0 or later",
+                "GPL-2.0-or-later",
+            ),
+            ("GNU General Public License v3.0 only", "GPL-3.0-only"),
+            (
+                "GNU General Public License v3.0 or later",
+                "GPL-3.0-or-later",
+            ),
+            (
+                "GNU Lesser General Public License v2.1 only",
+                "LGPL-2.1-only",
+            ),
+            (
+                "GNU Lesser General Public License v2.1 or later",
+                "LGPL-2.1-or-later",
+            ),
+            (
+                "GNU Lesser General Public License v3.0 only",
+                "LGPL-3.0-only",
+            ),
+            (
+                "GNU Lesser General Public License v3.0 or later",
+                "LGPL-3.0-or-later",
+            ),
+            (
+                "GNU Affero General Public License v3.0 only",
+                "AGPL-3.0-only",
+            ),
+            (
+                "GNU Affero General Public License v3.0 or later",
+                "AGPL-3.0-or-later",
+            ),
+            ("BSD 2-clause \"Simplified\" License", "BSD-2-Clause"),
+            (
+                "BSD 3-clause \"New\" or \"Revised\" License",
+                "BSD-3-Clause",
+            ),
+            ("BSD Zero Clause License", "0BSD"),
+            ("ISC License", "ISC"),
+            ("Mozilla Public License 2.0", "MPL-2.0"),
+            ("The Unlicense", "Unlicense"),
+            ("Creative Commons Zero v1.0 Universal", "CC0-1.0"),
+            (
+                "Creative Commons Attribution 4.0 International",
+                "CC-BY-4.0",
+            ),
+            ("zlib License", "Zlib"),
+            ("Boost Software License 1.0", "BSL-1.0"),
+            ("Eclipse Public License 2.0", "EPL-2.0"),
+            ("Artistic License 2.0", "Artistic-2.0"),
+            ("PostgreSQL License", "PostgreSQL"),
+            ("Do What The F*ck You Want To Public License", "WTFPL"),
+            ("Universal Permissive License v1.0", "UPL-1.0"),
+            ("OpenSSL License", "OpenSSL"),
+            ("SIL Open Font License 1.1", "OFL-1.1"),
+            ("European Union Public License 1.2", "EUPL-1.2"),
+            ("Business Source License 1.1", "BUSL-1.1"),
+        ] {
+            assert_eq!(spdx_id(&license(full_name)), Some(spdx), "{full_name}");
+        }
+
+        assert_eq!(spdx_id(&license("Unfree")), None);
+        assert_eq!(spdx_id(&license("Unfree redistributable")), None);
+        assert_eq!(spdx_id(&license("mit license")), None);
+
+        // the spdx.org link wins over the name
+        let linked = PackageLicense {
+            full_name: "MIT/Expat License".to_owned(),
+            url: Some("https://spdx.org/licenses/MIT.html".to_owned()),
+        };
+        assert_eq!(spdx_id(&linked), Some("MIT"));
+    }
+
+    #[test]
+    fn test_nonfree_spdx_ids() {
+        for spdx in NONFREE_SPDX_IDS {
+            assert!(SPDX_IDS.values().any(|known| known == spdx), "{spdx}");
+        }
+        assert!(is_nonfree_license("Elastic License 2.0"));
+        assert!(!is_nonfree_license("MIT License"));
+    }
+
+    #[test]
+    fn test_is_open_source() {
+        let license = |full_name: &str| PackageLicense {
+            full_name: full_name.to_owned(),
+            ..Default::default()
+        };
+
+        for spdx in OSI_APPROVED_SPDX_IDS {
+            assert!(SPDX_IDS.values().any(|known| known == spdx), "{spdx}");
+        }
+        assert!(is_open_source(&license("MIT License")));
+        assert!(is_open_source(&license("Mozilla Public License 2.0")));
+        assert!(!is_open_source(&license(
+            "Creative Commons Attribution 4.0 International"
+        )));
+        assert!(!is_open_source(&license(
+            "Do What The F*ck You Want To Public License"
+        )));
+        assert!(!is_open_source(&license("Unfree")));
+    }
+}
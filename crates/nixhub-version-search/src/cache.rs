@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{scrape_package_version, PackageVersionSearchError, VersionLookup};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    versions: Vec<VersionLookup>,
+    cached_at: DateTime<Utc>,
+}
+
+/// remembers what nixhub.io said about each package, so that
+/// it only gets scraped again once `ttl` has passed.
+#[derive(Debug, Clone)]
+pub struct CachedVersionSearcher {
+    ttl: Duration,
+    path: PathBuf,
+}
+
+impl CachedVersionSearcher {
+    /// the cache is kept at `path`, it's up to whoever uses
+    /// the searcher to decide where that is.
+    pub fn new(path: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            ttl,
+            path: path.into(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// like [`scrape_package_version`], but a recent enough
+    /// result is read from the cache instead.
+    pub fn scrape_package_version_cached(
+        &self,
+        name: &str,
+    ) -> Result<Vec<VersionLookup>, PackageVersionSearchError> {
+        self.cached_or_else(name, Utc::now(), || scrape_package_version(name))
+    }
+
+    fn cached_or_else(
+        &self,
+        name: &str,
+        now: DateTime<Utc>,
+        scrape: impl FnOnce() -> Result<Vec<VersionLookup>, PackageVersionSearchError>,
+    ) -> Result<Vec<VersionLookup>, PackageVersionSearchError> {
+        let mut entries = self.load();
+        if let Some(entry) = entries.get(name) {
+            if self.is_fresh(entry, now) {
+                return Ok(entry.versions.clone());
+            }
+        }
+
+        let versions = scrape()?;
+        entries.insert(
+            name.to_owned(),
+            CacheEntry {
+                versions: versions.clone(),
+                cached_at: now,
+            },
+        );
+        // failing to cache just means scraping again next time.
+        let _ = self.save(&entries);
+        Ok(versions)
+    }
+
+    fn is_fresh(&self, entry: &CacheEntry, now: DateTime<Utc>) -> bool {
+        match (now - entry.cached_at).to_std() {
+            Ok(age) => age < self.ttl,
+            // cached in the future, the clock must have changed.
+            Err(_) => false,
+        }
+    }
+
+    /// a missing or unreadable cache is treated as empty.
+    fn load(&self) -> BTreeMap<String, CacheEntry> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, entries: &BTreeMap<String, CacheEntry>) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(entries)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn lazygit(version: &str) -> Vec<VersionLookup> {
+        vec![VersionLookup {
+            version: version.to_owned(),
+            commit: "4aa36568d413aca0ea84a1684d2d46f55dbabad7".to_owned(),
+            date: "Nov 2, 2024".to_owned(),
+        }]
+    }
+
+    #[test]
+    fn test_cache_is_reused_until_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let searcher = CachedVersionSearcher::new(dir.path().join("version-cache.json"), DAY);
+        let now = Utc::now();
+
+        let versions = searcher
+            .cached_or_else("lazygit", now, || Ok(lazygit("0.44.1")))
+            .unwrap();
+        assert_eq!(versions, lazygit("0.44.1"));
+        assert!(searcher.path().is_file());
+
+        let later = now + chrono::Duration::hours(1);
+        let versions = searcher
+            .cached_or_else("lazygit", later, || panic!("should be cached"))
+            .unwrap();
+        assert_eq!(versions, lazygit("0.44.1"));
+
+        let much_later = now + chrono::Duration::days(1);
+        let versions = searcher
+            .cached_or_else("lazygit", much_later, || Ok(lazygit("0.45.0")))
+            .unwrap();
+        assert_eq!(versions, lazygit("0.45.0"));
+    }
+
+    #[test]
+    fn test_broken_cache_falls_back_to_live() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("version-cache.json");
+        std::fs::write(&path, "not json").unwrap();
+        let searcher = CachedVersionSearcher::new(&path, DAY);
+
+        let versions = searcher
+            .cached_or_else("lazygit", Utc::now(), || Ok(lazygit("0.44.1")))
+            .unwrap();
+        assert_eq!(versions, lazygit("0.44.1"));
+
+        // a cache which can't be written to doesn't fail the search either.
+        let searcher = CachedVersionSearcher::new(dir.path(), DAY);
+        let versions = searcher
+            .cached_or_else("lazygit", Utc::now(), || Ok(lazygit("0.44.1")))
+            .unwrap();
+        assert_eq!(versions, lazygit("0.44.1"));
+    }
+
+    #[test]
+    fn test_failed_scrape_is_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let searcher = CachedVersionSearcher::new(dir.path().join("version-cache.json"), DAY);
+
+        let result = searcher.cached_or_else("lazygit", Utc::now(), || {
+            Err(PackageVersionSearchError::RateLimited)
+        });
+        assert!(matches!(
+            result,
+            Err(PackageVersionSearchError::RateLimited)
+        ));
+        assert!(!searcher.path().exists());
+    }
+}
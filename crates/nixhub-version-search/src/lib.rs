@@ -1,79 +1,265 @@
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
 use scraper::Selector;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+mod cache;
+
+pub use cache::CachedVersionSearcher;
+
 #[derive(Debug, Error)]
 pub enum PackageVersionSearchError {
     #[error("Failed to search for package version. Network error: {0}")]
-    UreqError(#[from] ureq::Error),
+    UreqError(Box<ureq::Error>),
     #[error("Failed to parse package version. IO Error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("Selector error: {0}")]
     SelectorError(String),
+    #[error("nixhub.io is rate limiting us, try again later")]
+    RateLimited,
+    #[error("nixhub.io doesn't know about a package called {0}")]
+    NotFound(String),
+    #[error("gave up after {attempts} attempts, the last error was: {last_error}")]
+    MaxRetriesExceeded { attempts: u32, last_error: String },
+}
+
+// ureq's error is large, so it's boxed to
+// keep every other result small.
+impl From<ureq::Error> for PackageVersionSearchError {
+    fn from(error: ureq::Error) -> Self {
+        PackageVersionSearchError::UreqError(Box::new(error))
+    }
 }
 
+/// how long to wait before the first retry, each
+/// retry after that waits twice as long.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// how many times a failed request to nixhub.io is tried again.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// every request to nixhub.io waits its turn here, so that
+/// retries and lookups of several packages are spaced out.
+static NIXHUB_RATE_LIMITER: LazyLock<RateLimiter> = LazyLock::new(RateLimiter::default);
+
 pub fn search_package(exact_name: &str) -> Result<String, PackageVersionSearchError> {
-    let res = ureq::get(&format!("https://nixhub.io/packages/{exact_name}"))
-        .call()?
-        .into_string()?;
-    Ok(res)
+    NIXHUB_RATE_LIMITER.wait();
+    let res = match ureq::get(&format!("https://nixhub.io/packages/{exact_name}")).call() {
+        Ok(res) => res,
+        Err(ureq::Error::Status(429, _)) => return Err(PackageVersionSearchError::RateLimited),
+        Err(ureq::Error::Status(404, _)) => {
+            return Err(PackageVersionSearchError::NotFound(exact_name.to_owned()))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(res.into_string()?)
+}
+
+/// like [`search_package`], but network errors are retried up to
+/// `max_retries` times, backing off exponentially between attempts.
+pub fn search_package_with_retry(
+    exact_name: &str,
+    max_retries: u32,
+) -> Result<String, PackageVersionSearchError> {
+    retry_with_backoff(max_retries, INITIAL_RETRY_DELAY, || {
+        search_package(exact_name)
+    })
 }
 
-#[derive(Clone, Hash, Debug, PartialEq, Eq)]
+/// whether trying again could help, which it can when we couldn't
+/// reach nixhub.io, or it's busy, but not when it said no.
+fn is_retryable(error: &PackageVersionSearchError) -> bool {
+    match error {
+        PackageVersionSearchError::RateLimited => true,
+        PackageVersionSearchError::UreqError(error) => match **error {
+            ureq::Error::Transport(_) => true,
+            ureq::Error::Status(code, _) => code == 429 || code >= 500,
+        },
+        _ => false,
+    }
+}
+
+fn retry_with_backoff<T>(
+    max_retries: u32,
+    initial_delay: Duration,
+    mut attempt: impl FnMut() -> Result<T, PackageVersionSearchError>,
+) -> Result<T, PackageVersionSearchError> {
+    let mut delay = initial_delay;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(e) if is_retryable(&e) => {
+                if attempts > max_retries {
+                    return Err(PackageVersionSearchError::MaxRetriesExceeded {
+                        attempts,
+                        last_error: e.to_string(),
+                    });
+                }
+            }
+            Err(e) => return Err(e),
+        }
+        std::thread::sleep(delay);
+        delay *= 2;
+    }
+}
+
+/// spaces requests out so that we don't hammer nixhub.io,
+/// it can be shared between threads.
+#[derive(Debug)]
+pub struct RateLimiter {
+    pub interval_ms: u64,
+    last_request: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(interval_ms: u64) -> Self {
+        let interval = Duration::from_millis(interval_ms);
+        let now = Instant::now();
+        Self {
+            interval_ms,
+            // pretend the last request was long enough
+            // ago that the first one can go straight away.
+            last_request: Mutex::new(now.checked_sub(interval).unwrap_or(now)),
+        }
+    }
+
+    /// block until at least `interval_ms` has passed since
+    /// the last time `wait` returned.
+    pub fn wait(&self) {
+        let mut last_request = self.last_request.lock().unwrap();
+        let interval = Duration::from_millis(self.interval_ms);
+        let elapsed = last_request.elapsed();
+        if elapsed < interval {
+            std::thread::sleep(interval - elapsed);
+        }
+        *last_request = Instant::now();
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(500)
+    }
+}
+
+#[derive(Clone, Hash, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VersionLookup {
     pub version: String,
     pub commit: String,
+    /// when the version landed in nixpkgs, as nixhub.io
+    /// shows it, such as "Nov 2, 2024"
+    pub date: String,
+}
+
+impl VersionLookup {
+    /// the nixpkgs commit which has this version
+    pub fn nixpkgs_url(&self) -> String {
+        format!("https://github.com/NixOS/nixpkgs/commit/{}", self.commit)
+    }
+
+    /// a nix expression for `pkg_attr` from the
+    /// nixpkgs commit which has this version.
+    pub fn to_nix_pin(&self, pkg_attr: &str) -> String {
+        format!(
+            "(import (builtins.fetchTarball \"https://github.com/NixOS/nixpkgs/archive/{}.tar.gz\") {{}}).{pkg_attr}",
+            self.commit
+        )
+    }
 }
 
 pub fn scrape_package_version(
     package_name: &str,
 ) -> Result<Vec<VersionLookup>, PackageVersionSearchError> {
-    let html = search_package(package_name)?;
-    let scraper = scraper::Html::parse_document(&html);
+    parse_package_versions(&search_package_with_retry(
+        package_name,
+        DEFAULT_MAX_RETRIES,
+    )?)
+}
+
+fn selector(selector: &str) -> Result<Selector, PackageVersionSearchError> {
+    Selector::parse(selector)
+        .map_err(|e| e.to_string())
+        .map_err(PackageVersionSearchError::SelectorError)
+}
+
+/// pull the versions out of a package's page on nixhub.io
+pub fn parse_package_versions(html: &str) -> Result<Vec<VersionLookup>, PackageVersionSearchError> {
+    let scraper = scraper::Html::parse_document(html);
 
     // nixhub.io puts all nixpkgs versions in the
     // article sections of the page. a little strange,
     // but hey, I'm not going to question it too much.
-    let article_selector = Selector::parse("article")
-        .map_err(|e| e.to_string())
-        .map_err(PackageVersionSearchError::SelectorError)?;
+    let article_selector = selector("article")?;
+    let header_selector = selector("header > h3")?;
+    let date_selector = selector("header > :nth-child(2)")?;
+    let ref_selector = selector("div:first-of-type > p > span:first-of-type")?;
 
     let versions = scraper.select(&article_selector);
 
     let mut out_versions = Vec::new();
 
     for version in versions {
-        let header_selector = Selector::parse("header > h3")
-            .map_err(|e| e.to_string())
-            .map_err(PackageVersionSearchError::SelectorError)?;
-
-        let mut headers = version.select(&header_selector);
-
-        let header = headers.next().unwrap();
-        let just_version_child = header.children().skip(1).next().unwrap();
-        let version_text = 
-            just_version_child
-                .value()
-                .as_text()
-                .unwrap()
-                .text
-                .to_string();
-        let ref_selector = Selector::parse("div:first-of-type > p > span:first-of-type")
-            .map_err(|e| e.to_string())
-            .map_err(PackageVersionSearchError::SelectorError)?;
-
-        let mut refs = version.select(&ref_selector);
-        let re = refs.next().unwrap();
-        
+        let missing =
+            |what: &str| PackageVersionSearchError::SelectorError(format!("no {what} in article"));
+
+        let header = version
+            .select(&header_selector)
+            .next()
+            .ok_or_else(|| missing("version header"))?;
+        let version_text = header
+            .children()
+            .nth(1)
+            .and_then(|child| child.value().as_text())
+            .ok_or_else(|| missing("version"))?
+            .text
+            .to_string();
+
+        let date_text = version
+            .select(&date_selector)
+            .next()
+            .ok_or_else(|| missing("date"))?
+            .text()
+            .collect::<String>()
+            .trim()
+            .to_owned();
+
+        let re = version
+            .select(&ref_selector)
+            .next()
+            .ok_or_else(|| missing("commit"))?;
+
         let commit_text = re.text().collect::<Vec<_>>().join("");
 
         out_versions.push(VersionLookup {
             version: version_text,
             commit: commit_text,
+            date: date_text,
         });
     }
     Ok(out_versions)
 }
 
+/// look up the versions of several packages, one after another. every
+/// request already waits its turn, `rate_limiter` can space them out further.
+pub fn scrape_package_versions_batch(
+    package_names: &[&str],
+    rate_limiter: Option<&RateLimiter>,
+) -> Vec<Result<Vec<VersionLookup>, PackageVersionSearchError>> {
+    package_names
+        .iter()
+        .map(|name| {
+            if let Some(rate_limiter) = rate_limiter {
+                rate_limiter.wait();
+            }
+            scrape_package_version(name)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use std::error::Error;
@@ -92,4 +278,162 @@ mod test {
         dbg!(scrape_package_version("lazygit")?);
         Ok(())
     }
+
+    #[test]
+    fn test_parse_package_versions() {
+        let versions = parse_package_versions(include_str!("../fixtures/versions.html")).unwrap();
+        assert_eq!(
+            versions,
+            [
+                VersionLookup {
+                    version: "0.44.1".to_owned(),
+                    commit: "4aa36568d413aca0ea84a1684d2d46f55dbabad7".to_owned(),
+                    date: "Nov 2, 2024".to_owned(),
+                },
+                VersionLookup {
+                    version: "0.42.0".to_owned(),
+                    commit: "05bbf675397d5366259409139039af8077d695ce".to_owned(),
+                    date: "Jun 10, 2024".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_version_links() {
+        let lookup = VersionLookup {
+            version: "0.44.1".to_owned(),
+            commit: "4aa36568d413aca0ea84a1684d2d46f55dbabad7".to_owned(),
+            date: "Nov 2, 2024".to_owned(),
+        };
+        assert_eq!(
+            lookup.nixpkgs_url(),
+            "https://github.com/NixOS/nixpkgs/commit/4aa36568d413aca0ea84a1684d2d46f55dbabad7"
+        );
+        assert_eq!(
+            lookup.to_nix_pin("lazygit"),
+            "(import (builtins.fetchTarball \"https://github.com/NixOS/nixpkgs/archive/4aa36568d413aca0ea84a1684d2d46f55dbabad7.tar.gz\") {}).lazygit"
+        );
+    }
+
+    #[test]
+    fn test_parse_missing_elements() {
+        for fixture in [
+            include_str!("../fixtures/missing_commit.html"),
+            include_str!("../fixtures/missing_version.html"),
+            include_str!("../fixtures/missing_date.html"),
+        ] {
+            assert!(matches!(
+                parse_package_versions(fixture),
+                Err(PackageVersionSearchError::SelectorError(_))
+            ));
+        }
+        // a page without any versions isn't an error though.
+        assert_eq!(parse_package_versions("<html></html>").unwrap(), []);
+    }
+
+    #[test]
+    fn test_retry_gives_up() {
+        let mut calls = 0;
+        let result: Result<(), _> = retry_with_backoff(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(PackageVersionSearchError::RateLimited)
+        });
+        assert_eq!(calls, 4);
+        assert!(matches!(
+            result,
+            Err(PackageVersionSearchError::MaxRetriesExceeded { attempts: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_retry_recovers() {
+        let mut calls = 0;
+        let result = retry_with_backoff(3, Duration::from_millis(1), || {
+            calls += 1;
+            if calls < 3 {
+                Err(PackageVersionSearchError::RateLimited)
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        // errors which aren't the network's fault are returned straight away.
+        let mut calls = 0;
+        let result: Result<(), _> = retry_with_backoff(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(PackageVersionSearchError::SelectorError("bad".to_owned()))
+        });
+        assert_eq!(calls, 1);
+        assert!(matches!(
+            result,
+            Err(PackageVersionSearchError::SelectorError(_))
+        ));
+    }
+
+    fn status(code: u16) -> PackageVersionSearchError {
+        let response = ureq::Response::new(code, "", "").unwrap();
+        ureq::Error::Status(code, response).into()
+    }
+
+    #[test]
+    fn test_retry_client_errors() {
+        // a package which doesn't exist won't exist on the next try either.
+        let mut calls = 0;
+        let result: Result<(), _> = retry_with_backoff(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(status(404))
+        });
+        assert_eq!(calls, 1);
+        assert!(matches!(
+            result,
+            Err(PackageVersionSearchError::UreqError(ref error))
+                if matches!(**error, ureq::Error::Status(404, _))
+        ));
+
+        let mut calls = 0;
+        let result: Result<(), _> = retry_with_backoff(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(PackageVersionSearchError::NotFound("nope".to_owned()))
+        });
+        assert_eq!(calls, 1);
+        assert!(matches!(
+            result,
+            Err(PackageVersionSearchError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_retry_server_errors() {
+        let mut calls = 0;
+        let result = retry_with_backoff(3, Duration::from_millis(1), || {
+            calls += 1;
+            match calls {
+                1 => Err(status(503)),
+                2 => Err(status(429)),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_rate_limiter_spacing() {
+        let rate_limiter = RateLimiter::new(50);
+        let start = Instant::now();
+        rate_limiter.wait();
+        let first = Instant::now();
+        rate_limiter.wait();
+        let second = Instant::now();
+
+        // the first request shouldn't have to wait at all.
+        assert!(first - start < Duration::from_millis(50));
+        assert!(second - first >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_rate_limiter_default() {
+        assert_eq!(RateLimiter::default().interval_ms, 500);
+    }
 }